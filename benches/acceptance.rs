@@ -0,0 +1,36 @@
+//! Time `AcceptanceCache` against `app_acceptance`, which asks Launch Services for every url.
+//!
+//! Run with `cargo bench --bench acceptance`.
+
+#[cfg(target_os = "macos")]
+fn main() {
+    use macos_open::{app_acceptance, AcceptanceCache};
+    use std::path::Path;
+    use std::time::Instant;
+
+    let safari = Path::new("/Applications/Safari.app");
+    let urls = (0..10_000)
+        .map(|i| match i % 3 {
+            0 => format!("https://www.example.com/{}", i),
+            1 => format!("file:///tmp/page-{}.html", i),
+            _ => format!("mailto:user{}@example.com", i),
+        })
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    let expected = app_acceptance(safari, &urls).expect("Safari is not installed");
+    println!("app_acceptance:  {:?}", start.elapsed());
+
+    let cache = AcceptanceCache::new();
+    let start = Instant::now();
+    let cached = urls
+        .iter()
+        .map(|url| cache.app_accept_url(safari, url.as_str()))
+        .collect::<Vec<_>>();
+    println!("AcceptanceCache: {:?}", start.elapsed());
+
+    assert_eq!(cached, expected);
+}
+
+#[cfg(not(target_os = "macos"))]
+fn main() {}
@@ -0,0 +1,57 @@
+/* C API of macos-open, exported by its dynamic library built with the `ffi` feature:
+ *
+ *     cargo build --release --features ffi
+ *
+ * Every function returns MACOS_OPEN_OK on success, one of the positive MACOS_OPEN_* codes on
+ * failure, or the negative OSStatus returned by the system. Strings passed in must be NUL
+ * terminated UTF-8 and are only borrowed for the duration of the call. Strings handed out are
+ * owned by the caller, who must free them with macos_open_string_free. */
+
+#ifndef MACOS_OPEN_H
+#define MACOS_OPEN_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The call succeeded */
+#define MACOS_OPEN_OK 0
+/* A pointer is null or a string isn't valid UTF-8 */
+#define MACOS_OPEN_INVALID_ARGUMENT 1
+/* The url or the app path can't be opened */
+#define MACOS_OPEN_INVALID_URL 2
+/* No app was found */
+#define MACOS_OPEN_APP_NOT_FOUND 3
+/* The app can't open the url */
+#define MACOS_OPEN_NOT_ACCEPTED 4
+/* The operation isn't supported on this system */
+#define MACOS_OPEN_UNSUPPORTED 5
+/* An I/O error */
+#define MACOS_OPEN_IO 6
+/* Any other error, see macos_open_last_error */
+#define MACOS_OPEN_OTHER 7
+/* The opener command exited with a failure status, see macos_open_last_error */
+#define MACOS_OPEN_EXIT_STATUS 8
+
+/* Open the url, or the file at the given path, with its default handler. The path of the app
+ * which opened it is stored in out_app unless it's null (null if it's unknown). */
+int macos_open_url(const char *url, char **out_app);
+
+/* Open the url with the app at the given path, failing with MACOS_OPEN_NOT_ACCEPTED if the app
+ * can't handle it. */
+int macos_open_with_app(const char *app, const char *url);
+
+/* Store the path of the default app handling the scheme (e.g. "https") in out_app. */
+int macos_default_app_for_scheme(const char *scheme, char **out_app);
+
+/* The message of the last error on the calling thread, null if there is none. */
+char *macos_open_last_error(void);
+
+/* Free a string handed out by this library, null is ignored. */
+void macos_open_string_free(char *value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MACOS_OPEN_H */
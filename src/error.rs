@@ -0,0 +1,279 @@
+//! Errors returned by this crate.
+
+use crate::OSStatus;
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use url::Url;
+
+/// Everything that can go wrong while looking up or opening something. With the `serde` feature
+/// it can be serialized (not deserialized), urls and io errors as strings.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum OpenError {
+    /// No installed app matches the given bundle identifier or name
+    AppNotFound(String),
+    /// The app exists but can't handle the provided urls
+    NotAccepted {
+        /// The bundle identifier, name or path of the app
+        app: String,
+        /// The urls the app can't handle
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_urls"))]
+        rejected: Vec<Url>,
+    },
+    /// The app isn't running
+    NotRunning(String),
+    /// The app is running but refused to come to the front
+    ActivationFailed(String),
+    /// The app is running but the request to quit couldn't be delivered
+    TerminationFailed(String),
+    /// The provided app path can't be transformed in a CFURL
+    InvalidApp,
+    /// The provided url can't be transformed in a CFURL
+    InvalidUrl,
+    /// Some of the provided urls can't be transformed in a CFURL
+    InvalidUrls,
+    /// The provided items at the given indices (with their debug representation) can't be
+    /// transformed in a CFURL
+    InvalidItems(Vec<(usize, String)>),
+    /// The input is both a valid url and an existing path, refused in strict mode
+    Ambiguous(String),
+    /// The Spotlight query couldn't be created or run
+    QueryFailed,
+    /// The launch or the wait took longer than the timeout
+    TimedOut,
+    /// The launch or the wait was cancelled with its `CancelToken`
+    Cancelled,
+    /// The thread running a launch bounded by a timeout or a cancel token panicked
+    LaunchPanicked,
+    /// A routing rule is invalid, e.g. its regular expression doesn't compile
+    InvalidRule(String),
+    /// The uniform type identifier has no known file extension
+    UnknownType(String),
+    /// The launch options contradict each other
+    IncompatibleOptions(&'static str),
+    /// The same urls were opened within the dedupe window of the opener
+    AlreadyOpened(String),
+    /// Some chunks of a chunked launch failed, the other ones were delivered
+    ChunksFailed {
+        /// How many chunks the urls were split in
+        chunks: usize,
+        /// The index of every failed chunk, with its error
+        failed: Vec<(usize, OpenError)>,
+    },
+    /// The volume is in use by an app and can't be ejected
+    Busy(String),
+    /// The url isn't allowed by the `OpenPolicy` of the opener
+    Denied(String),
+    /// Launch Services or a script returned an error code
+    Status(OSStatus),
+    /// A script failed without an error code, with its error message if any
+    ScriptFailed(String),
+    /// NSWorkspace reported an error
+    Workspace {
+        /// The NSError code
+        code: i64,
+        /// The localized description of the error
+        message: String,
+    },
+    /// The requested feature is not available with the selected backend or on this system
+    Unsupported(&'static str),
+    /// Spawning the app process failed
+    Io(#[cfg_attr(feature = "serde", serde(serialize_with = "serialize_display"))] io::Error),
+    /// Gatekeeper refuses to open the app or document
+    Blocked(String),
+    /// Gatekeeper refuses to open the app or document because it isn't notarized
+    Unnotarized(String),
+    /// The code signature of the app is broken or doesn't satisfy the requirement
+    InvalidSignature {
+        /// The path of the app
+        app: String,
+        /// The Security framework status code
+        status: OSStatus,
+    },
+    /// The app isn't signed by the required team
+    UntrustedTeam {
+        /// The path of the app
+        app: String,
+        /// The team which signed the app, if any
+        team_id: Option<String>,
+    },
+    /// The required team identifier isn't 10 uppercase letters or digits
+    InvalidTeamId(String),
+    /// Creating or resolving a bookmark failed
+    Bookmark {
+        /// The CFError code
+        code: i64,
+        /// The description of the error
+        message: String,
+    },
+    /// Checking or downloading an iCloud Drive or File Provider item failed
+    Download {
+        /// The error code
+        code: i64,
+        /// The description of the error
+        message: String,
+    },
+    /// Reading or writing the Finder tags failed
+    Tags {
+        /// The CFError code
+        code: i64,
+        /// The description of the error
+        message: String,
+    },
+}
+
+#[cfg(feature = "serde")]
+fn serialize_urls<S: serde::Serializer>(urls: &[Url], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(urls.iter().map(Url::as_str))
+}
+
+#[cfg(feature = "serde")]
+fn serialize_display<T: fmt::Display, S: serde::Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// A `Result` with `OpenError` as error
+pub type OpenResult<T> = Result<T, OpenError>;
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpenError::AppNotFound(app) => write!(f, "No app found for {}", app),
+            OpenError::NotAccepted { app, rejected } => {
+                write!(f, "{} can't open the provided urls", app)?;
+                for (index, url) in rejected.iter().enumerate() {
+                    f.write_str(if index == 0 { ": " } else { ", " })?;
+                    f.write_str(url.as_str())?;
+                }
+                Ok(())
+            }
+            OpenError::NotRunning(app) => write!(f, "{} is not running", app),
+            OpenError::ActivationFailed(app) => write!(f, "{} can't be activated", app),
+            OpenError::TerminationFailed(app) => write!(f, "{} can't be terminated", app),
+            OpenError::InvalidApp => f.write_str("Provided app url is not valid"),
+            OpenError::InvalidUrl => f.write_str("Provided url is not openable"),
+            OpenError::InvalidUrls => f.write_str("Provided urls are not valid"),
+            OpenError::InvalidItems(items) => {
+                f.write_str("Provided urls are not valid:")?;
+                for (index, item) in items {
+                    write!(f, " #{} {}", index, item)?;
+                }
+                Ok(())
+            }
+            OpenError::Ambiguous(input) => {
+                write!(f, "{} is both a valid url and an existing path", input)
+            }
+            OpenError::QueryFailed => f.write_str("Spotlight query failed"),
+            OpenError::TimedOut => f.write_str("Timed out"),
+            OpenError::Cancelled => f.write_str("Cancelled"),
+            OpenError::LaunchPanicked => f.write_str("The launch thread panicked"),
+            OpenError::InvalidRule(message) => write!(f, "Invalid routing rule: {}", message),
+            OpenError::UnknownType(uti) => write!(f, "No file extension known for {}", uti),
+            OpenError::IncompatibleOptions(options) => {
+                write!(f, "Incompatible launch options: {}", options)
+            }
+            OpenError::AlreadyOpened(urls) => write!(f, "{} already opened", urls),
+            OpenError::ChunksFailed { chunks, failed } => {
+                write!(f, "{} of {} chunks failed:", failed.len(), chunks)?;
+                for (index, err) in failed {
+                    write!(f, " #{} {}", index, err)?;
+                }
+                Ok(())
+            }
+            OpenError::Busy(volume) => write!(f, "{} is in use", volume),
+            OpenError::Denied(url) => write!(f, "{} is denied by the open policy", url),
+            OpenError::Status(code) => write!(f, "return code {}", code),
+            OpenError::ScriptFailed(message) if message.is_empty() => {
+                f.write_str("The script failed")
+            }
+            OpenError::ScriptFailed(message) => write!(f, "The script failed: {}", message),
+            OpenError::Workspace { code, message } => write!(f, "{} (code {})", message, code),
+            OpenError::Unsupported(what) => write!(f, "Unsupported: {}", what),
+            OpenError::Io(err) => err.fmt(f),
+            OpenError::Blocked(item) => write!(f, "{} is blocked by Gatekeeper", item),
+            OpenError::Unnotarized(item) => write!(f, "{} is not notarized", item),
+            OpenError::InvalidSignature { app, status } => {
+                write!(f, "{} has an invalid signature (code {})", app, status)
+            }
+            OpenError::UntrustedTeam { app, team_id } => match team_id {
+                Some(team_id) => write!(f, "{} is signed by the untrusted team {}", app, team_id),
+                None => write!(f, "{} is not signed by a team", app),
+            },
+            OpenError::InvalidTeamId(team_id) => {
+                write!(f, "{} is not a valid team identifier", team_id)
+            }
+            OpenError::Bookmark { code, message } => {
+                write!(f, "Bookmark error: {} (code {})", message, code)
+            }
+            OpenError::Download { code, message } => {
+                write!(f, "Download error: {} (code {})", message, code)
+            }
+            OpenError::Tags { code, message } => {
+                write!(f, "Tags error: {} (code {})", message, code)
+            }
+        }
+    }
+}
+
+impl StdError for OpenError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            OpenError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<OpenError> for io::Error {
+    fn from(err: OpenError) -> io::Error {
+        let kind = match err {
+            OpenError::Io(err) => return err,
+            OpenError::AppNotFound(_) => io::ErrorKind::NotFound,
+            OpenError::NotAccepted { .. }
+            | OpenError::Ambiguous(_)
+            | OpenError::IncompatibleOptions(_)
+            | OpenError::InvalidTeamId(_)
+            | OpenError::UnknownType(_) => io::ErrorKind::InvalidInput,
+            OpenError::InvalidRule(_) => io::ErrorKind::InvalidInput,
+            OpenError::NotRunning(_) => io::ErrorKind::NotFound,
+            OpenError::AlreadyOpened(_) => io::ErrorKind::AlreadyExists,
+            OpenError::TimedOut => io::ErrorKind::TimedOut,
+            OpenError::Cancelled => io::ErrorKind::Interrupted,
+            OpenError::Denied(_)
+            | OpenError::Blocked(_)
+            | OpenError::Unnotarized(_)
+            | OpenError::InvalidSignature { .. }
+            | OpenError::UntrustedTeam { .. } => io::ErrorKind::PermissionDenied,
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_error_serde() {
+        let not_accepted = OpenError::NotAccepted {
+            app: "Preview".to_string(),
+            rejected: vec![Url::parse("https://www.example.com/").unwrap()],
+        };
+        assert_eq!(
+            serde_json::to_string(&not_accepted).unwrap(),
+            "{\"NotAccepted\":{\"app\":\"Preview\",\"rejected\":[\"https://www.example.com/\"]}}"
+        );
+        let io = OpenError::Io(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_eq!(serde_json::to_string(&io).unwrap(), "{\"Io\":\"gone\"}");
+        assert_eq!(
+            serde_json::to_string(&OpenError::TimedOut).unwrap(),
+            "\"TimedOut\""
+        );
+    }
+}
@@ -0,0 +1,615 @@
+//! The portable subset of the API, for crates also built on other systems: lookups find nothing
+//! and opening fails with `OpenError::Unsupported`, unless the `fallback` feature is enabled and
+//! urls are opened with `xdg-open` (`rundll32 url.dll,FileProtocolHandler` on Windows).
+
+use crate::{OpenError, OpenResult};
+
+use std::ffi::OsString;
+use std::io::Result;
+use std::ops::{BitOr, BitOrAssign};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The status code returned by macOS APIs
+pub type OSStatus = i32;
+
+/// The Launch Services launch flags, accepted and ignored by the system opener
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LSLaunchFlags(u32);
+
+impl LSLaunchFlags {
+    /// kLSLaunchDefaults
+    pub const DEFAULTS: LSLaunchFlags = LSLaunchFlags(0x0000_0001);
+    /// kLSLaunchAndPrint
+    pub const AND_PRINT: LSLaunchFlags = LSLaunchFlags(0x0000_0002);
+    /// kLSLaunchAndDisplayErrors
+    pub const AND_DISPLAY_ERRORS: LSLaunchFlags = LSLaunchFlags(0x0000_0040);
+    /// kLSLaunchDontAddToRecents
+    pub const DONT_ADD_TO_RECENTS: LSLaunchFlags = LSLaunchFlags(0x0000_0100);
+    /// kLSLaunchDontSwitch
+    pub const DONT_SWITCH: LSLaunchFlags = LSLaunchFlags(0x0000_0200);
+    /// kLSLaunchAsync
+    pub const ASYNC: LSLaunchFlags = LSLaunchFlags(0x0001_0000);
+    /// kLSLaunchNewInstance
+    pub const NEW_INSTANCE: LSLaunchFlags = LSLaunchFlags(0x0008_0000);
+    /// kLSLaunchAndHide
+    pub const AND_HIDE: LSLaunchFlags = LSLaunchFlags(0x0010_0000);
+    /// kLSLaunchAndHideOthers
+    pub const AND_HIDE_OTHERS: LSLaunchFlags = LSLaunchFlags(0x0020_0000);
+
+    /// The raw value of the flags
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// The flags of the raw value
+    pub fn from_bits_truncate(bits: u32) -> LSLaunchFlags {
+        LSLaunchFlags(bits)
+    }
+
+    /// Check if all the given flags are set
+    pub fn contains(self, other: LSLaunchFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for LSLaunchFlags {
+    type Output = LSLaunchFlags;
+
+    fn bitor(self, other: LSLaunchFlags) -> LSLaunchFlags {
+        LSLaunchFlags(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for LSLaunchFlags {
+    fn bitor_assign(&mut self, other: LSLaunchFlags) {
+        self.0 |= other.0;
+    }
+}
+
+#[cfg(not(feature = "fallback"))]
+const UNSUPPORTED: &str = "opening urls requires macOS or the fallback feature";
+
+/// A type implementing this trait can be passed to the system opener.
+pub trait Openable {
+    /// Transform this type in the argument of the system opener
+    #[allow(clippy::wrong_self_convention)] // the same name of the macOS trait
+    fn into_openable(&self) -> Option<OsString>;
+}
+
+/// Urls are passed as arguments, the ones starting with `-` would be taken for options
+fn url(value: &str) -> Option<OsString> {
+    if value.is_empty() || value.starts_with('-') {
+        None
+    } else {
+        Some(OsString::from(value))
+    }
+}
+
+/// Relative paths are resolved against the current directory, the opener may run elsewhere
+fn path(value: &Path) -> Option<OsString> {
+    if value.as_os_str().is_empty() {
+        None
+    } else if value.is_relative() {
+        Some(std::env::current_dir().ok()?.join(value).into_os_string())
+    } else {
+        Some(value.as_os_str().to_os_string())
+    }
+}
+
+impl Openable for &str {
+    fn into_openable(&self) -> Option<OsString> {
+        url(self)
+    }
+}
+
+impl Openable for str {
+    fn into_openable(&self) -> Option<OsString> {
+        url(self)
+    }
+}
+
+impl Openable for &String {
+    fn into_openable(&self) -> Option<OsString> {
+        url(self)
+    }
+}
+
+impl Openable for String {
+    fn into_openable(&self) -> Option<OsString> {
+        url(self)
+    }
+}
+
+impl Openable for &Path {
+    fn into_openable(&self) -> Option<OsString> {
+        path(self)
+    }
+}
+
+impl Openable for Path {
+    fn into_openable(&self) -> Option<OsString> {
+        path(self)
+    }
+}
+
+impl Openable for &PathBuf {
+    fn into_openable(&self) -> Option<OsString> {
+        path(self)
+    }
+}
+
+impl Openable for PathBuf {
+    fn into_openable(&self) -> Option<OsString> {
+        path(self)
+    }
+}
+
+/// A type implementing this trait can be passed, item by item, to the system opener.
+pub trait MultiOpenable {
+    /// Transform this type in the arguments of the system opener
+    #[allow(clippy::wrong_self_convention)] // the same name of the macOS trait
+    fn into_openable(&self) -> Option<Vec<OsString>>;
+
+    /// The index and the debug representation of the items which can't be opened
+    fn invalid_items(&self) -> Vec<(usize, String)> {
+        Vec::new()
+    }
+}
+
+fn invalid_items<'a, T, I>(items: I) -> Vec<(usize, String)>
+where
+    T: Openable + std::fmt::Debug + ?Sized + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(_, item)| Openable::into_openable(*item).is_none())
+        .map(|(index, item)| (index, format!("{:?}", item)))
+        .collect()
+}
+
+macro_rules! def_multiopenable_vec {
+    ( $type:ty ) => {
+        impl MultiOpenable for Vec<$type> {
+            fn into_openable(&self) -> Option<Vec<OsString>> {
+                self[..].into_openable()
+            }
+
+            fn invalid_items(&self) -> Vec<(usize, String)> {
+                invalid_items(self.iter())
+            }
+        }
+
+        impl MultiOpenable for &[$type] {
+            fn into_openable(&self) -> Option<Vec<OsString>> {
+                (*self).into_openable()
+            }
+
+            fn invalid_items(&self) -> Vec<(usize, String)> {
+                invalid_items(self.iter())
+            }
+        }
+
+        impl MultiOpenable for [$type] {
+            fn into_openable(&self) -> Option<Vec<OsString>> {
+                self.iter().map(Openable::into_openable).collect()
+            }
+
+            fn invalid_items(&self) -> Vec<(usize, String)> {
+                invalid_items(self.iter())
+            }
+        }
+    };
+}
+
+macro_rules! def_multiopenable_type {
+    ( $type:ty ) => {
+        impl MultiOpenable for $type {
+            fn into_openable(&self) -> Option<Vec<OsString>> {
+                Some(vec![Openable::into_openable(self)?])
+            }
+
+            fn invalid_items(&self) -> Vec<(usize, String)> {
+                invalid_items(std::iter::once(self))
+            }
+        }
+    };
+}
+
+macro_rules! def_multiopenable {
+    ( $type:ty ) => {
+        def_multiopenable_vec!($type);
+        def_multiopenable_type!($type);
+    };
+}
+
+def_multiopenable!(&str);
+def_multiopenable_vec!(&String);
+def_multiopenable_type!(str);
+def_multiopenable!(String);
+def_multiopenable!(&Path);
+def_multiopenable_vec!(&PathBuf);
+def_multiopenable_type!(Path);
+def_multiopenable!(PathBuf);
+
+fn remap_multiopenable<T: MultiOpenable + ?Sized>(urls: &T) -> OpenResult<Vec<OsString>> {
+    match urls.into_openable() {
+        Some(urls) => Ok(urls),
+        None => match urls.invalid_items() {
+            ref invalid if invalid.is_empty() => Err(OpenError::InvalidUrls),
+            invalid => Err(OpenError::InvalidItems(invalid)),
+        },
+    }
+}
+
+/// Hand the argument to the system opener and wait for it to exit
+#[cfg(feature = "fallback")]
+fn system_open(arg: OsString) -> OpenResult<()> {
+    use std::process::Command;
+
+    // unlike `cmd /C start`, no shell parses the url (e.g. its `&`)
+    #[cfg(windows)]
+    let status = Command::new("rundll32")
+        .arg("url.dll,FileProtocolHandler")
+        .arg(arg)
+        .status();
+    #[cfg(not(windows))]
+    let status = Command::new("xdg-open").arg(arg).status();
+
+    match status.map_err(OpenError::Io)?.code() {
+        Some(0) => Ok(()),
+        Some(code) => Err(OpenError::Status(code)),
+        None => Err(OpenError::Unsupported("the system opener was killed")),
+    }
+}
+
+#[cfg(not(feature = "fallback"))]
+fn system_open(_arg: OsString) -> OpenResult<()> {
+    Err(OpenError::Unsupported(UNSUPPORTED))
+}
+
+/// Open an Openable value with the system opener. The handler is unknown, so the path is
+/// always `None`.
+pub fn open<T: Openable + ?Sized>(url: &T) -> Result<Option<PathBuf>> {
+    let url = Openable::into_openable(url).ok_or(OpenError::InvalidUrl)?;
+    system_open(url)?;
+    Ok(None)
+}
+
+/// Open the urls with the given app, by running it with the urls as arguments
+#[cfg(feature = "fallback")]
+pub fn open_with<T: MultiOpenable + ?Sized>(app: &Path, urls: &T) -> OpenResult<Option<PathBuf>> {
+    let urls = remap_multiopenable(urls)?;
+    Ok(spawn(app, urls)?.path)
+}
+
+/// Open the urls with the given app, unsupported without the `fallback` feature
+#[cfg(not(feature = "fallback"))]
+pub fn open_with<T: MultiOpenable + ?Sized>(_app: &Path, urls: &T) -> OpenResult<Option<PathBuf>> {
+    remap_multiopenable(urls)?;
+    Err(OpenError::Unsupported(UNSUPPORTED))
+}
+
+/// Run the app with the arguments
+#[cfg(feature = "fallback")]
+fn spawn(app: &Path, args: Vec<OsString>) -> OpenResult<LaunchedApp> {
+    let mut child = std::process::Command::new(app)
+        .args(args)
+        .spawn()
+        .map_err(OpenError::Io)?;
+    let pid = child.id() as i32;
+    // reap the child so it doesn't linger as a zombie once it exits
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(LaunchedApp {
+        path: Some(app.to_path_buf()),
+        pid: Some(pid),
+    })
+}
+
+#[cfg(not(feature = "fallback"))]
+fn spawn(_app: &Path, _args: Vec<OsString>) -> OpenResult<LaunchedApp> {
+    Err(OpenError::Unsupported(UNSUPPORTED))
+}
+
+/// Open the app if no urls provided, open the urls in app if both provided and open urls in
+/// the system opener if no app is provided. The flags are ignored.
+pub fn open_complex<T: MultiOpenable + ?Sized>(
+    app: Option<&Path>,
+    urls: Option<&T>,
+    flags: LSLaunchFlags,
+) -> Result<Option<PathBuf>> {
+    let mut options = OpenOptions::new();
+    options.flags(flags);
+    if let Some(app) = app {
+        options.app(app);
+    }
+    let launched = match urls {
+        Some(urls) => options.open(urls)?,
+        None => options.launch()?,
+    };
+    Ok(launched.into_path())
+}
+
+/// Launch Services isn't available, always `OpenError::Unsupported` once the urls are checked
+pub fn open_with_bundle_id<T: MultiOpenable + ?Sized>(
+    _bundle_id: &str,
+    urls: &T,
+    _flags: LSLaunchFlags,
+) -> OpenResult<Option<PathBuf>> {
+    remap_multiopenable(urls)?;
+    Err(OpenError::Unsupported("Launch Services is not available"))
+}
+
+/// Spotlight isn't available, always `OpenError::Unsupported` once the urls are checked
+pub fn open_with_name<T: MultiOpenable + ?Sized>(
+    _name: &str,
+    urls: &T,
+    _flags: LSLaunchFlags,
+) -> OpenResult<Option<PathBuf>> {
+    remap_multiopenable(urls)?;
+    Err(OpenError::Unsupported("Spotlight is not available"))
+}
+
+/// An input to open, telling how it must be interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpenTarget<'a> {
+    /// A url, handed to the system opener as it is
+    Url(&'a str),
+    /// A file system path
+    Path(&'a Path),
+    /// Guess between a url and a path, the system opener takes both
+    Auto(&'a str),
+}
+
+impl<'a> Openable for OpenTarget<'a> {
+    fn into_openable(&self) -> Option<OsString> {
+        match *self {
+            OpenTarget::Url(value) | OpenTarget::Auto(value) => url(value),
+            OpenTarget::Path(value) => path(value),
+        }
+    }
+}
+
+def_multiopenable!(OpenTarget<'_>);
+
+/// The app which received the opened urls, only known when an app is given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchedApp {
+    path: Option<PathBuf>,
+    pid: Option<i32>,
+}
+
+impl LaunchedApp {
+    /// The pid of the app, if it was spawned
+    pub fn pid(&self) -> Option<i32> {
+        self.pid
+    }
+
+    /// The path of the app, if known
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Take the path of the app, if known
+    pub fn into_path(self) -> Option<PathBuf> {
+        self.path
+    }
+}
+
+/// The options of the macOS launches. The app and its arguments are honoured, the other
+/// options are accepted and ignored by the system opener.
+#[derive(Debug, Clone)]
+pub struct OpenOptions {
+    app: Option<PathBuf>,
+    flags: LSLaunchFlags,
+    args: Vec<String>,
+}
+
+impl Default for OpenOptions {
+    fn default() -> OpenOptions {
+        OpenOptions::new()
+    }
+}
+
+impl OpenOptions {
+    /// Create a blank set of options: urls are opened with the system opener
+    pub fn new() -> OpenOptions {
+        OpenOptions {
+            app: None,
+            flags: LSLaunchFlags::DEFAULTS,
+            args: Vec::new(),
+        }
+    }
+
+    /// Open urls by running the app at the given path with the urls as arguments
+    pub fn app<P: Into<PathBuf>>(&mut self, app: P) -> &mut OpenOptions {
+        self.app = Some(app.into());
+        self
+    }
+
+    /// Set the Launch Services flags, ignored
+    pub fn flags(&mut self, flags: LSLaunchFlags) -> &mut OpenOptions {
+        self.flags = flags;
+        self
+    }
+
+    /// Pass an argument to the app, before the urls
+    pub fn arg<S: Into<String>>(&mut self, arg: S) -> &mut OpenOptions {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Pass arguments to the app, before the urls
+    pub fn args<I, S>(&mut self, args: I) -> &mut OpenOptions
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Set the timeout of the launch, ignored
+    pub fn timeout(&mut self, _timeout: Duration) -> &mut OpenOptions {
+        self
+    }
+
+    /// Open the urls with these options: with an app it's run with the urls as arguments,
+    /// otherwise every url is handed to the system opener
+    pub fn open<T: MultiOpenable + ?Sized>(&self, urls: &T) -> OpenResult<LaunchedApp> {
+        let urls = remap_multiopenable(urls)?;
+        match self.app {
+            Some(ref app) => {
+                let mut args = self.args.iter().map(OsString::from).collect::<Vec<_>>();
+                args.extend(urls);
+                spawn(app, args)
+            }
+            None => {
+                for url in urls {
+                    system_open(url)?;
+                }
+                Ok(LaunchedApp {
+                    path: None,
+                    pid: None,
+                })
+            }
+        }
+    }
+
+    /// Run the configured app without any url
+    pub fn launch(&self) -> OpenResult<LaunchedApp> {
+        match self.app {
+            Some(ref app) => spawn(app, self.args.iter().map(OsString::from).collect()),
+            None => Err(OpenError::InvalidApp),
+        }
+    }
+}
+
+/// Show the item in the file manager, by opening its parent folder
+pub fn reveal(path: &Path) -> OpenResult<()> {
+    let parent = match path.parent() {
+        // a bare file name is in the current folder
+        Some(parent) if parent.as_os_str().is_empty() => Path::new("."),
+        Some(parent) => parent,
+        None => path,
+    };
+    system_open(Openable::into_openable(parent).ok_or(OpenError::InvalidUrl)?)
+}
+
+/// Launch Services isn't available: no app is ever found
+pub fn apps_for_scheme(_scheme: &str) -> Option<Vec<PathBuf>> {
+    None
+}
+
+/// Launch Services isn't available: no app is ever found
+pub fn app_for_scheme(_scheme: &str) -> Option<PathBuf> {
+    None
+}
+
+/// Launch Services isn't available, always `OpenError::Unsupported`
+pub fn try_apps_for_scheme(_scheme: &str) -> OpenResult<Vec<PathBuf>> {
+    Err(OpenError::Unsupported("Launch Services is not available"))
+}
+
+/// Launch Services isn't available: no app is ever found
+pub fn apps_for_bundle_id(_bundle_id: &str) -> Option<Vec<PathBuf>> {
+    None
+}
+
+/// Launch Services isn't available: no app is ever found
+pub fn app_for_bundle_id(_bundle_id: &str) -> Option<PathBuf> {
+    None
+}
+
+/// Launch Services isn't available, always `OpenError::Unsupported`
+pub fn try_apps_for_bundle_id(_bundle_id: &str) -> OpenResult<Vec<PathBuf>> {
+    Err(OpenError::Unsupported("Launch Services is not available"))
+}
+
+/// Spotlight isn't available: no app is ever found
+pub fn apps_for_name(_app_name: &str) -> Option<Vec<PathBuf>> {
+    None
+}
+
+/// Spotlight isn't available: no app is ever found
+pub fn app_for_name(_name: &str) -> Option<PathBuf> {
+    None
+}
+
+/// Spotlight isn't available, always `OpenError::Unsupported`
+pub fn try_apps_for_name(_app_name: &str) -> OpenResult<Vec<PathBuf>> {
+    Err(OpenError::Unsupported("Spotlight is not available"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_multiopenable() {
+        assert_eq!(
+            remap_multiopenable(&["https://www.example.com/", "/tmp"][..]).unwrap(),
+            vec![
+                OsString::from("https://www.example.com/"),
+                OsString::from("/tmp")
+            ]
+        );
+        match remap_multiopenable(&vec!["/tmp", "", "--load-extension=/tmp/x"]) {
+            Err(OpenError::InvalidItems(ref items)) => assert_eq!(
+                items,
+                &[
+                    (1, "\"\"".to_string()),
+                    (2, "\"--load-extension=/tmp/x\"".to_string())
+                ]
+            ),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(app_for_scheme("https").is_none());
+    }
+
+    #[cfg(not(feature = "fallback"))]
+    #[test]
+    fn test_fallback_unsupported() {
+        match open_with(Path::new("/usr/bin/true"), "https://www.example.com/") {
+            Err(OpenError::Unsupported(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(open_complex(
+            None,
+            Some(&OpenTarget::Url("https://www.example.com/")),
+            LSLaunchFlags::DEFAULTS,
+        )
+        .is_err());
+        match OpenOptions::new().app("/usr/bin/true").launch() {
+            Err(OpenError::Unsupported(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "fallback")]
+    #[test]
+    fn test_fallback_open_options() {
+        let launched = OpenOptions::new()
+            .app("/usr/bin/true")
+            .arg("--")
+            .open(&OpenTarget::Path(Path::new("/tmp")))
+            .unwrap();
+        assert_eq!(launched.path(), Some(Path::new("/usr/bin/true")));
+        assert!(launched.pid().is_some());
+
+        // the exited child is reaped instead of lingering as a zombie
+        #[cfg(target_os = "linux")]
+        {
+            let process = PathBuf::from(format!("/proc/{}", launched.pid().unwrap()));
+            let start = std::time::Instant::now();
+            while process.exists() && start.elapsed() < Duration::from_secs(5) {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            assert!(!process.exists());
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! C API, enabled by the `ffi` feature.
+//!
+//! Every function returns `MACOS_OPEN_OK` on success, one of the positive `MACOS_OPEN_*` codes
+//! on failure, or the negative OSStatus returned by the system. Strings passed in must be NUL
+//! terminated UTF-8 and are only borrowed for the duration of the call. Strings handed out
+//! (through `out_*` parameters or by `macos_open_last_error`) are owned by the caller, who must
+//! free them with `macos_open_string_free`.
+//!
+//! The declarations are in `include/macos_open.h`. The crate is also built as a cdylib, which
+//! only exports these functions with the `ffi` feature.
+
+use crate::{app_for_scheme, open, open_with, OpenError};
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::io;
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+/// The call succeeded
+pub const MACOS_OPEN_OK: i32 = 0;
+/// A pointer is null or a string isn't valid UTF-8
+pub const MACOS_OPEN_INVALID_ARGUMENT: i32 = 1;
+/// The url or the app path can't be opened
+pub const MACOS_OPEN_INVALID_URL: i32 = 2;
+/// No app was found
+pub const MACOS_OPEN_APP_NOT_FOUND: i32 = 3;
+/// The app can't open the url
+pub const MACOS_OPEN_NOT_ACCEPTED: i32 = 4;
+/// The operation isn't supported on this system
+pub const MACOS_OPEN_UNSUPPORTED: i32 = 5;
+/// An I/O error
+pub const MACOS_OPEN_IO: i32 = 6;
+/// Any other error, see `macos_open_last_error`
+pub const MACOS_OPEN_OTHER: i32 = 7;
+/// The opener command exited with a failure status, see `macos_open_last_error`
+pub const MACOS_OPEN_EXIT_STATUS: i32 = 8;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn error_code(err: &OpenError) -> i32 {
+    match err {
+        OpenError::Status(code) if *code < 0 => *code,
+        // the fallback reports the exit status of xdg-open, which would collide with ours
+        OpenError::Status(_) => MACOS_OPEN_EXIT_STATUS,
+        OpenError::InvalidApp
+        | OpenError::InvalidUrl
+        | OpenError::InvalidUrls
+        | OpenError::InvalidItems(_)
+        | OpenError::Ambiguous(_) => MACOS_OPEN_INVALID_URL,
+        OpenError::AppNotFound(_) => MACOS_OPEN_APP_NOT_FOUND,
+        OpenError::NotAccepted { .. } => MACOS_OPEN_NOT_ACCEPTED,
+        OpenError::Unsupported(_) => MACOS_OPEN_UNSUPPORTED,
+        OpenError::Io(_) => MACOS_OPEN_IO,
+        _ => MACOS_OPEN_OTHER,
+    }
+}
+
+/// Record the error for `macos_open_last_error` and return its code
+fn fail(err: OpenError) -> i32 {
+    let code = error_code(&err);
+    let message = CString::new(err.to_string().replace('\0', "")).ok();
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    code
+}
+
+/// `open` reports errors through `io::Error`, get the original one back
+fn from_io(err: io::Error) -> OpenError {
+    match err.get_ref() {
+        Some(inner) if inner.is::<OpenError>() => {
+            *err.into_inner().unwrap().downcast::<OpenError>().unwrap()
+        }
+        _ => OpenError::Io(err),
+    }
+}
+
+fn invalid_argument(message: String) -> i32 {
+    fail(OpenError::Io(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        message,
+    )));
+    MACOS_OPEN_INVALID_ARGUMENT
+}
+
+unsafe fn borrow_str<'a>(value: *const c_char) -> Result<&'a str, i32> {
+    if value.is_null() {
+        return Err(invalid_argument("null pointer".to_string()));
+    }
+    CStr::from_ptr(value)
+        .to_str()
+        .map_err(|err| invalid_argument(err.to_string()))
+}
+
+/// Store the path in `out`, if the caller asked for it
+unsafe fn give_path(path: Option<PathBuf>, out: *mut *mut c_char) {
+    if out.is_null() {
+        return;
+    }
+    *out = path
+        .and_then(|path| CString::new(path.to_string_lossy().into_owned()).ok())
+        .map_or(ptr::null_mut(), CString::into_raw);
+}
+
+/// Open the url, or the file at the given path, with its default handler. The path of the app
+/// which opened it is stored in `out_app` unless it's null (null if it's unknown).
+///
+/// # Safety
+///
+/// `url` must be a valid NUL terminated string, `out_app` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn macos_open_url(url: *const c_char, out_app: *mut *mut c_char) -> i32 {
+    let url = match borrow_str(url) {
+        Ok(url) => url,
+        Err(code) => return code,
+    };
+    match open(url) {
+        Ok(app) => {
+            give_path(app, out_app);
+            MACOS_OPEN_OK
+        }
+        Err(err) => fail(from_io(err)),
+    }
+}
+
+/// Open the url with the app at the given path, failing with `MACOS_OPEN_NOT_ACCEPTED` if the
+/// app can't handle it.
+///
+/// # Safety
+///
+/// `app` and `url` must be valid NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn macos_open_with_app(app: *const c_char, url: *const c_char) -> i32 {
+    let (app, url) = match (borrow_str(app), borrow_str(url)) {
+        (Ok(app), Ok(url)) => (app, url),
+        (Err(code), _) | (_, Err(code)) => return code,
+    };
+    match open_with(Path::new(app), url) {
+        Ok(_) => MACOS_OPEN_OK,
+        Err(err) => fail(err),
+    }
+}
+
+/// Store the path of the default app handling the scheme (e.g. `https`) in `out_app`.
+///
+/// # Safety
+///
+/// `scheme` must be a valid NUL terminated string, `out_app` valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn macos_default_app_for_scheme(
+    scheme: *const c_char,
+    out_app: *mut *mut c_char,
+) -> i32 {
+    let scheme = match borrow_str(scheme) {
+        Ok(scheme) => scheme,
+        Err(code) => return code,
+    };
+    if out_app.is_null() {
+        return invalid_argument("null pointer".to_string());
+    }
+    match app_for_scheme(scheme) {
+        Some(app) => {
+            give_path(Some(app), out_app);
+            MACOS_OPEN_OK
+        }
+        None => {
+            *out_app = ptr::null_mut();
+            fail(OpenError::AppNotFound(scheme.to_string()))
+        }
+    }
+}
+
+/// The message of the last error on the calling thread, null if there is none.
+#[no_mangle]
+pub extern "C" fn macos_open_last_error() -> *mut c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null_mut(), |message| message.clone().into_raw())
+    })
+}
+
+/// Free a string handed out by this library, null is ignored.
+///
+/// # Safety
+///
+/// `value` must be null or a string returned by this library, not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn macos_open_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffi_invalid_arguments() {
+        unsafe {
+            assert_eq!(
+                macos_open_url(ptr::null(), ptr::null_mut()),
+                MACOS_OPEN_INVALID_ARGUMENT
+            );
+            let message = macos_open_last_error();
+            assert_eq!(CStr::from_ptr(message).to_str().unwrap(), "null pointer");
+            macos_open_string_free(message);
+
+            let scheme = CString::new("https").unwrap();
+            assert_eq!(
+                macos_default_app_for_scheme(scheme.as_ptr(), ptr::null_mut()),
+                MACOS_OPEN_INVALID_ARGUMENT
+            );
+        }
+    }
+
+    #[test]
+    fn test_ffi_error_codes() {
+        assert_eq!(error_code(&OpenError::Status(-43)), -43);
+        assert_eq!(error_code(&OpenError::Status(2)), MACOS_OPEN_EXIT_STATUS);
+        assert_eq!(
+            error_code(&OpenError::AppNotFound("Safari".to_string())),
+            MACOS_OPEN_APP_NOT_FOUND
+        );
+        assert_eq!(error_code(&OpenError::QueryFailed), MACOS_OPEN_OTHER);
+        match from_io(OpenError::InvalidUrl.into()) {
+            OpenError::InvalidUrl => (),
+            other => panic!("unexpected error {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ffi_header() {
+        let header = include_str!("../include/macos_open.h");
+        for (name, code) in &[
+            ("MACOS_OPEN_OK", MACOS_OPEN_OK),
+            ("MACOS_OPEN_INVALID_ARGUMENT", MACOS_OPEN_INVALID_ARGUMENT),
+            ("MACOS_OPEN_INVALID_URL", MACOS_OPEN_INVALID_URL),
+            ("MACOS_OPEN_APP_NOT_FOUND", MACOS_OPEN_APP_NOT_FOUND),
+            ("MACOS_OPEN_NOT_ACCEPTED", MACOS_OPEN_NOT_ACCEPTED),
+            ("MACOS_OPEN_UNSUPPORTED", MACOS_OPEN_UNSUPPORTED),
+            ("MACOS_OPEN_IO", MACOS_OPEN_IO),
+            ("MACOS_OPEN_OTHER", MACOS_OPEN_OTHER),
+            ("MACOS_OPEN_EXIT_STATUS", MACOS_OPEN_EXIT_STATUS),
+        ] {
+            assert!(header.contains(&format!("#define {} {}\n", name, code)));
+        }
+        for function in &[
+            "macos_open_url(",
+            "macos_open_with_app(",
+            "macos_default_app_for_scheme(",
+            "macos_open_last_error(",
+            "macos_open_string_free(",
+        ] {
+            assert!(header.contains(function));
+        }
+    }
+}
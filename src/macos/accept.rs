@@ -0,0 +1,165 @@
+//! Parallel acceptance checks for large batches of urls.
+
+use crate::macos::MultiOpenable;
+
+use core_foundation::array::CFArray;
+use core_foundation::base::CFIndex;
+use core_foundation::url::CFURL;
+use launch_services::{can_url_accept_url, LSAcceptanceFlags, LSRolesMask};
+
+use url::Url;
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Batches smaller than this are checked on the calling thread
+const PARALLEL_THRESHOLD: usize = 8;
+const MAX_WORKERS: usize = 4;
+
+/// The app and the urls, shared with the workers
+struct Batch {
+    app: CFURL,
+    urls: CFArray<CFURL>,
+}
+
+// CFURL and CFArray are immutable and `LSCanURLAcceptURL` is thread-safe
+unsafe impl Send for Batch {}
+unsafe impl Sync for Batch {}
+
+impl Batch {
+    fn accepts(&self, index: usize) -> bool {
+        match self.urls.get(index as CFIndex) {
+            Some(url) => can_url_accept_url(
+                &url,
+                &self.app,
+                LSRolesMask::VIEWER,
+                LSAcceptanceFlags::DEFAULT,
+            )
+            .unwrap_or(false),
+            None => false,
+        }
+    }
+}
+
+/// Check every url, stopping at the first rejection with `early_exit`. Urls which weren't
+/// checked because of an early exit are `None`.
+fn check(app: CFURL, urls: CFArray<CFURL>, early_exit: bool) -> Vec<Option<bool>> {
+    let len = urls.len() as usize;
+    let batch = Batch { app, urls };
+
+    if len < PARALLEL_THRESHOLD {
+        let mut res = vec![None; len];
+        for (index, slot) in res.iter_mut().enumerate() {
+            let accepted = batch.accepts(index);
+            *slot = Some(accepted);
+            if early_exit && !accepted {
+                break;
+            }
+        }
+        return res;
+    }
+
+    let batch = Arc::new(batch);
+    let next = Arc::new(AtomicUsize::new(0));
+    let rejected = Arc::new(AtomicBool::new(false));
+    let res = Arc::new(Mutex::new(vec![None; len]));
+
+    let workers = (0..MAX_WORKERS.min(len))
+        .map(|_| {
+            let (batch, next, rejected, res) =
+                (batch.clone(), next.clone(), rejected.clone(), res.clone());
+            thread::spawn(move || loop {
+                if early_exit && rejected.load(Ordering::SeqCst) {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                if index >= len {
+                    break;
+                }
+                let accepted = batch.accepts(index);
+                if !accepted {
+                    rejected.store(true, Ordering::SeqCst);
+                }
+                res.lock().unwrap()[index] = Some(accepted);
+            })
+        })
+        .collect::<Vec<_>>();
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    let res = res.lock().unwrap().clone();
+    res
+}
+
+fn remap<T: MultiOpenable + ?Sized>(app: &Path, urls: &T) -> Option<(CFURL, CFArray<CFURL>)> {
+    Some((
+        CFURL::from_path(app, true)?,
+        MultiOpenable::into_openable(urls)?,
+    ))
+}
+
+/// Check if the app can handle all the given urls, in parallel for large batches and stopping
+/// at the first url the app can't handle
+pub(crate) fn all_accepted<T: MultiOpenable + ?Sized>(app: &Path, urls: &T) -> bool {
+    match remap(app, urls) {
+        Some((app, urls)) => check(app, urls, true)
+            .into_iter()
+            .all(|accepted| accepted == Some(true)),
+        None => false,
+    }
+}
+
+/// Get the urls the app can't handle, none if the app or the urls are not valid
+pub(crate) fn rejected<T: MultiOpenable + ?Sized>(app: &Path, urls: &T) -> Vec<Url> {
+    let accepted = match remap(app, urls) {
+        Some((app, cfurls)) => check(app, cfurls, false),
+        None => return Vec::new(),
+    };
+    let cfurls: CFArray<CFURL> = match MultiOpenable::into_openable(urls) {
+        Some(cfurls) => cfurls,
+        None => return Vec::new(),
+    };
+    cfurls
+        .iter()
+        .zip(accepted)
+        .filter(|(_, accepted)| *accepted != Some(true))
+        .filter_map(|(url, _)| Url::parse(&url.get_string().to_string()).ok())
+        .collect()
+}
+
+/// Check which of the given urls the app can handle, in parallel for large batches. Results
+/// are in the same order of the urls, `None` if the app or the urls are not valid.
+pub fn app_acceptance<T: MultiOpenable + ?Sized>(app: &Path, urls: &T) -> Option<Vec<bool>> {
+    let (app, urls) = remap(app, urls)?;
+    Some(
+        check(app, urls, false)
+            .into_iter()
+            .map(|accepted| accepted.unwrap_or(false))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_acceptance() {
+        let urls = (0..20)
+            .map(|i| format!("https://www.example.com/{}", i))
+            .chain(std::iter::once("mailto:someone@example.com".to_string()))
+            .collect::<Vec<_>>();
+        let res = app_acceptance(Path::new("/Applications/Safari.app"), &urls).unwrap();
+        assert_eq!(res.len(), 21);
+        assert!(res[..20].iter().all(|accepted| *accepted));
+        assert!(!res[20]);
+        assert!(!all_accepted(Path::new("/Applications/Safari.app"), &urls));
+        assert_eq!(
+            rejected(Path::new("/Applications/Safari.app"), &urls),
+            vec![Url::parse("mailto:someone@example.com").unwrap()]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Finder aliases and `.webloc`, `.inetloc` and `.fileloc` files, resolved to what they point to
+//! like a double click in Finder does.
+
+use crate::macos::bundle::dictionary_string;
+use crate::macos::{OpenResult, ResolvedTarget};
+
+use core_foundation::array::CFArray;
+use core_foundation::base::{CFAllocatorRef, CFOptionFlags, CFType, CFTypeRef, TCFType};
+use core_foundation::data::{CFData, CFDataRef};
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::error::{CFError, CFErrorRef};
+use core_foundation::propertylist::{create_with_data, kCFPropertyListImmutable};
+use core_foundation::url::{CFURLRef, CFURL};
+use core_foundation_sys::base::{kCFAllocatorDefault, Boolean};
+use url::Url;
+
+use std::path::Path;
+
+const K_CF_URL_BOOKMARK_RESOLUTION_WITHOUT_UI: CFOptionFlags = 1 << 8;
+const K_CF_URL_BOOKMARK_RESOLUTION_WITHOUT_MOUNTING: CFOptionFlags = 1 << 9;
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFURLCreateBookmarkDataFromFile(
+        allocator: CFAllocatorRef,
+        fileURL: CFURLRef,
+        errorRef: *mut CFErrorRef,
+    ) -> CFDataRef;
+    fn CFURLCreateByResolvingBookmarkData(
+        allocator: CFAllocatorRef,
+        bookmark: CFDataRef,
+        options: CFOptionFlags,
+        relativeToURL: CFURLRef,
+        resourcePropertiesToInclude: CFTypeRef,
+        isStale: *mut Boolean,
+        error: *mut CFErrorRef,
+    ) -> CFURLRef;
+}
+
+/// Release the error of a failed call, the failure just means there is nothing to resolve
+fn discard(error: CFErrorRef) {
+    if !error.is_null() {
+        drop(unsafe { CFError::wrap_under_create_rule(error) });
+    }
+}
+
+/// The url stored in a location file, file urls give the path
+fn location_file(path: &Path) -> Option<ResolvedTarget> {
+    let data = std::fs::read(path).ok()?;
+    let (plist, _) = create_with_data(CFData::from_buffer(&data), kCFPropertyListImmutable).ok()?;
+    let dict = unsafe { CFType::wrap_under_create_rule(plist) }.downcast::<CFDictionary>()?;
+    let url = dictionary_string(&dict, "URL")?;
+    match Url::parse(&url) {
+        Ok(ref parsed) if parsed.scheme() == "file" => {
+            parsed.to_file_path().ok().map(ResolvedTarget::Path)
+        }
+        _ => Some(ResolvedTarget::Url(url)),
+    }
+}
+
+/// The original of a Finder alias, without asking the user nor mounting volumes
+fn finder_alias(path: &Path) -> Option<ResolvedTarget> {
+    let url = CFURL::from_path(path, false)?;
+    let mut error: CFErrorRef = std::ptr::null_mut();
+    let data = unsafe {
+        CFURLCreateBookmarkDataFromFile(kCFAllocatorDefault, url.as_concrete_TypeRef(), &mut error)
+    };
+    if data.is_null() {
+        discard(error);
+        return None;
+    }
+    let data = unsafe { CFData::wrap_under_create_rule(data) };
+
+    let mut stale: Boolean = 0;
+    let resolved = unsafe {
+        CFURLCreateByResolvingBookmarkData(
+            kCFAllocatorDefault,
+            data.as_concrete_TypeRef(),
+            K_CF_URL_BOOKMARK_RESOLUTION_WITHOUT_UI | K_CF_URL_BOOKMARK_RESOLUTION_WITHOUT_MOUNTING,
+            std::ptr::null(),
+            std::ptr::null(),
+            &mut stale,
+            &mut error,
+        )
+    };
+    if resolved.is_null() {
+        discard(error);
+        return None;
+    }
+    unsafe { CFURL::wrap_under_create_rule(resolved) }
+        .to_path()
+        .map(ResolvedTarget::Path)
+}
+
+/// Resolve a Finder alias or a `.webloc`, `.inetloc` or `.fileloc` file to the url or the path
+/// it points to, `None` if the path is none of them or the original can't be found
+pub fn resolve_alias(path: &Path) -> Option<ResolvedTarget> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("webloc") | Some("inetloc") | Some("fileloc") => location_file(path),
+        _ if path.is_file() => finder_alias(path),
+        _ => None,
+    }
+}
+
+/// Replace the aliases among the urls with their originals
+pub(crate) fn follow_aliases(urls: CFArray<CFURL>) -> OpenResult<CFArray<CFURL>> {
+    let mut followed = Vec::with_capacity(urls.len() as usize);
+    for url in urls.iter() {
+        let resolved = match url.to_path().and_then(|path| resolve_alias(&path)) {
+            Some(target) => target.as_target().to_url(false)?,
+            None => url.clone(),
+        };
+        followed.push(resolved);
+    }
+    Ok(CFArray::<CFURL>::from_CFTypes(&followed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_resolve_location_files() {
+        let dir = std::env::temp_dir().join(format!("macos-open-alias-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let plist = |url: &str| {
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <plist version=\"1.0\"><dict><key>URL</key><string>{}</string></dict></plist>",
+                url
+            )
+        };
+
+        let webloc = dir.join("Example.webloc");
+        std::fs::write(&webloc, plist("https://www.example.com/")).unwrap();
+        assert_eq!(
+            resolve_alias(&webloc),
+            Some(ResolvedTarget::Url("https://www.example.com/".to_string()))
+        );
+
+        let fileloc = dir.join("Hosts.fileloc");
+        std::fs::write(&fileloc, plist("file:///etc/hosts")).unwrap();
+        assert_eq!(
+            resolve_alias(&fileloc),
+            Some(ResolvedTarget::Path(PathBuf::from("/etc/hosts")))
+        );
+
+        assert_eq!(resolve_alias(Path::new("/etc/hosts")), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,138 @@
+//! Minimal Apple Event sender, used to deliver urls with a custom verb.
+
+use crate::macos::{OpenError, OpenResult};
+
+use core_foundation::array::CFArray;
+use core_foundation::url::CFURL;
+use core_foundation_sys::base::OSStatus;
+use libc::{c_long, c_void, pid_t};
+
+type OSErr = i16;
+type DescType = u32;
+
+#[repr(C)]
+struct AEDesc {
+    descriptor_type: DescType,
+    data_handle: *mut c_void,
+}
+
+impl AEDesc {
+    fn null() -> AEDesc {
+        AEDesc {
+            descriptor_type: TYPE_NULL,
+            data_handle: std::ptr::null_mut(),
+        }
+    }
+}
+
+impl Drop for AEDesc {
+    fn drop(&mut self) {
+        unsafe { AEDisposeDesc(self) };
+    }
+}
+
+const TYPE_NULL: DescType = 0x6e75_6c6c; // 'null'
+const TYPE_KERNEL_PROCESS_ID: DescType = 0x6b70_6964; // 'kpid'
+const TYPE_FILE_URL: DescType = 0x6675_726c; // 'furl'
+const KEY_DIRECT_OBJECT: u32 = 0x2d2d_2d2d; // '----'
+const K_AUTO_GENERATE_RETURN_ID: i16 = -1;
+const K_ANY_TRANSACTION_ID: i32 = 0;
+const K_AE_NO_REPLY: i32 = 0x0000_0001;
+const K_AE_DEFAULT_TIMEOUT: c_long = -1;
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn AECreateDesc(
+        typeCode: DescType,
+        dataPtr: *const c_void,
+        dataSize: isize,
+        result: *mut AEDesc,
+    ) -> OSErr;
+    fn AECreateAppleEvent(
+        theAEEventClass: u32,
+        theAEEventID: u32,
+        target: *const AEDesc,
+        returnID: i16,
+        transactionID: i32,
+        result: *mut AEDesc,
+    ) -> OSErr;
+    fn AECreateList(
+        factoringPtr: *const c_void,
+        factoredSize: isize,
+        isRecord: u8,
+        resultList: *mut AEDesc,
+    ) -> OSErr;
+    fn AEPutDesc(theAEDescList: *mut AEDesc, index: c_long, theAEDesc: *const AEDesc) -> OSErr;
+    fn AEPutParamDesc(
+        theAppleEvent: *mut AEDesc,
+        theAEKeyword: u32,
+        theAEDesc: *const AEDesc,
+    ) -> OSErr;
+    fn AESendMessage(
+        event: *const AEDesc,
+        reply: *mut AEDesc,
+        sendMode: i32,
+        timeOutInTicks: c_long,
+    ) -> OSStatus;
+    fn AEDisposeDesc(theAEDesc: *mut AEDesc) -> OSErr;
+}
+
+fn check(code: OSErr) -> OpenResult<()> {
+    if code == 0 {
+        Ok(())
+    } else {
+        Err(OpenError::Status(OSStatus::from(code)))
+    }
+}
+
+/// Create a descriptor holding the bytes of the value
+unsafe fn bytes_desc(kind: DescType, data: *const c_void, len: usize) -> OpenResult<AEDesc> {
+    let mut desc = AEDesc::null();
+    check(AECreateDesc(kind, data, len as isize, &mut desc))?;
+    Ok(desc)
+}
+
+/// Send the event `class`/`id` to the process with the given direct object
+unsafe fn send_event(pid: pid_t, class: u32, id: u32, direct_object: &AEDesc) -> OpenResult<()> {
+    let target = bytes_desc(
+        TYPE_KERNEL_PROCESS_ID,
+        &pid as *const pid_t as *const c_void,
+        std::mem::size_of::<pid_t>(),
+    )?;
+
+    let mut event = AEDesc::null();
+    check(AECreateAppleEvent(
+        class,
+        id,
+        &target,
+        K_AUTO_GENERATE_RETURN_ID,
+        K_ANY_TRANSACTION_ID,
+        &mut event,
+    ))?;
+    check(AEPutParamDesc(&mut event, KEY_DIRECT_OBJECT, direct_object))?;
+
+    match AESendMessage(
+        &event,
+        std::ptr::null_mut(),
+        K_AE_NO_REPLY,
+        K_AE_DEFAULT_TIMEOUT,
+    ) {
+        0 => Ok(()),
+        code => Err(OpenError::Status(code)),
+    }
+}
+
+/// Send the event `class`/`id` to the process, with the urls as direct object
+pub(crate) fn send(pid: pid_t, class: u32, id: u32, urls: &CFArray<CFURL>) -> OpenResult<()> {
+    unsafe {
+        let mut list = AEDesc::null();
+        check(AECreateList(std::ptr::null(), 0, 0, &mut list))?;
+        for url in urls.iter() {
+            let url = url.absolute().get_string().to_string();
+            let desc = bytes_desc(TYPE_FILE_URL, url.as_ptr() as *const c_void, url.len())?;
+            check(AEPutDesc(&mut list, 0, &desc))?;
+        }
+
+        send_event(pid, class, id, &list)
+    }
+}
@@ -0,0 +1,263 @@
+//! Export and apply the current user's default handlers in bulk, e.g. to keep them in a
+//! dotfiles repository.
+//!
+//! Launch Services has no API listing the user's choices: they are read from its private
+//! preferences file, whose format is undocumented and may change with any macOS release.
+//! Applying them goes through the public setters.
+
+use crate::macos::bundle::{dictionary_dictionaries, dictionary_string};
+use crate::macos::{
+    set_default_handler_for_scheme, set_default_handler_for_type, OpenError, OpenResult,
+};
+
+use core_foundation::base::{CFType, TCFType};
+use core_foundation::data::CFData;
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::propertylist::{create_with_data, kCFPropertyListImmutable};
+use core_foundation::string::{CFString, CFStringRef};
+use launch_services::LSRolesMask;
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+
+/// Where Launch Services stores the user's choices, relative to the home folder
+const HANDLERS_PLIST: &str =
+    "Library/Preferences/com.apple.LaunchServices/com.apple.launchservices.secure.plist";
+/// The bundle identifier Launch Services writes for "no handler"
+const NO_HANDLER: &str = "-";
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn UTTypeCreatePreferredIdentifierForTag(
+        inTagClass: CFStringRef,
+        inTag: CFStringRef,
+        inConformingToUTI: CFStringRef,
+    ) -> CFStringRef;
+}
+
+/// The handlers of a content type by role, as bundle identifiers. `all` stands for the roles
+/// without a handler of their own.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RoleHandlers {
+    /// The handler in every role (LSHandlerRoleAll)
+    pub all: Option<String>,
+    /// The handler opening documents to view them (LSHandlerRoleViewer)
+    pub viewer: Option<String>,
+    /// The handler opening documents to edit them (LSHandlerRoleEditor)
+    pub editor: Option<String>,
+    /// The handler running scripts (LSHandlerRoleShell)
+    pub shell: Option<String>,
+}
+
+impl RoleHandlers {
+    /// The handlers of an LSHandlers entry, without the "no handler" ones
+    fn from_entry(entry: &CFDictionary) -> RoleHandlers {
+        let handler = |role| dictionary_string(entry, role).filter(|handler| handler != NO_HANDLER);
+        RoleHandlers {
+            all: handler("LSHandlerRoleAll"),
+            viewer: handler("LSHandlerRoleViewer"),
+            editor: handler("LSHandlerRoleEditor"),
+            shell: handler("LSHandlerRoleShell"),
+        }
+    }
+
+    /// The handlers with their role, `all` first so that the other ones override it
+    fn by_role(&self) -> [(&'static str, LSRolesMask, Option<&String>); 4] {
+        [
+            ("all", LSRolesMask::ALL, self.all.as_ref()),
+            ("viewer", LSRolesMask::VIEWER, self.viewer.as_ref()),
+            ("editor", LSRolesMask::EDITOR, self.editor.as_ref()),
+            ("shell", LSRolesMask::SHELL, self.shell.as_ref()),
+        ]
+    }
+
+    /// The first handler, for the schemes which have no roles
+    fn any(self) -> Option<String> {
+        self.all.or(self.viewer).or(self.editor).or(self.shell)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.by_role()
+            .iter()
+            .all(|(_, _, handler)| handler.is_none())
+    }
+}
+
+/// The user's default handlers as bundle identifiers, by scheme, by uniform type identifier
+/// and by tag. The maps are sorted, so a serialized map diffs well.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct AssociationMap {
+    /// The handlers of the url schemes (e.g. `mailto`)
+    pub schemes: BTreeMap<String, String>,
+    /// The handlers of the content types (e.g. `public.plain-text`)
+    pub content_types: BTreeMap<String, RoleHandlers>,
+    /// The handlers of the types known by a tag only, by tag class then tag (e.g. the `md`
+    /// extension in `public.filename-extension`)
+    pub content_tags: BTreeMap<String, BTreeMap<String, RoleHandlers>>,
+}
+
+impl AssociationMap {
+    /// Create an empty map
+    pub fn new() -> AssociationMap {
+        AssociationMap::default()
+    }
+}
+
+fn handlers_plist() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HANDLERS_PLIST))
+}
+
+/// Read the schemes and the content types the user picked a default handler for, from the
+/// private preferences file of Launch Services. The handlers the system picks by itself
+/// aren't listed.
+pub fn export_associations() -> OpenResult<AssociationMap> {
+    let path = handlers_plist()
+        .ok_or_else(|| OpenError::Io(io::Error::new(io::ErrorKind::NotFound, "HOME")))?;
+    let data = match std::fs::read(&path) {
+        Ok(data) => data,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(AssociationMap::new()),
+        Err(err) => return Err(OpenError::Io(err)),
+    };
+    let invalid = || {
+        OpenError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            path.display().to_string(),
+        ))
+    };
+    let (plist, _) = create_with_data(CFData::from_buffer(&data), kCFPropertyListImmutable)
+        .map_err(|_| invalid())?;
+    let plist = unsafe { CFType::wrap_under_create_rule(plist) }
+        .downcast::<CFDictionary>()
+        .ok_or_else(invalid)?;
+
+    let mut map = AssociationMap::new();
+    for entry in dictionary_dictionaries(&plist, "LSHandlers") {
+        let handlers = RoleHandlers::from_entry(&entry);
+        if handlers.is_empty() {
+            continue;
+        }
+        if let Some(scheme) = dictionary_string(&entry, "LSHandlerURLScheme") {
+            if let Some(handler) = handlers.any() {
+                map.schemes.insert(scheme.to_ascii_lowercase(), handler);
+            }
+        } else if let Some(uti) = dictionary_string(&entry, "LSHandlerContentType") {
+            map.content_types.insert(uti.to_ascii_lowercase(), handlers);
+        } else if let (Some(class), Some(tag)) = (
+            dictionary_string(&entry, "LSHandlerContentTagClass"),
+            dictionary_string(&entry, "LSHandlerContentTag"),
+        ) {
+            map.content_tags
+                .entry(class)
+                .or_default()
+                .insert(tag.to_ascii_lowercase(), handlers);
+        }
+    }
+    Ok(map)
+}
+
+/// The uniform type identifier of the tag, dynamic for the tags no type declares
+fn uti_for_tag(class: &str, tag: &str) -> Option<String> {
+    let class = CFString::new(class);
+    let tag = CFString::new(tag);
+    let uti = unsafe {
+        UTTypeCreatePreferredIdentifierForTag(
+            class.as_concrete_TypeRef(),
+            tag.as_concrete_TypeRef(),
+            std::ptr::null(),
+        )
+    };
+    if uti.is_null() {
+        None
+    } else {
+        Some(unsafe { CFString::wrap_under_create_rule(uti) }.to_string())
+    }
+}
+
+fn apply_roles(
+    entry: &str,
+    uti: &str,
+    handlers: &RoleHandlers,
+    failed: &mut Vec<(String, OpenError)>,
+) {
+    for &(name, role, handler) in handlers.by_role().iter() {
+        if let Some(bundle_id) = handler {
+            if let Err(err) = set_default_handler_for_type(uti, role, bundle_id) {
+                failed.push((format!("{} {}", entry, name), err));
+            }
+        }
+    }
+}
+
+/// Make the apps of the map the default handlers of their schemes, content types and tags.
+/// Every entry is applied even when some fail: the failed ones are returned with their
+/// error, named like `mailto`, `public.plain-text viewer` or `public.filename-extension md
+/// all`. The system asks the user to confirm a new default browser.
+pub fn apply_associations(map: &AssociationMap) -> Vec<(String, OpenError)> {
+    let mut failed = Vec::new();
+    for (scheme, bundle_id) in &map.schemes {
+        if let Err(err) = set_default_handler_for_scheme(scheme, bundle_id) {
+            failed.push((scheme.clone(), err));
+        }
+    }
+    for (uti, handlers) in &map.content_types {
+        apply_roles(uti, uti, handlers, &mut failed);
+    }
+    for (class, tags) in &map.content_tags {
+        for (tag, handlers) in tags {
+            let entry = format!("{} {}", class, tag);
+            match uti_for_tag(class, tag) {
+                Some(uti) => apply_roles(&entry, &uti, handlers, &mut failed),
+                None => failed.push((entry, OpenError::UnknownType(tag.clone()))),
+            }
+        }
+    }
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_associations() {
+        let map = export_associations().unwrap();
+        assert!(map
+            .schemes
+            .keys()
+            .all(|scheme| scheme.to_ascii_lowercase() == *scheme));
+        assert!(map.content_types.values().all(|handlers| handlers
+            .by_role()
+            .iter()
+            .all(|(_, _, handler)| handler.map(String::as_str) != Some(NO_HANDLER))));
+    }
+
+    #[test]
+    fn test_role_handlers_from_entry() {
+        let pair = |key: &str, value: &str| {
+            (
+                CFString::new(key).as_CFType(),
+                CFString::new(value).as_CFType(),
+            )
+        };
+        let entry = CFDictionary::from_CFType_pairs(&[
+            pair("LSHandlerContentTagClass", "public.filename-extension"),
+            pair("LSHandlerContentTag", "md"),
+            pair("LSHandlerRoleAll", NO_HANDLER),
+            pair("LSHandlerRoleViewer", "com.apple.TextEdit"),
+        ]);
+        let handlers = RoleHandlers::from_entry(&entry.to_untyped());
+        assert_eq!(
+            handlers,
+            RoleHandlers {
+                viewer: Some("com.apple.TextEdit".to_string()),
+                ..RoleHandlers::default()
+            }
+        );
+        assert_eq!(handlers.any(), Some("com.apple.TextEdit".to_string()));
+    }
+}
@@ -0,0 +1,152 @@
+//! Security-scoped bookmarks, to reopen user-granted files from sandboxed apps.
+
+use crate::macos::{open_default, OpenError, OpenResult};
+
+use core_foundation::base::{CFAllocatorRef, CFOptionFlags, CFTypeRef, TCFType};
+use core_foundation::data::{CFData, CFDataRef};
+use core_foundation::error::{CFError, CFErrorRef};
+use core_foundation::url::{CFURLRef, CFURL};
+use core_foundation_sys::base::{kCFAllocatorDefault, Boolean};
+
+use std::path::{Path, PathBuf};
+
+const K_CF_URL_BOOKMARK_CREATION_WITH_SECURITY_SCOPE: CFOptionFlags = 1 << 11;
+const K_CF_URL_BOOKMARK_RESOLUTION_WITH_SECURITY_SCOPE: CFOptionFlags = 1 << 10;
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFURLCreateBookmarkData(
+        allocator: CFAllocatorRef,
+        url: CFURLRef,
+        options: CFOptionFlags,
+        resourcePropertiesToInclude: CFTypeRef,
+        relativeToURL: CFURLRef,
+        error: *mut CFErrorRef,
+    ) -> CFDataRef;
+    fn CFURLCreateByResolvingBookmarkData(
+        allocator: CFAllocatorRef,
+        bookmark: CFDataRef,
+        options: CFOptionFlags,
+        relativeToURL: CFURLRef,
+        resourcePropertiesToInclude: CFTypeRef,
+        isStale: *mut Boolean,
+        error: *mut CFErrorRef,
+    ) -> CFURLRef;
+    fn CFURLStartAccessingSecurityScopedResource(url: CFURLRef) -> Boolean;
+    fn CFURLStopAccessingSecurityScopedResource(url: CFURLRef);
+}
+
+fn bookmark_error(error: CFErrorRef) -> OpenError {
+    if error.is_null() {
+        return OpenError::Bookmark {
+            code: 0,
+            message: "unknown error".to_string(),
+        };
+    }
+    let error = unsafe { CFError::wrap_under_create_rule(error) };
+    OpenError::Bookmark {
+        code: error.code() as i64,
+        message: error.description().to_string(),
+    }
+}
+
+/// Access to the resource of a resolved bookmark, given back when dropped
+#[derive(Debug)]
+pub struct SecurityScope {
+    url: CFURL,
+    accessing: bool,
+    stale: bool,
+}
+
+impl SecurityScope {
+    /// The path of the bookmarked resource
+    pub fn path(&self) -> Option<PathBuf> {
+        self.url.to_path()
+    }
+
+    /// If the bookmark data is outdated and should be created again
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+}
+
+impl Drop for SecurityScope {
+    fn drop(&mut self) {
+        if self.accessing {
+            unsafe { CFURLStopAccessingSecurityScopedResource(self.url.as_concrete_TypeRef()) };
+        }
+    }
+}
+
+/// Create a security-scoped bookmark for the given path, to be stored and resolved later
+pub fn bookmark_for_path(path: &Path) -> OpenResult<Vec<u8>> {
+    let url = CFURL::from_path(path, path.is_dir()).ok_or(OpenError::InvalidUrl)?;
+    let mut error: CFErrorRef = std::ptr::null_mut();
+    let data = unsafe {
+        CFURLCreateBookmarkData(
+            kCFAllocatorDefault,
+            url.as_concrete_TypeRef(),
+            K_CF_URL_BOOKMARK_CREATION_WITH_SECURITY_SCOPE,
+            std::ptr::null(),
+            std::ptr::null(),
+            &mut error,
+        )
+    };
+    if data.is_null() {
+        return Err(bookmark_error(error));
+    }
+    let data = unsafe { CFData::wrap_under_create_rule(data) };
+    Ok(data.bytes().to_vec())
+}
+
+/// Resolve a security-scoped bookmark and start accessing its resource until the returned
+/// guard is dropped
+pub fn resolve_bookmark(data: &[u8]) -> OpenResult<SecurityScope> {
+    let data = CFData::from_buffer(data);
+    let mut stale: Boolean = 0;
+    let mut error: CFErrorRef = std::ptr::null_mut();
+    let url = unsafe {
+        CFURLCreateByResolvingBookmarkData(
+            kCFAllocatorDefault,
+            data.as_concrete_TypeRef(),
+            K_CF_URL_BOOKMARK_RESOLUTION_WITH_SECURITY_SCOPE,
+            std::ptr::null(),
+            std::ptr::null(),
+            &mut stale,
+            &mut error,
+        )
+    };
+    if url.is_null() {
+        return Err(bookmark_error(error));
+    }
+    let url = unsafe { CFURL::wrap_under_create_rule(url) };
+    let accessing = unsafe { CFURLStartAccessingSecurityScopedResource(url.as_concrete_TypeRef()) };
+    Ok(SecurityScope {
+        url,
+        accessing: accessing != 0,
+        stale: stale != 0,
+    })
+}
+
+/// Resolve a security-scoped bookmark and open its resource with the default handler
+pub fn open_bookmark(data: &[u8]) -> OpenResult<Option<PathBuf>> {
+    let scope = resolve_bookmark(data)?;
+    let path = scope.path().ok_or(OpenError::InvalidUrl)?;
+    open_default(&path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmark_round_trip() {
+        let path = std::env::temp_dir();
+        let data = bookmark_for_path(&path).unwrap();
+        let scope = resolve_bookmark(&data).unwrap();
+        assert_eq!(
+            scope.path().map(|p| p.canonicalize().unwrap()),
+            Some(path.canonicalize().unwrap())
+        );
+    }
+}
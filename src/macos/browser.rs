@@ -0,0 +1,196 @@
+//! Open urls in a given browser, profile or private window.
+
+use crate::macos::{
+    _url, app_for_bundle_id, AppInfo, Backend, LaunchedApp, OpenError, OpenResult, Openable, Opener,
+};
+
+use core_foundation::array::CFArray;
+use core_foundation::url::CFURL;
+use launch_services::{default_application_url_for_url, LSRolesMask};
+use url::Url;
+
+use std::path::PathBuf;
+
+/// The schemes a browser is started with on its command line
+const BROWSER_SCHEMES: [&str; 3] = ["http", "https", "file"];
+
+/// A browser, with the profile and the kind of window to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BrowserTarget<'a> {
+    /// Safari, private windows can't be requested from the outside
+    Safari,
+    /// Google Chrome
+    Chrome {
+        /// The profile directory name, e.g. `Default` or `Profile 1`
+        profile: Option<&'a str>,
+        /// Open the url in an incognito window
+        incognito: bool,
+    },
+    /// Mozilla Firefox
+    Firefox {
+        /// The profile name
+        profile: Option<&'a str>,
+        /// Open the url in a private window
+        private: bool,
+    },
+    /// Microsoft Edge
+    Edge {
+        /// The profile directory name, e.g. `Default` or `Profile 1`
+        profile: Option<&'a str>,
+        /// Open the url in an InPrivate window
+        inprivate: bool,
+    },
+}
+
+impl<'a> BrowserTarget<'a> {
+    /// The bundle identifier of the browser
+    pub fn bundle_id(&self) -> &'static str {
+        match self {
+            BrowserTarget::Safari => "com.apple.Safari",
+            BrowserTarget::Chrome { .. } => "com.google.Chrome",
+            BrowserTarget::Firefox { .. } => "org.mozilla.firefox",
+            BrowserTarget::Edge { .. } => "com.microsoft.edgemac",
+        }
+    }
+
+    /// The command line arguments which open the url, `None` if the browser is given the url
+    /// through Launch Services
+    fn arguments(&self, url: &Url) -> Option<Vec<String>> {
+        let mut args = Vec::new();
+        match *self {
+            BrowserTarget::Safari => return None,
+            BrowserTarget::Chrome { profile, incognito } => {
+                if let Some(profile) = profile {
+                    args.push(format!("--profile-directory={}", profile));
+                }
+                if incognito {
+                    args.push("--incognito".to_string());
+                }
+                args.push("--".to_string());
+            }
+            BrowserTarget::Firefox { profile, private } => {
+                if let Some(profile) = profile {
+                    args.push("-P".to_string());
+                    args.push(profile.to_string());
+                }
+                if private {
+                    args.push("-private-window".to_string());
+                } else {
+                    args.push("-new-tab".to_string());
+                }
+            }
+            BrowserTarget::Edge { profile, inprivate } => {
+                if let Some(profile) = profile {
+                    args.push(format!("--profile-directory={}", profile));
+                }
+                if inprivate {
+                    args.push("--inprivate".to_string());
+                }
+                args.push("--".to_string());
+            }
+        }
+        args.push(url.to_string());
+        Some(args)
+    }
+}
+
+/// Parse the url handed to a browser, only absolute http, https and file urls are accepted so
+/// it can't be taken for an option
+fn browser_url(url: &str) -> OpenResult<Url> {
+    if url.starts_with('-') {
+        return Err(OpenError::InvalidUrl);
+    }
+    match Url::parse(url) {
+        Ok(url) if BROWSER_SCHEMES.contains(&url.scheme()) => Ok(url),
+        _ => Err(OpenError::InvalidUrl),
+    }
+}
+
+/// Open the url in the given browser, profile and kind of window. The url must be an absolute
+/// http, https or file url.
+pub fn open_in_browser(url: &str, target: BrowserTarget) -> OpenResult<LaunchedApp> {
+    let url = browser_url(url)?;
+    let app = app_for_bundle_id(target.bundle_id())
+        .ok_or_else(|| OpenError::AppNotFound(target.bundle_id().to_string()))?;
+
+    let mut options = Opener::global().options().clone();
+    options.app(app);
+    match target.arguments(&url) {
+        // a direct launch forwards the arguments to the running instance as well
+        Some(args) => options.backend(Backend::LaunchServices).args(args).launch(),
+        None => options.open(url.as_str()),
+    }
+}
+
+fn default_browser_path() -> Option<PathBuf> {
+    let https = _url("https:")?;
+    default_application_url_for_url(&https, LSRolesMask::VIEWER)
+        .ok()?
+        .to_path()
+}
+
+/// Get the default browser, the handler of the `https` scheme
+pub fn default_browser() -> Option<AppInfo> {
+    default_browser_path().map(AppInfo::from_path)
+}
+
+/// Open the url in the default browser, even if another app is the default handler of the url
+/// (e.g. local html files)
+pub fn open_in_default_browser<T: Openable + ?Sized>(url: &T) -> OpenResult<LaunchedApp> {
+    let url = Openable::into_openable(url).ok_or(OpenError::InvalidUrl)?;
+    let browser =
+        default_browser_path().ok_or_else(|| OpenError::AppNotFound("https".to_string()))?;
+    Opener::global()
+        .options()
+        .clone()
+        .app(browser)
+        .launch_urls(Some(CFArray::<CFURL>::from_CFTypes(&[url])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_browser() {
+        let browser = default_browser().unwrap();
+        assert!(browser.bundle_id().is_some());
+    }
+
+    #[test]
+    fn test_browser_arguments() {
+        let url = browser_url("https://www.example.com/").unwrap();
+        assert_eq!(BrowserTarget::Safari.arguments(&url), None);
+        assert_eq!(
+            BrowserTarget::Chrome {
+                profile: Some("Profile 1"),
+                incognito: true,
+            }
+            .arguments(&url),
+            Some(vec![
+                "--profile-directory=Profile 1".to_string(),
+                "--incognito".to_string(),
+                "--".to_string(),
+                url.to_string(),
+            ])
+        );
+        assert_eq!(
+            BrowserTarget::Firefox {
+                profile: None,
+                private: false,
+            }
+            .arguments(&url),
+            Some(vec!["-new-tab".to_string(), url.to_string()])
+        );
+
+        assert!(browser_url("file:///etc/hosts").is_ok());
+        for url in &[
+            "--load-extension=/tmp/x",
+            "-P evil",
+            "javascript:alert(1)",
+            "www.example.com",
+        ] {
+            assert!(matches!(browser_url(url), Err(OpenError::InvalidUrl)));
+        }
+    }
+}
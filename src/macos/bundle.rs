@@ -0,0 +1,371 @@
+//! Helpers to read informations out of an application bundle.
+
+use core_foundation::array::CFArray;
+use core_foundation::base::{CFType, TCFType};
+use core_foundation::bundle::CFBundle;
+use core_foundation::data::CFData;
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::propertylist::{create_with_data, kCFPropertyListImmutable};
+use core_foundation::string::CFString;
+use core_foundation::url::CFURL;
+
+use std::path::{Path, PathBuf};
+
+/// Get the Info.plist dictionary of the bundle at the given path
+pub(crate) fn info_dictionary(path: &Path) -> Option<CFDictionary> {
+    let bundle = CFBundle::new(CFURL::from_path(path, true)?)?;
+    Some(bundle.info_dictionary().into_untyped())
+}
+
+/// Get a value out of a Core Foundation dictionary with string keys
+pub(crate) fn dictionary_value(dict: &CFDictionary, key: &str) -> Option<CFType> {
+    let key = CFString::new(key);
+    dict.find(key.as_CFTypeRef())
+        .map(|value| unsafe { CFType::wrap_under_get_rule(*value) })
+}
+
+/// Get a string value out of a Core Foundation dictionary with string keys
+pub(crate) fn dictionary_string(dict: &CFDictionary, key: &str) -> Option<String> {
+    dictionary_value(dict, key)?
+        .downcast::<CFString>()
+        .map(|value| value.to_string())
+}
+
+/// Get the items of an array value out of a Core Foundation dictionary with string keys
+pub(crate) fn dictionary_array(dict: &CFDictionary, key: &str) -> Vec<CFType> {
+    dictionary_value(dict, key)
+        .and_then(|value| value.downcast::<CFArray>())
+        .map(|array| {
+            array
+                .iter()
+                .map(|item| unsafe { CFType::wrap_under_get_rule(*item) })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Get the strings of an array value out of a Core Foundation dictionary with string keys
+pub(crate) fn dictionary_strings(dict: &CFDictionary, key: &str) -> Vec<String> {
+    dictionary_array(dict, key)
+        .into_iter()
+        .filter_map(|item| item.downcast::<CFString>())
+        .map(|item| item.to_string())
+        .collect()
+}
+
+/// Get the dictionaries of an array value out of a Core Foundation dictionary with string keys
+pub(crate) fn dictionary_dictionaries(dict: &CFDictionary, key: &str) -> Vec<CFDictionary> {
+    dictionary_array(dict, key)
+        .into_iter()
+        .filter_map(|item| item.downcast::<CFDictionary>())
+        .collect()
+}
+
+/// Get the bundle identifier (CFBundleIdentifier) of the bundle at the given path
+pub(crate) fn bundle_identifier(path: &Path) -> Option<String> {
+    dictionary_string(&info_dictionary(path)?, "CFBundleIdentifier")
+}
+
+/// Get the marketing version (CFBundleShortVersionString) of the bundle at the given path
+pub(crate) fn short_version(path: &Path) -> Option<String> {
+    dictionary_string(&info_dictionary(path)?, "CFBundleShortVersionString")
+}
+
+/// Get the path of the main executable (CFBundleExecutable) of the bundle at the given path
+pub(crate) fn executable_path(path: &Path) -> Option<PathBuf> {
+    let executable = dictionary_string(&info_dictionary(path)?, "CFBundleExecutable")?;
+    Some(path.join("Contents").join("MacOS").join(executable))
+}
+
+/// The names (CFBundleDisplayName and CFBundleName) in the given InfoPlist.strings or
+/// Info.plist dictionary
+fn bundle_names(dict: &CFDictionary) -> impl Iterator<Item = String> {
+    dictionary_string(dict, "CFBundleDisplayName")
+        .into_iter()
+        .chain(dictionary_string(dict, "CFBundleName"))
+}
+
+/// Read a property list file whose root is a dictionary
+fn read_dictionary(path: &Path) -> Option<CFDictionary> {
+    let data = std::fs::read(path).ok()?;
+    let (plist, _) = create_with_data(CFData::from_buffer(&data), kCFPropertyListImmutable).ok()?;
+    unsafe { CFType::wrap_under_create_rule(plist) }.downcast::<CFDictionary>()
+}
+
+/// Read the InfoPlist.strings of a localization directory
+fn localized_info(lproj: &Path) -> Option<CFDictionary> {
+    read_dictionary(&lproj.join("InfoPlist.strings"))
+}
+
+/// Read the InfoPlist.loctable recent system apps ship instead of the `.lproj` strings: the
+/// strings of every localization in one property list, keyed by the localization name
+fn localized_table(path: &Path) -> Vec<(String, CFDictionary)> {
+    let table = path
+        .join("Contents")
+        .join("Resources")
+        .join("InfoPlist.loctable");
+    let table = match read_dictionary(&table) {
+        Some(table) => table,
+        None => return Vec::new(),
+    };
+    let (keys, values) = table.get_keys_and_values();
+    keys.into_iter()
+        .zip(values)
+        .filter_map(|(key, value)| {
+            let key = unsafe { CFType::wrap_under_get_rule(key) }.downcast::<CFString>()?;
+            let value = unsafe { CFType::wrap_under_get_rule(value) }.downcast::<CFDictionary>()?;
+            Some((key.to_string(), value))
+        })
+        .collect()
+}
+
+/// If the localization name (e.g. the stem of an `.lproj` directory) is in the language of the
+/// given localization, so `de` matches `de_DE` too
+fn is_localization(name: &str, localization: &str) -> bool {
+    let language = |name: &str| {
+        name.split(|c| c == '_' || c == '-')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase()
+    };
+    language(name) == language(localization)
+}
+
+/// Get the names of the app at the given path in the given localization (e.g. `de`), or in
+/// all the localizations it ships with if `None`, from its `.lproj` strings or its loctable.
+/// The unlocalized names are included when looking at all the localizations or when the app
+/// isn't localized in the given one.
+pub(crate) fn localized_names(path: &Path, localization: Option<&str>) -> Vec<String> {
+    let lprojs = std::fs::read_dir(path.join("Contents").join("Resources"))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|entry| entry.extension().map_or(false, |ext| ext == "lproj"))
+                .filter(|entry| {
+                    match (
+                        localization,
+                        entry.file_stem().and_then(|name| name.to_str()),
+                    ) {
+                        (None, _) => true,
+                        (Some(localization), Some(name)) => is_localization(name, localization),
+                        (Some(_), None) => false,
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut names = lprojs
+        .iter()
+        .filter_map(|lproj| localized_info(lproj))
+        .flat_map(|info| bundle_names(&info).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    for (name, info) in localized_table(path) {
+        if localization.iter().all(|l| is_localization(&name, l)) {
+            names.extend(bundle_names(&info));
+        }
+    }
+    if localization.is_none() || names.is_empty() {
+        if let Some(info) = info_dictionary(path) {
+            names.extend(bundle_names(&info));
+        }
+        if let Some(stem) = path.file_stem() {
+            names.push(stem.to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Informations about an installed app
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AppInfo {
+    path: PathBuf,
+    bundle_id: Option<String>,
+    name: String,
+    version: Option<String>,
+}
+
+impl AppInfo {
+    pub(crate) fn from_path(path: PathBuf) -> AppInfo {
+        let info = info_dictionary(&path);
+        let string = |key| info.as_ref().and_then(|info| dictionary_string(info, key));
+        let name = string("CFBundleDisplayName")
+            .or_else(|| string("CFBundleName"))
+            .unwrap_or_else(|| {
+                path.file_stem()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            });
+
+        AppInfo {
+            bundle_id: string("CFBundleIdentifier"),
+            version: string("CFBundleShortVersionString"),
+            name,
+            path,
+        }
+    }
+
+    /// The path of the app
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The bundle identifier of the app
+    pub fn bundle_id(&self) -> Option<&str> {
+        self.bundle_id.as_ref().map(String::as_str)
+    }
+
+    /// The user visible name of the app
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The marketing version of the app
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_ref().map(String::as_str)
+    }
+}
+
+/// A document type declared by an app (an entry of CFBundleDocumentTypes)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DocumentType {
+    name: Option<String>,
+    role: Option<String>,
+    rank: Option<String>,
+    content_types: Vec<String>,
+    extensions: Vec<String>,
+}
+
+impl DocumentType {
+    #[cfg(test)]
+    pub(crate) fn new(
+        role: Option<&str>,
+        rank: Option<&str>,
+        content_types: Vec<String>,
+        extensions: Vec<String>,
+    ) -> DocumentType {
+        DocumentType {
+            name: None,
+            role: role.map(str::to_string),
+            rank: rank.map(str::to_string),
+            content_types,
+            extensions,
+        }
+    }
+
+    fn from_dictionary(dict: &CFDictionary) -> DocumentType {
+        DocumentType {
+            name: dictionary_string(dict, "CFBundleTypeName"),
+            role: dictionary_string(dict, "CFBundleTypeRole"),
+            rank: dictionary_string(dict, "LSHandlerRank"),
+            content_types: dictionary_strings(dict, "LSItemContentTypes"),
+            extensions: dictionary_strings(dict, "CFBundleTypeExtensions"),
+        }
+    }
+
+    /// The name of the type (CFBundleTypeName)
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(String::as_str)
+    }
+
+    /// The role of the app for the type: `Editor`, `Viewer`, `Shell` or `None`
+    /// (CFBundleTypeRole)
+    pub fn role(&self) -> Option<&str> {
+        self.role.as_ref().map(String::as_str)
+    }
+
+    /// How the app ranks itself as handler of the type: `Owner`, `Default`, `Alternate` or
+    /// `None` (LSHandlerRank)
+    pub fn rank(&self) -> Option<&str> {
+        self.rank.as_ref().map(String::as_str)
+    }
+
+    /// The uniform type identifiers of the type (LSItemContentTypes)
+    pub fn content_types(&self) -> &[String] {
+        &self.content_types
+    }
+
+    /// The legacy file extensions of the type (CFBundleTypeExtensions)
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+}
+
+/// Get the url schemes the app at the given path declares in CFBundleURLTypes
+pub fn declared_schemes_for_app(path: &Path) -> Vec<String> {
+    match info_dictionary(path) {
+        Some(info) => dictionary_dictionaries(&info, "CFBundleURLTypes")
+            .iter()
+            .flat_map(|url_type| dictionary_strings(url_type, "CFBundleURLSchemes"))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Get the document types the app at the given path declares in CFBundleDocumentTypes
+pub fn declared_document_types_for_app(path: &Path) -> Vec<DocumentType> {
+    match info_dictionary(path) {
+        Some(info) => dictionary_dictionaries(&info, "CFBundleDocumentTypes")
+            .iter()
+            .map(DocumentType::from_dictionary)
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_declared_schemes_for_app() {
+        let schemes = declared_schemes_for_app(Path::new("/Applications/Safari.app"));
+        assert!(schemes.iter().any(|scheme| scheme == "http"));
+    }
+
+    #[test]
+    fn test_localized_names() {
+        let preview = Path::new("/System/Applications/Preview.app");
+        assert!(localized_names(preview, None)
+            .iter()
+            .any(|name| name == "Vorschau"));
+        assert!(localized_names(preview, Some("de"))
+            .iter()
+            .all(|name| name != "Preview"));
+    }
+
+    #[test]
+    fn test_localized_table() {
+        let dir = std::env::temp_dir().join(format!("macos-open-loctable-{}", std::process::id()));
+        let app = dir.join("Viewer.app");
+        let resources = app.join("Contents").join("Resources");
+        std::fs::create_dir_all(&resources).unwrap();
+        std::fs::write(
+            resources.join("InfoPlist.loctable"),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\"><dict>\
+             <key>de</key><dict><key>CFBundleDisplayName</key><string>Vorschau</string></dict>\
+             <key>fr</key><dict><key>CFBundleDisplayName</key><string>Aper\u{e7}u</string></dict>\
+             </dict></plist>",
+        )
+        .unwrap();
+
+        let german = localized_names(&app, Some("de_DE"));
+        let all = localized_names(&app, None);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(german, vec!["Vorschau".to_string()]);
+        assert_eq!(all, vec!["Aper\u{e7}u", "Viewer", "Vorschau"]);
+    }
+
+    #[test]
+    fn test_declared_document_types_for_app() {
+        let types = declared_document_types_for_app(Path::new("/System/Applications/TextEdit.app"));
+        assert!(types
+            .iter()
+            .flat_map(DocumentType::content_types)
+            .any(|uti| uti == "public.plain-text"));
+    }
+}
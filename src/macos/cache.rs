@@ -0,0 +1,168 @@
+//! Memoization of the handler and Spotlight lookups.
+
+use crate::macos::{app_for_bundle_id, app_for_scheme, apps_for_name, apps_for_scheme};
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Results of a single lookup function, by key
+#[derive(Debug)]
+struct Memo<V> {
+    entries: Mutex<HashMap<String, (Instant, V)>>,
+}
+
+impl<V: Clone> Memo<V> {
+    fn new() -> Memo<V> {
+        Memo {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get_or_insert_with<F: FnOnce() -> V>(&self, key: &str, ttl: Option<Duration>, f: F) -> V {
+        if let Some((at, value)) = self.lock().get(key) {
+            if ttl.map_or(true, |ttl| at.elapsed() < ttl) {
+                return value.clone();
+            }
+        }
+
+        // the lookup is slow, don't keep the other keys locked in the meantime
+        let value = f();
+        self.lock()
+            .insert(key.to_string(), (Instant::now(), value.clone()));
+        value
+    }
+
+    fn remove(&self, key: &str) {
+        self.lock().remove(key);
+    }
+
+    fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, (Instant, V)>> {
+        // a panicking lookup can't leave the map half updated
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// An opt-in cache for the slow lookups (Launch Services handlers and Spotlight queries), for
+/// hot paths which resolve the same schemes, bundle identifiers and names over and over.
+///
+/// Entries never expire unless a TTL is set, invalidate them when the installed apps or the
+/// default handlers change. The cache can be shared between threads.
+///
+/// ```no_run
+/// use macos_open::LookupCache;
+/// use std::time::Duration;
+///
+/// let cache = LookupCache::with_ttl(Duration::from_secs(60));
+/// let browser = cache.app_for_scheme("https");
+/// ```
+#[derive(Debug)]
+pub struct LookupCache {
+    ttl: Option<Duration>,
+    scheme_handlers: Memo<Option<PathBuf>>,
+    scheme_apps: Memo<Option<Vec<PathBuf>>>,
+    bundle_ids: Memo<Option<PathBuf>>,
+    names: Memo<Option<Vec<PathBuf>>>,
+}
+
+impl Default for LookupCache {
+    fn default() -> LookupCache {
+        LookupCache::new()
+    }
+}
+
+impl LookupCache {
+    /// Create an empty cache whose entries never expire
+    pub fn new() -> LookupCache {
+        LookupCache {
+            ttl: None,
+            scheme_handlers: Memo::new(),
+            scheme_apps: Memo::new(),
+            bundle_ids: Memo::new(),
+            names: Memo::new(),
+        }
+    }
+
+    /// Create an empty cache whose entries expire after the given time
+    pub fn with_ttl(ttl: Duration) -> LookupCache {
+        LookupCache {
+            ttl: Some(ttl),
+            ..LookupCache::new()
+        }
+    }
+
+    /// Cached `app_for_scheme`
+    pub fn app_for_scheme(&self, scheme: &str) -> Option<PathBuf> {
+        self.scheme_handlers
+            .get_or_insert_with(scheme, self.ttl, || app_for_scheme(scheme))
+    }
+
+    /// Cached `apps_for_scheme`
+    pub fn apps_for_scheme(&self, scheme: &str) -> Option<Vec<PathBuf>> {
+        self.scheme_apps
+            .get_or_insert_with(scheme, self.ttl, || apps_for_scheme(scheme))
+    }
+
+    /// Cached `app_for_bundle_id`
+    pub fn app_for_bundle_id(&self, bundle_id: &str) -> Option<PathBuf> {
+        self.bundle_ids
+            .get_or_insert_with(bundle_id, self.ttl, || app_for_bundle_id(bundle_id))
+    }
+
+    /// Cached `apps_for_name`
+    pub fn apps_for_name(&self, name: &str) -> Option<Vec<PathBuf>> {
+        self.names
+            .get_or_insert_with(name, self.ttl, || apps_for_name(name))
+    }
+
+    /// Forget the handlers of the given scheme, e.g. after the default browser changed
+    pub fn invalidate_scheme(&self, scheme: &str) {
+        self.scheme_handlers.remove(scheme);
+        self.scheme_apps.remove(scheme);
+    }
+
+    /// Forget the path of the given bundle identifier
+    pub fn invalidate_bundle_id(&self, bundle_id: &str) {
+        self.bundle_ids.remove(bundle_id);
+    }
+
+    /// Forget the apps matching the given name
+    pub fn invalidate_name(&self, name: &str) {
+        self.names.remove(name);
+    }
+
+    /// Forget everything
+    pub fn clear(&self) {
+        self.scheme_handlers.clear();
+        self.scheme_apps.clear();
+        self.bundle_ids.clear();
+        self.names.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memo() {
+        let memo = Memo::new();
+        assert_eq!(memo.get_or_insert_with("a", None, || 1), 1);
+        assert_eq!(memo.get_or_insert_with("a", None, || 2), 1);
+        assert_eq!(
+            memo.get_or_insert_with("a", Some(Duration::from_secs(0)), || 3),
+            3
+        );
+        memo.remove("a");
+        assert_eq!(memo.get_or_insert_with("a", None, || 4), 4);
+        memo.clear();
+        assert_eq!(memo.get_or_insert_with("a", None, || 5), 5);
+    }
+}
@@ -0,0 +1,122 @@
+//! Give up on launches and waits, after a timeout or when asked to.
+
+use crate::macos::{OpenError, OpenResult};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A flag shared with a launch or a wait, cancelling it when set. Clones share the same flag.
+///
+/// ```no_run
+/// use macos_open::{CancelToken, OpenOptions};
+///
+/// let token = CancelToken::new();
+/// let mut options = OpenOptions::new();
+/// options.cancel_token(token.clone()).terminate_on_abort(true);
+/// // from another thread: token.cancel();
+/// options.open_and_wait("/System/Applications/TextEdit.app").unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Create a token not cancelled yet
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Cancel every launch and wait using the token
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Check if the token was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// When to give up on an operation polling for its completion
+#[derive(Debug, Clone)]
+pub(crate) struct Deadline {
+    at: Option<Instant>,
+    cancel: Option<CancelToken>,
+}
+
+impl Deadline {
+    pub(crate) fn new(timeout: Option<Duration>, cancel: Option<&CancelToken>) -> Deadline {
+        Deadline {
+            at: timeout.map(|timeout| Instant::now() + timeout),
+            cancel: cancel.cloned(),
+        }
+    }
+
+    /// The same deadline, bounded by `timeout` from now if it has no timeout of its own
+    pub(crate) fn or_timeout(&self, timeout: Duration) -> Deadline {
+        Deadline {
+            at: self.at.or_else(|| Some(Instant::now() + timeout)),
+            cancel: self.cancel.clone(),
+        }
+    }
+
+    /// Neither a timeout nor a token, the operation can block forever
+    pub(crate) fn is_unbounded(&self) -> bool {
+        self.at.is_none() && self.cancel.is_none()
+    }
+
+    /// Fail with `OpenError::Cancelled` or `OpenError::TimedOut` if it's time to give up
+    pub(crate) fn check(&self) -> OpenResult<()> {
+        if self
+            .cancel
+            .as_ref()
+            .map_or(false, CancelToken::is_cancelled)
+        {
+            return Err(OpenError::Cancelled);
+        }
+        match self.at {
+            Some(at) if Instant::now() >= at => Err(OpenError::TimedOut),
+            _ => Ok(()),
+        }
+    }
+
+    /// How long to wait before polling again, at most `interval`
+    pub(crate) fn step(&self, interval: Duration) -> Duration {
+        match self.at {
+            Some(at) => interval.min(at.saturating_duration_since(Instant::now())),
+            None => interval,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadline() {
+        assert!(Deadline::new(None, None).is_unbounded());
+        assert!(Deadline::new(None, None).check().is_ok());
+
+        let token = CancelToken::new();
+        let deadline = Deadline::new(Some(Duration::from_secs(60)), Some(&token));
+        assert!(deadline.check().is_ok());
+        assert!(deadline.step(Duration::from_millis(100)) <= Duration::from_millis(100));
+        token.clone().cancel();
+        match deadline.check() {
+            Err(OpenError::Cancelled) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        match Deadline::new(Some(Duration::from_secs(0)), None).check() {
+            Err(OpenError::TimedOut) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let unbounded = Deadline::new(None, None).or_timeout(Duration::from_secs(0));
+        assert!(!unbounded.is_unbounded());
+        assert!(deadline.or_timeout(Duration::from_secs(0)).at == deadline.at);
+    }
+}
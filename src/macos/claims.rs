@@ -0,0 +1,250 @@
+//! Acceptance checks answered in memory from the schemes and document types apps declare, for
+//! callers checking thousands of urls against the same apps.
+
+use crate::macos::bundle::{
+    declared_document_types_for_app, declared_schemes_for_app, DocumentType,
+};
+use crate::macos::{MultiOpenable, Openable};
+
+use core_foundation::url::CFURL;
+use launch_services::{can_url_accept_url, LSAcceptanceFlags, LSRolesMask};
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Content types claimed by apps opening any file
+const ANY_FILE_TYPES: &[&str] = &["public.data", "public.item", "public.content"];
+
+/// What a url is judged by: the scheme, or the extension for files
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Scheme(String),
+    Extension(String),
+}
+
+/// The declared schemes and extensions of an app, with the Launch Services answers for the
+/// keys the declarations don't cover
+#[derive(Debug)]
+struct Claims {
+    schemes: HashSet<String>,
+    extensions: HashSet<String>,
+    any_file: bool,
+    answers: HashMap<Key, bool>,
+}
+
+impl Claims {
+    fn of_app(app: &Path) -> Claims {
+        Claims::new(
+            &declared_schemes_for_app(app),
+            &declared_document_types_for_app(app),
+        )
+    }
+
+    /// The claims of the declarations Launch Services honours: types with the `None` role or
+    /// rank don't make the app a handler, and the extensions of a type are ignored when it
+    /// lists content types
+    fn new(schemes: &[String], types: &[DocumentType]) -> Claims {
+        let handled = types
+            .iter()
+            .filter(|doc| doc.role() != Some("None") && doc.rank() != Some("None"))
+            .collect::<Vec<_>>();
+        let extensions = handled
+            .iter()
+            .filter(|doc| doc.content_types().is_empty())
+            .flat_map(|doc| doc.extensions())
+            .map(|ext| ext.to_ascii_lowercase())
+            .collect::<HashSet<_>>();
+        let any_file = extensions.contains("*")
+            || handled.iter().any(|doc| {
+                doc.content_types()
+                    .iter()
+                    .any(|uti| ANY_FILE_TYPES.contains(&uti.as_str()))
+            });
+        Claims {
+            schemes: schemes
+                .iter()
+                .map(|scheme| scheme.to_ascii_lowercase())
+                .collect(),
+            extensions,
+            any_file,
+            answers: HashMap::new(),
+        }
+    }
+
+    /// If the declarations alone say the app accepts the key
+    fn declares(&self, key: &Key) -> bool {
+        match key {
+            Key::Scheme(scheme) => self.schemes.contains(scheme),
+            Key::Extension(ext) => self.any_file || self.extensions.contains(ext),
+        }
+    }
+}
+
+/// The key of a url, `None` for the ones only Launch Services can judge (folders, packages and
+/// files without an extension)
+fn key(url: &CFURL) -> Option<Key> {
+    match url.to_path() {
+        Some(path) => {
+            if path.is_dir() {
+                return None;
+            }
+            let ext = path.extension()?.to_string_lossy().to_ascii_lowercase();
+            Some(Key::Extension(ext))
+        }
+        None => {
+            let raw = url.get_string().to_string();
+            let scheme = raw.split(':').next()?.to_ascii_lowercase();
+            Some(Key::Scheme(scheme))
+        }
+    }
+}
+
+fn ls_accepts(app: &CFURL, url: &CFURL) -> bool {
+    can_url_accept_url(url, app, LSRolesMask::VIEWER, LSAcceptanceFlags::DEFAULT).unwrap_or(false)
+}
+
+/// A faster `app_accept_url` for large batches: the Info.plist of each app is read once and
+/// urls with a declared scheme or extension are accepted without asking Launch Services.
+/// Launch Services is asked once per undeclared scheme or extension and its answer is reused,
+/// folders and files without an extension are always asked.
+///
+/// Answers are never refreshed, `invalidate` an app after it's updated. The cache can be
+/// shared between threads.
+///
+/// ```no_run
+/// use macos_open::AcceptanceCache;
+/// use std::path::Path;
+///
+/// let cache = AcceptanceCache::new();
+/// let safari = Path::new("/Applications/Safari.app");
+/// let urls = (0..10_000)
+///     .map(|i| format!("https://www.example.com/{}", i))
+///     .collect::<Vec<_>>();
+/// assert!(cache.app_accept_urls(safari, &urls));
+/// ```
+#[derive(Debug)]
+pub struct AcceptanceCache {
+    apps: Mutex<HashMap<PathBuf, Claims>>,
+}
+
+impl Default for AcceptanceCache {
+    fn default() -> AcceptanceCache {
+        AcceptanceCache::new()
+    }
+}
+
+impl AcceptanceCache {
+    /// Create an empty cache
+    pub fn new() -> AcceptanceCache {
+        AcceptanceCache {
+            apps: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<PathBuf, Claims>> {
+        // a panicking check can't leave the map half updated
+        self.apps
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn accepts(&self, app: &Path, app_url: &CFURL, url: &CFURL) -> bool {
+        let key = match key(url) {
+            Some(key) => key,
+            None => return ls_accepts(app_url, url),
+        };
+
+        if !self.lock().contains_key(app) {
+            // reading the Info.plist is slow, don't keep the other apps locked in the meantime
+            let claims = Claims::of_app(app);
+            self.lock().entry(app.to_path_buf()).or_insert(claims);
+        }
+        if let Some(claims) = self.lock().get(app) {
+            if claims.declares(&key) {
+                return true;
+            }
+            if let Some(&answer) = claims.answers.get(&key) {
+                return answer;
+            }
+        }
+
+        let answer = ls_accepts(app_url, url);
+        if let Some(claims) = self.lock().get_mut(app) {
+            claims.answers.insert(key, answer);
+        }
+        answer
+    }
+
+    /// Check if the app can handle the given url
+    pub fn app_accept_url<T: Openable + ?Sized>(&self, app: &Path, url: &T) -> bool {
+        match (CFURL::from_path(app, true), Openable::into_openable(url)) {
+            (Some(app_url), Some(url)) => self.accepts(app, &app_url, &url),
+            _ => false,
+        }
+    }
+
+    /// Check if the app can handle all the given urls, stopping at the first one it can't
+    pub fn app_accept_urls<T: MultiOpenable + ?Sized>(&self, app: &Path, urls: &T) -> bool {
+        match (
+            CFURL::from_path(app, true),
+            MultiOpenable::into_openable(urls),
+        ) {
+            (Some(app_url), Some(urls)) => urls.iter().all(|url| self.accepts(app, &app_url, &url)),
+            _ => false,
+        }
+    }
+
+    /// Forget what's known of the app, e.g. after it's updated
+    pub fn invalidate(&self, app: &Path) {
+        self.lock().remove(app);
+    }
+
+    /// Forget everything
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acceptance_cache() {
+        let cache = AcceptanceCache::new();
+        let safari = Path::new("/Applications/Safari.app");
+        let urls = (0..100)
+            .map(|i| format!("https://www.example.com/{}", i))
+            .collect::<Vec<_>>();
+        assert!(cache.app_accept_urls(safari, &urls));
+        assert!(!cache.app_accept_url(safari, "mailto:someone@example.com"));
+        assert!(!cache.app_accept_url(safari, "mailto:other@example.com"));
+        assert_eq!(
+            cache.lock()[safari]
+                .answers
+                .get(&Key::Scheme("mailto".to_string())),
+            Some(&false)
+        );
+
+        cache.invalidate(safari);
+        assert!(cache.lock().is_empty());
+    }
+
+    #[test]
+    fn test_claims_new() {
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let types = [
+            DocumentType::new(None, None, strings(&["public.text"]), strings(&["txt"])),
+            DocumentType::new(None, None, Vec::new(), strings(&["MD"])),
+            DocumentType::new(Some("None"), None, Vec::new(), strings(&["pdf"])),
+            DocumentType::new(None, Some("None"), strings(&["public.data"]), Vec::new()),
+        ];
+        let claims = Claims::new(&strings(&["HTTPS"]), &types);
+        assert!(claims.declares(&Key::Scheme("https".to_string())));
+        assert!(claims.declares(&Key::Extension("md".to_string())));
+        assert!(!claims.declares(&Key::Extension("txt".to_string())));
+        assert!(!claims.declares(&Key::Extension("pdf".to_string())));
+        assert!(!claims.any_file);
+    }
+}
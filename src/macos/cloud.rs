@@ -0,0 +1,176 @@
+//! iCloud Drive and File Provider items, which may be dataless placeholders until downloaded.
+
+use crate::macos::cancel::Deadline;
+use crate::macos::ns::{to_string, AutoreleasePool, Id};
+use crate::macos::{OpenError, OpenResult};
+
+use core_foundation::array::CFArray;
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::boolean::CFBoolean;
+use core_foundation::error::{CFError, CFErrorRef};
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation::url::{CFURLRef, CFURL};
+use core_foundation_sys::base::Boolean;
+use objc::runtime::{BOOL, NO};
+
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+const DOWNLOAD_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    static kCFURLIsUbiquitousItemKey: CFStringRef;
+    static kCFURLUbiquitousItemDownloadingStatusKey: CFStringRef;
+    static kCFURLUbiquitousItemDownloadingStatusNotDownloaded: CFStringRef;
+    static kCFURLUbiquitousItemDownloadingStatusCurrent: CFStringRef;
+
+    fn CFURLCopyResourcePropertyForKey(
+        url: CFURLRef,
+        key: CFStringRef,
+        propertyValueTypeRefPtr: *mut CFTypeRef,
+        error: *mut CFErrorRef,
+    ) -> Boolean;
+}
+
+/// Where the content of a file lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DownloadStatus {
+    /// A plain local file, not synced by iCloud Drive or a File Provider
+    Local,
+    /// A dataless placeholder, the content must be downloaded before it can be read
+    NotDownloaded,
+    /// A local copy which may be older than the one in the cloud
+    Downloaded,
+    /// A local copy of the latest version
+    Current,
+}
+
+fn download_error(error: CFErrorRef) -> OpenError {
+    if error.is_null() {
+        return OpenError::Download {
+            code: 0,
+            message: "unknown error".to_string(),
+        };
+    }
+    let error = unsafe { CFError::wrap_under_create_rule(error) };
+    OpenError::Download {
+        code: error.code() as i64,
+        message: error.description().to_string(),
+    }
+}
+
+fn resource_property(url: &CFURL, key: CFStringRef) -> OpenResult<Option<CFType>> {
+    let mut value: CFTypeRef = std::ptr::null();
+    let mut error: CFErrorRef = std::ptr::null_mut();
+    let ok = unsafe {
+        CFURLCopyResourcePropertyForKey(url.as_concrete_TypeRef(), key, &mut value, &mut error)
+    };
+    if ok == 0 {
+        Err(download_error(error))
+    } else if value.is_null() {
+        Ok(None)
+    } else {
+        Ok(Some(unsafe { CFType::wrap_under_create_rule(value) }))
+    }
+}
+
+/// Check if the file at the given path is in the cloud and if it has been downloaded
+pub fn download_status(path: &Path) -> OpenResult<DownloadStatus> {
+    let url = CFURL::from_path(path, path.is_dir()).ok_or(OpenError::InvalidUrl)?;
+    let ubiquitous = resource_property(&url, unsafe { kCFURLIsUbiquitousItemKey })?
+        .and_then(|value| value.downcast::<CFBoolean>())
+        .map_or(false, bool::from);
+    if !ubiquitous {
+        return Ok(DownloadStatus::Local);
+    }
+
+    let status = resource_property(&url, unsafe { kCFURLUbiquitousItemDownloadingStatusKey })?
+        .and_then(|value| value.downcast::<CFString>());
+    let is = |expected: CFStringRef| {
+        status.as_ref().map_or(false, |status| {
+            *status == unsafe { CFString::wrap_under_get_rule(expected) }
+        })
+    };
+    let status = if is(unsafe { kCFURLUbiquitousItemDownloadingStatusNotDownloaded }) {
+        DownloadStatus::NotDownloaded
+    } else if is(unsafe { kCFURLUbiquitousItemDownloadingStatusCurrent }) {
+        DownloadStatus::Current
+    } else {
+        DownloadStatus::Downloaded
+    };
+    Ok(status)
+}
+
+/// Ask the system to download the content of a placeholder
+fn start_download(url: &CFURL) -> OpenResult<()> {
+    let _pool = AutoreleasePool::new();
+    let mut error: Id = std::ptr::null_mut();
+    let started: BOOL = unsafe {
+        let manager: Id = msg_send![class!(NSFileManager), defaultManager];
+        msg_send![
+            manager,
+            startDownloadingUbiquitousItemAtURL: url.as_concrete_TypeRef() as Id
+            error: &mut error
+        ]
+    };
+    if started != NO {
+        return Ok(());
+    }
+    Err(if error.is_null() {
+        OpenError::Download {
+            code: 0,
+            message: "unknown error".to_string(),
+        }
+    } else {
+        unsafe {
+            let code: isize = msg_send![error, code];
+            let message: Id = msg_send![error, localizedDescription];
+            OpenError::Download {
+                code: code as i64,
+                message: to_string(message).unwrap_or_default(),
+            }
+        }
+    })
+}
+
+/// Download the placeholders among the urls, then wait for every one of them to be local
+pub(crate) fn materialize(urls: &CFArray<CFURL>, deadline: &Deadline) -> OpenResult<()> {
+    let mut pending = Vec::new();
+    for url in urls.iter() {
+        if let Some(path) = url.to_path() {
+            if download_status(&path)? == DownloadStatus::NotDownloaded {
+                start_download(&url)?;
+                pending.push(path);
+            }
+        }
+    }
+
+    while !pending.is_empty() {
+        deadline.check()?;
+        thread::sleep(deadline.step(DOWNLOAD_POLL_INTERVAL));
+        let mut still = Vec::with_capacity(pending.len());
+        for path in pending {
+            if download_status(&path)? == DownloadStatus::NotDownloaded {
+                still.push(path);
+            }
+        }
+        pending = still;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_status_local() {
+        assert_eq!(
+            download_status(Path::new("/etc/hosts")).unwrap(),
+            DownloadStatus::Local
+        );
+    }
+}
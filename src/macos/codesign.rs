@@ -0,0 +1,261 @@
+//! Code signature verification through the Security framework.
+
+use crate::macos::bundle::{dictionary_array, dictionary_string};
+use crate::macos::{OpenError, OpenResult};
+
+use core_foundation::base::{CFTypeRef, TCFType};
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation::url::{CFURLRef, CFURL};
+use core_foundation_sys::base::{CFRelease, OSStatus};
+
+use std::path::Path;
+
+type SecStaticCodeRef = CFTypeRef;
+type SecRequirementRef = CFTypeRef;
+
+const K_SEC_CS_DEFAULT_FLAGS: u32 = 0;
+const K_SEC_CS_SIGNING_INFORMATION: u32 = 1 << 1;
+const K_SEC_CS_CHECK_ALL_ARCHITECTURES: u32 = 1 << 0;
+/// The code is signed but doesn't satisfy the requirement
+const ERR_SEC_CS_REQ_FAILED: OSStatus = -67050;
+
+#[link(name = "Security", kind = "framework")]
+extern "C" {
+    static kSecCodeInfoIdentifier: CFStringRef;
+    static kSecCodeInfoTeamIdentifier: CFStringRef;
+    static kSecCodeInfoCertificates: CFStringRef;
+
+    fn SecStaticCodeCreateWithPath(
+        path: CFURLRef,
+        flags: u32,
+        staticCode: *mut SecStaticCodeRef,
+    ) -> OSStatus;
+    fn SecRequirementCreateWithString(
+        text: CFStringRef,
+        flags: u32,
+        requirement: *mut SecRequirementRef,
+    ) -> OSStatus;
+    fn SecStaticCodeCheckValidity(
+        staticCode: SecStaticCodeRef,
+        flags: u32,
+        requirement: SecRequirementRef,
+    ) -> OSStatus;
+    fn SecCodeCopySigningInformation(
+        code: SecStaticCodeRef,
+        flags: u32,
+        information: *mut CFDictionaryRef,
+    ) -> OSStatus;
+    fn SecCertificateCopySubjectSummary(certificate: CFTypeRef) -> CFStringRef;
+}
+
+/// A retained Security object, released when dropped
+struct Owned(CFTypeRef);
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { CFRelease(self.0) };
+        }
+    }
+}
+
+fn check(code: OSStatus) -> OpenResult<()> {
+    match code {
+        0 => Ok(()),
+        code => Err(OpenError::Status(code)),
+    }
+}
+
+fn create_requirement(text: &str) -> OpenResult<Owned> {
+    let text = CFString::new(text);
+    let mut requirement: SecRequirementRef = std::ptr::null();
+    check(unsafe {
+        SecRequirementCreateWithString(text.as_concrete_TypeRef(), 0, &mut requirement)
+    })?;
+    Ok(Owned(requirement))
+}
+
+fn key(key: CFStringRef) -> String {
+    unsafe { CFString::wrap_under_get_rule(key) }.to_string()
+}
+
+/// The signature of a verified app
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureInfo {
+    identifier: Option<String>,
+    team_id: Option<String>,
+    signing_identity: Option<String>,
+    notarized: bool,
+}
+
+impl SignatureInfo {
+    /// The signing identifier, usually the bundle identifier
+    pub fn identifier(&self) -> Option<&str> {
+        self.identifier.as_ref().map(String::as_str)
+    }
+
+    /// The team identifier of the developer, `None` for ad-hoc and Apple signatures
+    pub fn team_id(&self) -> Option<&str> {
+        self.team_id.as_ref().map(String::as_str)
+    }
+
+    /// The summary of the leaf certificate, e.g. `Developer ID Application: Name (TEAMID)`
+    pub fn signing_identity(&self) -> Option<&str> {
+        self.signing_identity.as_ref().map(String::as_str)
+    }
+
+    /// If Apple notarized the app
+    pub fn is_notarized(&self) -> bool {
+        self.notarized
+    }
+}
+
+/// Verify the code signature of the app at the given path, optionally against a code
+/// requirement (e.g. `anchor apple generic and certificate leaf[subject.OU] = "ABCD1234"`)
+pub fn verify_signature(path: &Path, requirement: Option<&str>) -> OpenResult<SignatureInfo> {
+    let url = CFURL::from_path(path, true).ok_or(OpenError::InvalidApp)?;
+    let mut code: SecStaticCodeRef = std::ptr::null();
+    check(unsafe {
+        SecStaticCodeCreateWithPath(url.as_concrete_TypeRef(), K_SEC_CS_DEFAULT_FLAGS, &mut code)
+    })?;
+    let code = Owned(code);
+
+    let requirement = match requirement {
+        Some(text) => create_requirement(text)?,
+        None => Owned(std::ptr::null()),
+    };
+    let status = unsafe {
+        SecStaticCodeCheckValidity(code.0, K_SEC_CS_CHECK_ALL_ARCHITECTURES, requirement.0)
+    };
+    if status != 0 {
+        return Err(OpenError::InvalidSignature {
+            app: path.display().to_string(),
+            status,
+        });
+    }
+
+    let notarized = {
+        let notarized = create_requirement("notarized")?;
+        unsafe { SecStaticCodeCheckValidity(code.0, K_SEC_CS_DEFAULT_FLAGS, notarized.0) == 0 }
+    };
+
+    let mut info: CFDictionaryRef = std::ptr::null();
+    check(unsafe {
+        SecCodeCopySigningInformation(code.0, K_SEC_CS_SIGNING_INFORMATION, &mut info)
+    })?;
+    let info: CFDictionary = unsafe { CFDictionary::wrap_under_create_rule(info) };
+
+    let signing_identity = dictionary_array(&info, &key(unsafe { kSecCodeInfoCertificates }))
+        .first()
+        .and_then(|certificate| {
+            let summary = unsafe { SecCertificateCopySubjectSummary(certificate.as_CFTypeRef()) };
+            if summary.is_null() {
+                None
+            } else {
+                Some(unsafe { CFString::wrap_under_create_rule(summary) }.to_string())
+            }
+        });
+
+    Ok(SignatureInfo {
+        identifier: dictionary_string(&info, &key(unsafe { kSecCodeInfoIdentifier })),
+        team_id: dictionary_string(&info, &key(unsafe { kSecCodeInfoTeamIdentifier })),
+        signing_identity,
+        notarized,
+    })
+}
+
+/// If the team identifier has the shape Apple assigns, 10 uppercase letters or digits
+fn is_team_id(team_id: &str) -> bool {
+    team_id.len() == 10
+        && team_id
+            .bytes()
+            .all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit())
+}
+
+/// Verify the signature of the app and check it's signed by the given team through a
+/// certificate Apple issued, the team identifier of self-signed and ad-hoc signatures can't
+/// be trusted
+pub(crate) fn verify_team_id(app: &Path, team_id: &str) -> OpenResult<()> {
+    if !is_team_id(team_id) {
+        return Err(OpenError::InvalidTeamId(team_id.to_string()));
+    }
+
+    let requirement = format!(
+        "anchor apple generic and certificate leaf[subject.OU] = \"{}\"",
+        team_id
+    );
+    match verify_signature(app, Some(&requirement)) {
+        Ok(_) => Ok(()),
+        Err(OpenError::InvalidSignature {
+            status: ERR_SEC_CS_REQ_FAILED,
+            ..
+        }) => Err(OpenError::UntrustedTeam {
+            app: app.display().to_string(),
+            team_id: verify_signature(app, None)
+                .ok()
+                .and_then(|info| info.team_id),
+        }),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_signature() {
+        let info =
+            verify_signature(Path::new("/System/Applications/Calculator.app"), None).unwrap();
+        assert_eq!(info.identifier(), Some("com.apple.calculator"));
+        assert_eq!(info.team_id(), None);
+
+        match verify_signature(
+            Path::new("/System/Applications/Calculator.app"),
+            Some("certificate leaf[subject.OU] = \"ABCD1234\""),
+        ) {
+            Err(OpenError::InvalidSignature { .. }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_verify_team_id() {
+        use std::process::Command;
+
+        match verify_team_id(Path::new("/System/Applications/Calculator.app"), "abcd1234") {
+            Err(OpenError::InvalidTeamId(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // an ad-hoc signature has no certificate from Apple, whatever it claims
+        let dir = std::env::temp_dir().join(format!("macos-open-adhoc-{}", std::process::id()));
+        let app = dir.join("AdHoc.app");
+        let macos = app.join("Contents").join("MacOS");
+        std::fs::create_dir_all(&macos).unwrap();
+        std::fs::write(
+            app.join("Contents").join("Info.plist"),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\"><dict>\
+             <key>CFBundleExecutable</key><string>true</string>\
+             <key>CFBundleIdentifier</key><string>org.example.adhoc</string>\
+             </dict></plist>",
+        )
+        .unwrap();
+        std::fs::copy("/usr/bin/true", macos.join("true")).unwrap();
+        let signed = Command::new("/usr/bin/codesign")
+            .args(["--force", "--sign", "-"])
+            .arg(&app)
+            .status()
+            .unwrap();
+        assert!(signed.success());
+
+        assert!(verify_signature(&app, None).is_ok());
+        let result = verify_team_id(&app, "ABCD123456");
+        std::fs::remove_dir_all(&dir).unwrap();
+        match result {
+            Err(OpenError::UntrustedTeam { team_id: None, .. }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
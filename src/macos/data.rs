@@ -0,0 +1,167 @@
+//! Open in-memory content by writing it to a temporary file of the right type.
+
+use crate::macos::{LaunchedApp, OpenError, OpenResult, Opener};
+
+use core_foundation::base::TCFType;
+use core_foundation::string::{CFString, CFStringRef};
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    static kUTTagClassFilenameExtension: CFStringRef;
+
+    fn UTTypeCopyPreferredTagWithClass(inUTI: CFStringRef, inTagClass: CFStringRef) -> CFStringRef;
+}
+
+/// The preferred file extension of the uniform type identifier (e.g. `pdf` for
+/// `com.adobe.pdf`)
+fn extension_for_uti(uti: &str) -> Option<String> {
+    let uti = CFString::new(uti);
+    let extension = unsafe {
+        UTTypeCopyPreferredTagWithClass(uti.as_concrete_TypeRef(), kUTTagClassFilenameExtension)
+    };
+    if extension.is_null() {
+        None
+    } else {
+        Some(unsafe { CFString::wrap_under_create_rule(extension) }.to_string())
+    }
+}
+
+/// The name of the temporary file: the hint without its directories (`document` without a
+/// hint), with the extension appended unless it already has it
+fn file_name(extension: Option<&str>, hint: Option<&str>) -> Option<String> {
+    let name = hint
+        .and_then(|hint| Path::new(hint).file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "document".to_string());
+    let current = Path::new(&name)
+        .extension()
+        .map(|ext| ext.to_string_lossy());
+
+    match (extension, current) {
+        (Some(extension), Some(ref current)) if current.eq_ignore_ascii_case(extension) => {
+            Some(name.clone())
+        }
+        (Some(extension), _) => Some(format!("{}.{}", name, extension)),
+        (None, Some(_)) => Some(name.clone()),
+        (None, None) => None,
+    }
+}
+
+/// A private directory in the temporary folder, so the file keeps the name it's given
+fn create_temp_dir() -> io::Result<PathBuf> {
+    loop {
+        let dir = std::env::temp_dir().join(format!(
+            "macos-open-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        match fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// A temporary file opened by `open_data`, deleted when dropped. The handler may still be
+/// reading it right after the launch, `wait` keeps it until the handler exits.
+#[derive(Debug)]
+pub struct TempDocument {
+    path: PathBuf,
+    app: LaunchedApp,
+}
+
+impl TempDocument {
+    /// The path of the temporary file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The app which opened the file
+    pub fn app(&self) -> &LaunchedApp {
+        &self.app
+    }
+
+    /// Block until the app which opened the file exits, then delete it
+    pub fn wait(self) {
+        self.app.wait();
+    }
+}
+
+impl Drop for TempDocument {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        if let Some(dir) = self.path.parent() {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+}
+
+/// Write the data to a temporary file with the extension of the uniform type identifier (and
+/// named after the hint, if any), then open it with its default handler
+///
+/// ```no_run
+/// use macos_open::open_data;
+///
+/// let document = open_data(b"Hello, world!", "public.plain-text", Some("hello")).unwrap();
+/// document.wait();
+/// ```
+pub fn open_data(bytes: &[u8], uti: &str, filename_hint: Option<&str>) -> OpenResult<TempDocument> {
+    let extension = extension_for_uti(uti);
+    let name = file_name(extension.as_deref(), filename_hint)
+        .ok_or_else(|| OpenError::UnknownType(uti.to_string()))?;
+
+    let dir = create_temp_dir().map_err(OpenError::Io)?;
+    let path = dir.join(name);
+    let opened = fs::File::create(&path)
+        .and_then(|mut file| file.write_all(bytes))
+        .map_err(OpenError::Io)
+        .and_then(|()| Opener::global().open(&path));
+    match opened {
+        Ok(app) => Ok(TempDocument { path, app }),
+        Err(err) => {
+            let _ = fs::remove_file(&path);
+            let _ = fs::remove_dir(&dir);
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_file_name() {
+        assert_eq!(
+            file_name(Some("pdf"), None),
+            Some("document.pdf".to_string())
+        );
+        assert_eq!(
+            file_name(Some("pdf"), Some("../report.PDF")),
+            Some("report.PDF".to_string())
+        );
+        assert_eq!(
+            file_name(Some("txt"), Some("notes.v2")),
+            Some("notes.v2.txt".to_string())
+        );
+        assert_eq!(
+            file_name(None, Some("image.png")),
+            Some("image.png".to_string())
+        );
+        assert_eq!(file_name(None, Some("image")), None);
+    }
+
+    #[test]
+    fn test_extension_for_uti() {
+        assert_eq!(extension_for_uti("com.adobe.pdf"), Some("pdf".to_string()));
+        assert_eq!(extension_for_uti("org.example.nothing"), None);
+    }
+}
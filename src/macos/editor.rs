@@ -0,0 +1,140 @@
+//! Open text files at a given position with the editors which support it.
+
+use crate::macos::bundle::bundle_identifier;
+use crate::macos::{OpenError, OpenResult, Opener};
+
+use core_foundation::url::CFURL;
+use launch_services::{default_application_url_for_url, LSRolesMask};
+use url::form_urlencoded::byte_serialize;
+use url::Url;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Editors which can be asked to jump to a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Editor {
+    /// `<scheme>://file/<path>:<line>:<column>`
+    VSCode(&'static str),
+    /// `<scheme>://open?url=<file url>&line=<line>&column=<column>`
+    TextMateLike(&'static str),
+    /// `xed --line <line> <path>`
+    Xcode,
+}
+
+impl Editor {
+    fn from_bundle_id(bundle_id: &str) -> Option<Editor> {
+        Some(match bundle_id {
+            "com.microsoft.VSCode" => Editor::VSCode("vscode"),
+            "com.microsoft.VSCodeInsiders" => Editor::VSCode("vscode-insiders"),
+            "com.vscodium" => Editor::VSCode("vscodium"),
+            "com.sublimetext.3" | "com.sublimetext.4" => Editor::TextMateLike("subl"),
+            "com.macromates.TextMate" | "com.macromates.TextMate.preview" => {
+                Editor::TextMateLike("txmt")
+            }
+            "com.barebones.bbedit" => Editor::TextMateLike("x-bbedit"),
+            "com.apple.dt.Xcode" => Editor::Xcode,
+            _ => return None,
+        })
+    }
+
+    /// The url which opens the file at the position, `None` if the editor has no such url
+    fn position_url(self, path: &Path, line: u32, column: u32) -> Option<String> {
+        let file = Url::from_file_path(path).ok()?;
+        match self {
+            Editor::VSCode(scheme) => Some(format!(
+                "{}://file{}:{}:{}",
+                scheme,
+                file.path(),
+                line,
+                column
+            )),
+            Editor::TextMateLike(scheme) => Some(format!(
+                "{}://open?url={}&line={}&column={}",
+                scheme,
+                byte_serialize(file.as_str().as_bytes()).collect::<String>(),
+                line,
+                column
+            )),
+            Editor::Xcode => None,
+        }
+    }
+}
+
+fn absolute(path: &Path) -> OpenResult<PathBuf> {
+    if path.is_relative() {
+        path.canonicalize().map_err(|_| OpenError::InvalidUrl)
+    } else {
+        Ok(path.to_path_buf())
+    }
+}
+
+/// Open the file with its default editor at the given line and column (both starting at 1).
+/// Editors which can't jump to a position just open the file.
+pub fn open_at(path: &Path, line: u32, column: u32) -> OpenResult<Option<PathBuf>> {
+    let path = absolute(path)?;
+    let url = CFURL::from_path(&path, false).ok_or(OpenError::InvalidUrl)?;
+    let app = default_application_url_for_url(&url, LSRolesMask::all())
+        .ok()
+        .and_then(|app| app.to_path())
+        .ok_or_else(|| OpenError::AppNotFound(path.display().to_string()))?;
+    open_at_with(&app, &path, line, column)
+}
+
+/// Open the file with the given editor at the given line and column (both starting at 1).
+/// Editors which can't jump to a position just open the file.
+pub fn open_at_with(
+    app: &Path,
+    path: &Path,
+    line: u32,
+    column: u32,
+) -> OpenResult<Option<PathBuf>> {
+    let path = absolute(path)?;
+    let editor = bundle_identifier(app).and_then(|bundle_id| Editor::from_bundle_id(&bundle_id));
+
+    match editor {
+        Some(Editor::Xcode) => {
+            let status = Command::new("/usr/bin/xed")
+                .arg("--line")
+                .arg(line.to_string())
+                .arg(&path)
+                .status();
+            if let Ok(status) = status {
+                if status.success() {
+                    return Ok(Some(app.to_path_buf()));
+                }
+            }
+        }
+        Some(editor) => {
+            if let Some(url) = editor.position_url(&path, line, column) {
+                let mut options = Opener::global().options().clone();
+                return Ok(options.app(app).open(&url)?.into_path());
+            }
+        }
+        None => (),
+    }
+
+    let mut options = Opener::global().options().clone();
+    Ok(options.app(app).open(&path)?.into_path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_url() {
+        let path = Path::new("/tmp/my file.rs");
+        assert_eq!(
+            Editor::VSCode("vscode").position_url(path, 12, 3),
+            Some("vscode://file/tmp/my%20file.rs:12:3".to_string())
+        );
+        assert_eq!(
+            Editor::TextMateLike("txmt").position_url(path, 12, 3),
+            Some(
+                "txmt://open?url=file%3A%2F%2F%2Ftmp%2Fmy%2520file.rs&line=12&column=3".to_string()
+            )
+        );
+        assert_eq!(Editor::Xcode.position_url(path, 12, 3), None);
+    }
+}
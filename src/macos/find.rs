@@ -0,0 +1,342 @@
+//! Search installed apps by their Spotlight and Info.plist metadata.
+
+use crate::macos::bundle::{dictionary_string, info_dictionary};
+use crate::macos::rank::rank_apps;
+use crate::macos::version::Version;
+use crate::macos::{spotlight_paths, AppInfo, Architecture, NameMatch, OpenResult};
+
+use core_foundation::array::{CFArray, CFArrayRef};
+use core_foundation::base::TCFType;
+use core_foundation::url::CFURL;
+use core_foundation_sys::base::OSStatus;
+use libc::{c_char, c_void};
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const APPS_QUERY: &str = "kMDItemContentTypeTree == \"com.apple.application\"c";
+/// Where apps are looked for when Spotlight has nothing (e.g. indexing is disabled)
+const APPLICATIONS_DIRS: [&str; 2] = ["/Applications", "/System/Applications"];
+/// Where the apps bundled with the system which aren't meant to be launched by hand live
+const SYSTEM_APP_DIRS: [&str; 2] = ["/System/Library", "/Library/Apple"];
+/// The bundles whose contents are private to them, the apps inside aren't listed
+const BUNDLE_EXTENSIONS: [&str; 5] = ["app", "appex", "bundle", "framework", "xpc"];
+
+/// `_LSCopyAllApplicationURLs`, the Launch Services enumeration of the registered apps
+type CopyAllApplicationUrls = unsafe extern "C" fn(*mut CFArrayRef) -> OSStatus;
+
+/// Filters for `find_apps`, in the same fashion of `OpenOptions`. An empty filter matches every
+/// installed app.
+///
+/// ```no_run
+/// use macos_open::{find_apps, AppFilter};
+///
+/// let ides = find_apps(AppFilter::new().category("public.app-category.developer-tools"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AppFilter {
+    category: Option<String>,
+    system_version: Option<String>,
+    arch: Option<Architecture>,
+    used_within: Option<Duration>,
+}
+
+impl AppFilter {
+    /// Create a filter matching every installed app
+    pub fn new() -> AppFilter {
+        AppFilter::default()
+    }
+
+    /// Only apps in the given Mac App Store category, given by name (e.g. `Developer Tools`) or
+    /// by type (e.g. `public.app-category.developer-tools`)
+    pub fn category<S: Into<String>>(&mut self, category: S) -> &mut AppFilter {
+        self.category = Some(category.into());
+        self
+    }
+
+    /// Only apps which can run on the given macOS version, the ones whose
+    /// LSMinimumSystemVersion is at most `version`
+    pub fn system_version<S: Into<String>>(&mut self, version: S) -> &mut AppFilter {
+        self.system_version = Some(version.into());
+        self
+    }
+
+    /// Only apps with an executable for the given architecture
+    pub fn arch(&mut self, arch: Architecture) -> &mut AppFilter {
+        self.arch = Some(arch);
+        self
+    }
+
+    /// Only apps used within the given time
+    pub fn used_within(&mut self, duration: Duration) -> &mut AppFilter {
+        self.used_within = Some(duration);
+        self
+    }
+
+    fn query(&self) -> String {
+        let mut query = String::from(APPS_QUERY);
+        if let Some(ref category) = self.category {
+            let category = NameMatch::Exact.pattern(category);
+            query.push_str(&format!(
+                " && (kMDItemAppStoreCategory == \"{0}\"cd || kMDItemAppStoreCategoryType == \"{0}\"c)",
+                category
+            ));
+        }
+        if let Some(arch) = self.arch {
+            query.push_str(&format!(
+                " && kMDItemExecutableArchitectures == \"{}\"",
+                arch.name()
+            ));
+        }
+        if let Some(duration) = self.used_within {
+            query.push_str(&format!(
+                " && kMDItemLastUsedDate >= $time.now(-{})",
+                duration.as_secs()
+            ));
+        }
+        query
+    }
+
+    fn runs_on_system_version(&self, app: &Path) -> bool {
+        let version = match self.system_version {
+            Some(ref version) => version,
+            None => return true,
+        };
+        let minimum = info_dictionary(app)
+            .and_then(|info| dictionary_string(&info, "LSMinimumSystemVersion"));
+        match minimum {
+            Some(minimum) => match (Version::parse(&minimum), Version::parse(version)) {
+                (Some(minimum), Some(version)) => minimum <= version,
+                _ => true,
+            },
+            None => true,
+        }
+    }
+}
+
+/// Find the installed apps matching all the given filters, ranked like `apps_for_bundle_id`
+pub fn find_apps(filter: &AppFilter) -> OpenResult<Vec<PathBuf>> {
+    let apps = spotlight_paths(&filter.query())?
+        .into_iter()
+        .filter(|app| filter.runs_on_system_version(app))
+        .collect();
+    Ok(rank_apps(apps))
+}
+
+/// Which apps `installed_apps` lists, in the same fashion of `OpenOptions`. By default only
+/// the apps meant to be launched by the user, the ones in `/Applications`,
+/// `/System/Applications`, the user's Applications folder and anywhere else Spotlight finds
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AppScope {
+    system: bool,
+    extensions: bool,
+}
+
+impl AppScope {
+    /// Create a scope with the user facing apps only
+    pub fn new() -> AppScope {
+        AppScope::default()
+    }
+
+    /// Include the apps bundled with the system in `/System/Library` and `/Library/Apple`,
+    /// e.g. the Finder and the background agents
+    pub fn system(&mut self, system: bool) -> &mut AppScope {
+        self.system = system;
+        self
+    }
+
+    /// Include the app extensions (`.appex`) embedded in the listed apps
+    pub fn extensions(&mut self, extensions: bool) -> &mut AppScope {
+        self.extensions = extensions;
+        self
+    }
+}
+
+fn is_system_app(path: &Path) -> bool {
+    SYSTEM_APP_DIRS.iter().any(|dir| path.starts_with(dir))
+}
+
+/// If the app lives inside another bundle, e.g. the helpers of an app or Simulator in Xcode
+fn is_nested(path: &Path) -> bool {
+    path.ancestors().skip(1).any(|dir| {
+        matches!(
+            dir.extension().and_then(OsStr::to_str),
+            Some(ext) if BUNDLE_EXTENSIONS.contains(&ext)
+        )
+    })
+}
+
+/// The apps registered with Launch Services. The function listing them is private, it's
+/// looked up at runtime and `None` is returned where it's missing.
+fn launch_services_apps() -> Option<Vec<PathBuf>> {
+    let name = b"_LSCopyAllApplicationURLs\0";
+    let symbol = unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr() as *const c_char) };
+    if symbol.is_null() {
+        return None;
+    }
+    let copy_all = unsafe { std::mem::transmute::<*mut c_void, CopyAllApplicationUrls>(symbol) };
+    let mut urls: CFArrayRef = std::ptr::null();
+    if unsafe { copy_all(&mut urls) } != 0 || urls.is_null() {
+        return None;
+    }
+    let urls = unsafe { CFArray::<CFURL>::wrap_under_create_rule(urls) };
+    Some(urls.iter().filter_map(|url| url.to_path()).collect())
+}
+
+/// Collect the bundles with the extension in the folder, looking in the subfolders which
+/// aren't bundles up to the given depth
+fn bundles_in(dir: &Path, extension: &str, depth: usize, bundles: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if !path.is_dir() {
+            continue;
+        }
+        if path.extension() == Some(OsStr::new(extension)) {
+            bundles.push(path);
+        } else if depth > 0 {
+            bundles_in(&path, extension, depth - 1, bundles);
+        }
+    }
+}
+
+/// The apps registered with Launch Services, else the ones Spotlight knows, else the ones in
+/// the standard folders
+fn app_paths(scope: &AppScope) -> OpenResult<Vec<PathBuf>> {
+    match launch_services_apps() {
+        Some(apps) if !apps.is_empty() => return Ok(apps),
+        _ => (),
+    }
+    let apps = spotlight_paths(APPS_QUERY)?;
+    if !apps.is_empty() {
+        return Ok(apps);
+    }
+
+    let mut dirs: Vec<PathBuf> = APPLICATIONS_DIRS.iter().map(PathBuf::from).collect();
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(Path::new(&home).join("Applications"));
+    }
+    if scope.system {
+        dirs.push(PathBuf::from("/System/Library/CoreServices"));
+    }
+    let mut apps = Vec::new();
+    for dir in dirs {
+        bundles_in(&dir, "app", 2, &mut apps);
+    }
+    Ok(apps)
+}
+
+/// The apps of the scope among the ones found, without the duplicates and the apps nested
+/// in other bundles
+fn listed(scope: &AppScope, found: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    found
+        .into_iter()
+        .filter(|path| !is_nested(path))
+        .filter(|path| scope.system || !is_system_app(path))
+        .filter(|path| seen.insert(path.clone()))
+        .collect()
+}
+
+/// List the installed apps in the scope, sorted by name. The apps registered with Launch
+/// Services are listed where the system lets it enumerate them, else Spotlight is asked,
+/// and the standard Applications folders are scanned if it finds nothing. The apps inside
+/// other bundles (helpers, or Simulator in Xcode) aren't listed.
+pub fn installed_apps(scope: &AppScope) -> OpenResult<Vec<AppInfo>> {
+    let mut paths = listed(scope, app_paths(scope)?);
+    if scope.extensions {
+        let mut extensions = Vec::new();
+        for app in &paths {
+            bundles_in(&app.join("Contents/PlugIns"), "appex", 0, &mut extensions);
+        }
+        paths.extend(extensions);
+    }
+
+    let mut apps: Vec<AppInfo> = paths.into_iter().map(AppInfo::from_path).collect();
+    apps.sort_by(|a, b| {
+        a.name()
+            .to_lowercase()
+            .cmp(&b.name().to_lowercase())
+            .then_with(|| a.path().cmp(b.path()))
+    });
+    Ok(apps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_filter_query() {
+        assert_eq!(
+            AppFilter::new()
+                .arch(Architecture::Arm64)
+                .used_within(Duration::from_secs(3600))
+                .query(),
+            "kMDItemContentTypeTree == \"com.apple.application\"c \
+             && kMDItemExecutableArchitectures == \"arm64\" \
+             && kMDItemLastUsedDate >= $time.now(-3600)"
+        );
+    }
+
+    #[test]
+    fn test_find_apps() {
+        let apps = find_apps(AppFilter::new().system_version("99")).unwrap();
+        assert!(apps.contains(&PathBuf::from("/System/Applications/Calculator.app")));
+    }
+
+    #[test]
+    fn test_installed_apps_listed() {
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+        let found = paths(&[
+            "/Applications/Xcode.app",
+            "/Applications/Xcode.app/Contents/Developer/Applications/Simulator.app",
+            "/Applications/Slack.app/Contents/Frameworks/Slack Helper.app",
+            "/System/Library/CoreServices/Finder.app",
+            "/Applications/Xcode.app",
+        ]);
+        assert_eq!(
+            listed(&AppScope::new(), found.clone()),
+            paths(&["/Applications/Xcode.app"])
+        );
+        assert_eq!(
+            listed(AppScope::new().system(true), found),
+            paths(&[
+                "/Applications/Xcode.app",
+                "/System/Library/CoreServices/Finder.app"
+            ])
+        );
+    }
+
+    #[test]
+    fn test_bundles_in() {
+        let dir = std::env::temp_dir().join(format!("macos-open-bundles-{}", std::process::id()));
+        for bundle in &["A.app/Contents/PlugIns/X.appex", "Utilities/B.app", "C.txt"] {
+            fs::create_dir_all(dir.join(bundle)).unwrap();
+        }
+        let mut apps = Vec::new();
+        bundles_in(&dir, "app", 2, &mut apps);
+        let mut extensions = Vec::new();
+        bundles_in(
+            &dir.join("A.app/Contents/PlugIns"),
+            "appex",
+            0,
+            &mut extensions,
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        apps.sort();
+        assert_eq!(apps, vec![dir.join("A.app"), dir.join("Utilities/B.app")]);
+        assert_eq!(extensions, vec![dir.join("A.app/Contents/PlugIns/X.appex")]);
+    }
+}
@@ -0,0 +1,153 @@
+//! Named launch options compiled down to `LSLaunchFlags`, for callers which don't want to
+//! combine the raw flags by hand.
+
+use crate::macos::{OpenError, OpenResult};
+
+use launch_services::LSLaunchFlags;
+
+/// kLSLaunchNoParams, deprecated and ignored by recent systems
+const K_LS_LAUNCH_NO_PARAMS: u32 = 0x0000_0800;
+
+/// The Launch Services flags as named switches, all off by default. `to_flags` refuses the
+/// combinations which contradict each other.
+///
+/// ```no_run
+/// use macos_open::{LaunchOptions, OpenOptions};
+///
+/// let flags = LaunchOptions {
+///     new_instance: true,
+///     dont_add_to_recents: true,
+///     ..LaunchOptions::default()
+/// }
+/// .to_flags()
+/// .unwrap();
+/// OpenOptions::new().flags(flags).open("/etc/hosts").unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct LaunchOptions {
+    /// Return as soon as the launch starts instead of waiting for the app to check in
+    pub async_launch: bool,
+    /// Launch a new instance of the app even if one is already running
+    pub new_instance: bool,
+    /// Keep the documents and the app out of the Recent Items
+    pub dont_add_to_recents: bool,
+    /// Don't bring the app to the front
+    pub dont_switch: bool,
+    /// Hide the app once launched
+    pub hide: bool,
+    /// Hide every other app, so only the launched one is visible
+    pub hide_others: bool,
+    /// Print the documents instead of opening them
+    pub and_print: bool,
+    /// Let Launch Services show its own alerts when the launch fails
+    pub display_errors: bool,
+    /// Launch without the launch parameters, deprecated and ignored since macOS 10.5
+    pub no_params: bool,
+}
+
+impl LaunchOptions {
+    /// Compile the options to the flags to hand to `OpenOptions::flags`, failing with
+    /// `OpenError::IncompatibleOptions` if they contradict each other: `hide` with
+    /// `hide_others` would leave nothing visible and `hide_others` with `dont_switch` would
+    /// hide everything but the app without showing it
+    pub fn to_flags(&self) -> OpenResult<LSLaunchFlags> {
+        let mut flags = LSLaunchFlags::DEFAULTS;
+        let switches = [
+            (self.async_launch, LSLaunchFlags::ASYNC),
+            (self.new_instance, LSLaunchFlags::NEW_INSTANCE),
+            (self.dont_add_to_recents, LSLaunchFlags::DONT_ADD_TO_RECENTS),
+            (self.dont_switch, LSLaunchFlags::DONT_SWITCH),
+            (self.hide, LSLaunchFlags::AND_HIDE),
+            (self.hide_others, LSLaunchFlags::AND_HIDE_OTHERS),
+            (self.and_print, LSLaunchFlags::AND_PRINT),
+            (self.display_errors, LSLaunchFlags::AND_DISPLAY_ERRORS),
+            (
+                self.no_params,
+                LSLaunchFlags::from_bits_truncate(K_LS_LAUNCH_NO_PARAMS),
+            ),
+        ];
+        for &(on, flag) in switches.iter() {
+            if on {
+                flags |= flag;
+            }
+        }
+        check_flags(flags)
+    }
+}
+
+/// Fail with `OpenError::IncompatibleOptions` if the flags contradict each other, the checks
+/// of `LaunchOptions::to_flags`
+pub(crate) fn check_flags(flags: LSLaunchFlags) -> OpenResult<LSLaunchFlags> {
+    if flags.contains(LSLaunchFlags::AND_HIDE | LSLaunchFlags::AND_HIDE_OTHERS) {
+        return Err(OpenError::IncompatibleOptions("hide and hide_others"));
+    }
+    if flags.contains(LSLaunchFlags::AND_HIDE_OTHERS | LSLaunchFlags::DONT_SWITCH) {
+        return Err(OpenError::IncompatibleOptions(
+            "hide_others and dont_switch",
+        ));
+    }
+    Ok(flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_launch_options_to_flags() {
+        assert_eq!(
+            LaunchOptions::default().to_flags().unwrap(),
+            LSLaunchFlags::DEFAULTS
+        );
+        let flags = LaunchOptions {
+            new_instance: true,
+            hide_others: true,
+            and_print: true,
+            ..LaunchOptions::default()
+        }
+        .to_flags()
+        .unwrap();
+        assert_eq!(
+            flags,
+            LSLaunchFlags::DEFAULTS
+                | LSLaunchFlags::NEW_INSTANCE
+                | LSLaunchFlags::AND_HIDE_OTHERS
+                | LSLaunchFlags::AND_PRINT
+        );
+
+        let invalid = LaunchOptions {
+            hide: true,
+            hide_others: true,
+            ..LaunchOptions::default()
+        };
+        match invalid.to_flags() {
+            Err(OpenError::IncompatibleOptions(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_launch_options_serde() {
+        let options = LaunchOptions {
+            dont_switch: true,
+            and_print: true,
+            ..LaunchOptions::default()
+        };
+        let json = serde_json::to_string(&options).unwrap();
+        assert_eq!(
+            serde_json::from_str::<LaunchOptions>(&json).unwrap(),
+            options
+        );
+        let partial: LaunchOptions = serde_json::from_str("{\"hide\":true}").unwrap();
+        assert_eq!(
+            partial,
+            LaunchOptions {
+                hide: true,
+                ..LaunchOptions::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Cheap queries and updates of the Launch Services handlers database.
+
+use crate::macos::watcher::Poller;
+use crate::macos::{OpenError, OpenResult};
+
+use core_foundation::base::{CFRelease, CFTypeRef, TCFType};
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation::url::{CFURLRef, CFURL};
+use core_foundation_sys::base::{Boolean, OSStatus};
+use launch_services::LSRolesMask;
+
+use std::path::Path;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn LSCopyDefaultHandlerForURLScheme(inURLScheme: CFStringRef) -> CFStringRef;
+    fn LSCopyDefaultRoleHandlerForContentType(
+        inContentType: CFStringRef,
+        inRole: u32,
+    ) -> CFStringRef;
+    fn LSRegisterURL(inURL: CFURLRef, inUpdate: Boolean) -> OSStatus;
+    fn LSSetDefaultHandlerForURLScheme(
+        inURLScheme: CFStringRef,
+        inHandlerBundleID: CFStringRef,
+    ) -> OSStatus;
+    fn LSSetDefaultRoleHandlerForContentType(
+        inContentType: CFStringRef,
+        inRole: u32,
+        inHandlerBundleID: CFStringRef,
+    ) -> OSStatus;
+}
+
+/// Release the copied handler, telling if there was one
+fn found(handler: CFStringRef) -> bool {
+    if handler.is_null() {
+        false
+    } else {
+        unsafe { CFRelease(handler as CFTypeRef) };
+        true
+    }
+}
+
+/// The bundle identifier of the default handler of the scheme
+fn default_handler_for_scheme(scheme: &CFString) -> Option<String> {
+    let handler = unsafe { LSCopyDefaultHandlerForURLScheme(scheme.as_concrete_TypeRef()) };
+    if handler.is_null() {
+        None
+    } else {
+        Some(unsafe { CFString::wrap_under_create_rule(handler) }.to_string())
+    }
+}
+
+/// The bundle identifier of the default handler of the uniform type identifier in the role
+#[cfg(feature = "probe")]
+pub(crate) fn default_handler_for_type(uti: &str, role: LSRolesMask) -> Option<String> {
+    let uti = CFString::new(uti);
+    let handler =
+        unsafe { LSCopyDefaultRoleHandlerForContentType(uti.as_concrete_TypeRef(), role.bits()) };
+    if handler.is_null() {
+        None
+    } else {
+        Some(unsafe { CFString::wrap_under_create_rule(handler) }.to_string())
+    }
+}
+
+/// Check if at least one app handles the given scheme, without listing the apps
+pub fn scheme_has_handler(scheme: &str) -> bool {
+    let scheme = CFString::new(scheme);
+    found(unsafe { LSCopyDefaultHandlerForURLScheme(scheme.as_concrete_TypeRef()) })
+}
+
+/// Check if at least one app handles the given uniform type identifier in the given role,
+/// without listing the apps
+pub fn uti_has_handler(uti: &str, role: LSRolesMask) -> bool {
+    let uti = CFString::new(uti);
+    found(unsafe { LSCopyDefaultRoleHandlerForContentType(uti.as_concrete_TypeRef(), role.bits()) })
+}
+
+/// Make Launch Services aware of the app at the given path, like `lsregister`. With `update`
+/// the app is registered again even if it didn't change since the last registration.
+///
+/// Launch Services has no public API to unregister an app: delete it and it'll be dropped at
+/// the next rescan.
+pub fn register_app(path: &Path, update: bool) -> OpenResult<()> {
+    let url = CFURL::from_path(path, true).ok_or(OpenError::InvalidApp)?;
+    status(unsafe { LSRegisterURL(url.as_concrete_TypeRef(), update as Boolean) })
+}
+
+fn status(code: OSStatus) -> OpenResult<()> {
+    match code {
+        0 => Ok(()),
+        code => Err(OpenError::Status(code)),
+    }
+}
+
+/// Make the app with the bundle identifier the default handler of the scheme for the current
+/// user. The system asks the user to confirm a new default browser (`http` and `https`).
+pub fn set_default_handler_for_scheme(scheme: &str, bundle_id: &str) -> OpenResult<()> {
+    let scheme = CFString::new(scheme);
+    let bundle_id = CFString::new(bundle_id);
+    status(unsafe {
+        LSSetDefaultHandlerForURLScheme(
+            scheme.as_concrete_TypeRef(),
+            bundle_id.as_concrete_TypeRef(),
+        )
+    })
+}
+
+/// Make the app with the bundle identifier the default handler of the uniform type
+/// identifier in the role for the current user
+pub fn set_default_handler_for_type(
+    uti: &str,
+    role: LSRolesMask,
+    bundle_id: &str,
+) -> OpenResult<()> {
+    let uti = CFString::new(uti);
+    let bundle_id = CFString::new(bundle_id);
+    status(unsafe {
+        LSSetDefaultRoleHandlerForContentType(
+            uti.as_concrete_TypeRef(),
+            role.bits(),
+            bundle_id.as_concrete_TypeRef(),
+        )
+    })
+}
+
+/// A running watcher of a default handler, the watch stops when dropped
+pub struct HandlerWatcher(Poller);
+
+impl HandlerWatcher {
+    /// Stop watching and wait for the watcher thread to finish
+    pub fn stop(self) {
+        self.0.stop();
+    }
+}
+
+/// Call `callback` from a background thread every time the default handler of the scheme
+/// changes (e.g. the user picks another default browser for `https`), with the bundle
+/// identifier of the new handler.
+///
+/// The Launch Services database is polled every second.
+pub fn watch_default_handler<F>(scheme: &str, mut callback: F) -> HandlerWatcher
+where
+    F: FnMut(Option<String>) + Send + 'static,
+{
+    let scheme = scheme.to_string();
+    HandlerWatcher(Poller::spawn(POLL_INTERVAL, move || {
+        let scheme = CFString::new(&scheme);
+        let normalize = |handler: Option<String>| handler.map(|id| id.to_lowercase());
+        let mut current = normalize(default_handler_for_scheme(&scheme));
+
+        move || {
+            let handler = default_handler_for_scheme(&scheme);
+            if normalize(handler.clone()) != current {
+                current = normalize(handler.clone());
+                callback(handler);
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_handler() {
+        assert!(scheme_has_handler("https"));
+        assert!(!scheme_has_handler("surely-nobody-handles-this"));
+        assert!(uti_has_handler("public.html", LSRolesMask::all()));
+    }
+
+    #[test]
+    fn test_register_app() {
+        register_app(Path::new("/System/Applications/Calculator.app"), false).unwrap();
+    }
+
+    #[test]
+    fn test_watch_default_handler() {
+        use std::sync::mpsc::channel;
+
+        let scheme = "x-macos-open-watch-test";
+        set_default_handler_for_scheme(scheme, "com.apple.calculator").unwrap();
+        let (tx, rx) = channel();
+        let watcher = watch_default_handler(scheme, move |handler| {
+            tx.send(handler).unwrap();
+        });
+        std::thread::sleep(POLL_INTERVAL);
+
+        set_default_handler_for_scheme(scheme, "com.apple.TextEdit").unwrap();
+        let handler = rx.recv_timeout(POLL_INTERVAL * 5).unwrap();
+        assert_eq!(
+            handler.map(|id| id.to_lowercase()),
+            Some("com.apple.textedit".to_string())
+        );
+        watcher.stop();
+        assert!(rx.recv().is_err());
+    }
+}
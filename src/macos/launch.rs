@@ -0,0 +1,1609 @@
+//! Configurable launches.
+
+use crate::macos::alias::follow_aliases;
+use crate::macos::apple_event;
+use crate::macos::bundle::bundle_identifier;
+use crate::macos::cancel::{CancelToken, Deadline};
+use crate::macos::cloud::materialize;
+use crate::macos::codesign::verify_team_id;
+use crate::macos::flags::check_flags;
+#[cfg(feature = "probe")]
+use crate::macos::probe::handler_for_remote;
+use crate::macos::progress::{open_each_with_progress, OpenProgress, ProgressCallback};
+use crate::macos::quarantine::{assess, quarantine_status};
+use crate::macos::retry::RetryPolicy;
+use crate::macos::running::{
+    running_app_for_pid, running_apps, running_apps_for_bundle_id, RunningApp,
+};
+use crate::macos::spawn::spawn;
+#[cfg(feature = "workspace")]
+use crate::macos::workspace;
+use crate::macos::{
+    remap_app, remap_multiopenable, MultiOpenable, OpenError, OpenPolicy, OpenResult, Openable,
+    Opener,
+};
+
+use core_foundation::array::CFArray;
+use core_foundation::base::TCFType;
+use core_foundation::url::CFURL;
+use core_foundation_sys::base::OSStatus;
+use launch_services::{
+    default_application_url_for_url, open_from_url_spec, LSLaunchFlags, LSLaunchURLSpec,
+    LSRolesMask,
+};
+use libc::pid_t;
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The largest number of urls handed to Launch Services at once by default
+const DEFAULT_CHUNK_SIZE: usize = 500;
+/// How often a bounded launch checks its deadline
+const LAUNCH_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long `materialize_before_open` waits for the downloads without a timeout
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long to wait for a launched app to check in before delivering the urls
+pub(crate) const CHECK_IN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn GetProcessForPID(pid: pid_t, psn: *mut ProcessSerialNumber) -> OSStatus;
+}
+
+/// The Process Manager identifier of a running app, the target of the legacy Apple Event and
+/// Carbon APIs
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessSerialNumber {
+    high: u32,
+    low: u32,
+}
+
+impl ProcessSerialNumber {
+    fn for_pid(pid: pid_t) -> Option<ProcessSerialNumber> {
+        let mut psn = ProcessSerialNumber { high: 0, low: 0 };
+        match unsafe { GetProcessForPID(pid, &mut psn) } {
+            0 => Some(psn),
+            _ => None,
+        }
+    }
+
+    /// The high 32 bits
+    pub fn high(&self) -> u32 {
+        self.high
+    }
+
+    /// The low 32 bits
+    pub fn low(&self) -> u32 {
+        self.low
+    }
+}
+
+/// The system API used to open urls. `LaunchedApp::backend` tells which one `Auto` picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Backend {
+    /// NSWorkspace when available (macOS 10.15 and later, with the `workspace` feature),
+    /// Launch Services otherwise. The app is spawned directly when Launch Services can't
+    /// honour the arguments, environment, architecture or redirections.
+    Auto,
+    /// The deprecated `LSOpenFromURLSpec`, available on every macOS version
+    LaunchServices,
+    /// `NSWorkspace` with an `NSWorkspaceOpenConfiguration`, it requires the `workspace`
+    /// feature and macOS 10.15
+    Workspace,
+    /// Start the executable of the app directly, then hand it the urls through Launch
+    /// Services. It requires an app.
+    Spawn,
+}
+
+impl Default for Backend {
+    fn default() -> Backend {
+        Backend::Auto
+    }
+}
+
+/// A CPU architecture of universal binaries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Architecture {
+    /// Intel 64 bit, run under Rosetta on Apple Silicon
+    X86_64,
+    /// Apple Silicon
+    Arm64,
+}
+
+impl Architecture {
+    /// The mach `cpu_type_t` of the architecture
+    pub(crate) fn cpu_type(self) -> i32 {
+        match self {
+            Architecture::X86_64 => 0x0100_0007,
+            Architecture::Arm64 => 0x0100_000c,
+        }
+    }
+
+    /// The name of the architecture, as in `lipo` and Spotlight
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Architecture::X86_64 => "x86_64",
+            Architecture::Arm64 => "arm64",
+        }
+    }
+}
+
+/// The Apple Event used to hand the urls to the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OpenVerb {
+    /// Open the documents (`odoc`)
+    Open,
+    /// Open the documents for viewing only (`rodc`)
+    OpenReadOnly,
+    /// Print the documents (`pdoc`)
+    Print,
+    /// A custom event, sent with the urls as direct object. Codes are four char codes, e.g.
+    /// `u32::from_be_bytes(*b"odoc")`
+    Event {
+        /// The event class
+        class: u32,
+        /// The event identifier
+        id: u32,
+    },
+}
+
+impl Default for OpenVerb {
+    fn default() -> OpenVerb {
+        OpenVerb::Open
+    }
+}
+
+impl OpenVerb {
+    /// The event sent by hand, `None` when Launch Services delivers it
+    fn event(self) -> Option<(u32, u32)> {
+        const CORE_EVENT_CLASS: u32 = 0x6165_7674; // 'aevt'
+
+        match self {
+            OpenVerb::Open | OpenVerb::Print => None,
+            OpenVerb::OpenReadOnly => Some((CORE_EVENT_CLASS, 0x726f_6463)), // 'rodc'
+            OpenVerb::Event { class, id } => Some((class, id)),
+        }
+    }
+}
+
+/// Where a standard stream of a launched app is connected, like `open --stdin/--stdout/--stderr`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Redirect {
+    /// Share the stream of the current process
+    Inherit,
+    /// Connect the stream to `/dev/null`
+    Null,
+    /// Read from or write (truncating it) to the file at the given path
+    File(PathBuf),
+    /// Create a pipe, the other end is available from the `LaunchedApp`
+    Piped,
+}
+
+/// Options and flags which can be used to configure how urls are opened, in the same fashion of
+/// `std::fs::OpenOptions`. Deserialized options can run apps with any arguments and
+/// environment and truncate files through the redirections, only load them from trusted
+/// sources.
+///
+/// ```no_run
+/// use macos_open::OpenOptions;
+///
+/// OpenOptions::new()
+///     .app("/Applications/Safari.app")
+///     .open("https://www.example.com/")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct OpenOptions {
+    pub(crate) app: Option<PathBuf>,
+    #[cfg_attr(feature = "serde", serde(with = "flags_bits"))]
+    pub(crate) flags: LSLaunchFlags,
+    pub(crate) backend: Backend,
+    pub(crate) args: Vec<String>,
+    pub(crate) env: Vec<(String, String)>,
+    pub(crate) new_instance: bool,
+    pub(crate) arch: Option<Architecture>,
+    pub(crate) stdin: Option<Redirect>,
+    pub(crate) stdout: Option<Redirect>,
+    pub(crate) stderr: Option<Redirect>,
+    pub(crate) fresh: bool,
+    pub(crate) verb: OpenVerb,
+    pub(crate) assess_before_open: bool,
+    pub(crate) team_id: Option<String>,
+    pub(crate) timeout: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) cancel: Option<CancelToken>,
+    pub(crate) terminate_on_abort: bool,
+    pub(crate) detect_already_running: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) retry: Option<RetryPolicy>,
+    pub(crate) merge_tabs: bool,
+    pub(crate) materialize_before_open: bool,
+    pub(crate) follow_aliases: bool,
+    pub(crate) only_if_running: bool,
+    pub(crate) activate_all_windows: bool,
+    #[cfg_attr(not(feature = "probe"), allow(dead_code))]
+    pub(crate) probe_remote: bool,
+    pub(crate) chunk_size: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) policy: Option<OpenPolicy>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) progress: Option<ProgressCallback>,
+}
+
+/// Launch Services flags as their bits, unknown bits are dropped
+#[cfg(feature = "serde")]
+mod flags_bits {
+    use launch_services::LSLaunchFlags;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(crate) fn serialize<S: Serializer>(
+        flags: &LSLaunchFlags,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(flags.bits())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<LSLaunchFlags, D::Error> {
+        u32::deserialize(deserializer).map(LSLaunchFlags::from_bits_truncate)
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> OpenOptions {
+        OpenOptions::new()
+    }
+}
+
+impl OpenOptions {
+    /// Create a blank set of options: urls are opened with their default handlers using
+    /// `LSLaunchFlags::DEFAULTS`.
+    pub fn new() -> OpenOptions {
+        OpenOptions {
+            app: None,
+            flags: LSLaunchFlags::DEFAULTS,
+            backend: Backend::Auto,
+            args: Vec::new(),
+            env: Vec::new(),
+            new_instance: false,
+            arch: None,
+            stdin: None,
+            stdout: None,
+            stderr: None,
+            fresh: false,
+            verb: OpenVerb::Open,
+            assess_before_open: false,
+            team_id: None,
+            timeout: None,
+            cancel: None,
+            terminate_on_abort: false,
+            detect_already_running: false,
+            retry: None,
+            merge_tabs: false,
+            materialize_before_open: false,
+            follow_aliases: false,
+            only_if_running: false,
+            activate_all_windows: false,
+            probe_remote: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            policy: None,
+            progress: None,
+        }
+    }
+
+    /// Open urls with the app at the given path instead of their default handlers
+    pub fn app<P: Into<PathBuf>>(&mut self, app: P) -> &mut OpenOptions {
+        self.app = Some(app.into());
+        self
+    }
+
+    /// Set the Launch Services flags used for the launch, replacing the ones set by
+    /// `hide_from_recents` and the other switches called before
+    pub fn flags(&mut self, flags: LSLaunchFlags) -> &mut OpenOptions {
+        self.flags = flags;
+        self
+    }
+
+    /// Choose the system API used to open urls
+    pub fn backend(&mut self, backend: Backend) -> &mut OpenOptions {
+        self.backend = backend;
+        self
+    }
+
+    /// Add an argument passed to the app when it's launched
+    pub fn arg<S: Into<String>>(&mut self, arg: S) -> &mut OpenOptions {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Add arguments passed to the app when it's launched
+    pub fn args<I, S>(&mut self, args: I) -> &mut OpenOptions
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Add an environment variable for the app when it's launched
+    pub fn env<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> &mut OpenOptions {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Launch a new instance of the app even if one is already running
+    pub fn new_instance(&mut self, new_instance: bool) -> &mut OpenOptions {
+        self.new_instance = new_instance;
+        self
+    }
+
+    /// Run universal apps with the given architecture, like `open --arch`
+    pub fn arch(&mut self, arch: Architecture) -> &mut OpenOptions {
+        self.arch = Some(arch);
+        self
+    }
+
+    /// Connect the standard input of the launched app
+    pub fn stdin(&mut self, stdin: Redirect) -> &mut OpenOptions {
+        self.stdin = Some(stdin);
+        self
+    }
+
+    /// Connect the standard output of the launched app
+    pub fn stdout(&mut self, stdout: Redirect) -> &mut OpenOptions {
+        self.stdout = Some(stdout);
+        self
+    }
+
+    /// Connect the standard error of the launched app
+    pub fn stderr(&mut self, stderr: Redirect) -> &mut OpenOptions {
+        self.stderr = Some(stderr);
+        self
+    }
+
+    /// Launch the app without restoring its saved windows, like `open -F`
+    pub fn fresh(&mut self, fresh: bool) -> &mut OpenOptions {
+        self.fresh = fresh;
+        self
+    }
+
+    /// Choose the Apple Event used to hand the urls to the app
+    pub fn verb(&mut self, verb: OpenVerb) -> &mut OpenOptions {
+        self.verb = verb;
+        self
+    }
+
+    /// Ask Gatekeeper before opening, failing with `OpenError::Blocked` or
+    /// `OpenError::Unnotarized` instead of letting the system show its dialogs
+    pub fn assess_before_open(&mut self, assess: bool) -> &mut OpenOptions {
+        self.assess_before_open = assess;
+        self
+    }
+
+    /// Refuse to open urls with apps which aren't signed by the given team
+    pub fn require_team_id<S: Into<String>>(&mut self, team_id: S) -> &mut OpenOptions {
+        self.team_id = Some(team_id.into());
+        self
+    }
+
+    /// Give up on the launch, and on waiting for the app in `open_and_wait`, after the given
+    /// time with `OpenError::TimedOut`
+    pub fn timeout(&mut self, timeout: Duration) -> &mut OpenOptions {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Give up on the launch, and on waiting for the app in `open_and_wait`, with
+    /// `OpenError::Cancelled` as soon as the token is cancelled
+    pub fn cancel_token(&mut self, token: CancelToken) -> &mut OpenOptions {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Kill the app when the launch or `open_and_wait` gives up on it after the timeout or a
+    /// cancellation, unless it was already running before the launch. It implies
+    /// `detect_already_running`.
+    pub fn terminate_on_abort(&mut self, terminate: bool) -> &mut OpenOptions {
+        self.terminate_on_abort = terminate;
+        self
+    }
+
+    /// Tell if the urls were handed to an instance which was already running, see
+    /// `LaunchedApp::already_running`. The running instances of the handler are listed before
+    /// every launch, so it's off by default.
+    pub fn detect_already_running(&mut self, detect: bool) -> &mut OpenOptions {
+        self.detect_already_running = detect;
+        self
+    }
+
+    /// Retry the launch when it fails with a transient error, according to the policy
+    pub fn retry(&mut self, policy: RetryPolicy) -> &mut OpenOptions {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Open all the urls in a single instance of the app (the handler of the first url if none
+    /// is configured), as tabs of the same window where the app supports it (e.g. TextEdit,
+    /// Preview, Terminal). The tabbing mode is forced when the app is launched, an instance
+    /// already running keeps the mode chosen in its preferences.
+    pub fn merge_tabs(&mut self, merge: bool) -> &mut OpenOptions {
+        self.merge_tabs = merge;
+        self
+    }
+
+    /// Download the iCloud Drive and File Provider placeholders among the urls, and wait for
+    /// them to be local, before launching the handler. The wait gives up after the timeout (a
+    /// minute without one) or when the cancel token is cancelled.
+    pub fn materialize_before_open(&mut self, materialize: bool) -> &mut OpenOptions {
+        self.materialize_before_open = materialize;
+        self
+    }
+
+    /// Open the originals of Finder aliases and the targets of `.webloc`, `.inetloc` and
+    /// `.fileloc` files instead of the files themselves, like a double click in Finder
+    pub fn follow_aliases(&mut self, follow: bool) -> &mut OpenOptions {
+        self.follow_aliases = follow;
+        self
+    }
+
+    /// Keep the opened documents and the app out of the Recent Items. The NSWorkspace backend
+    /// honours it for the documents too, with Launch Services it's best-effort: the app may
+    /// still record the documents it opens. Same as `LSLaunchFlags::DONT_ADD_TO_RECENTS`.
+    pub fn hide_from_recents(&mut self, hide: bool) -> &mut OpenOptions {
+        self.flags.set(LSLaunchFlags::DONT_ADD_TO_RECENTS, hide);
+        self
+    }
+
+    /// Only hand the urls to the app (the default handler of the first url if none is
+    /// configured) if it's already running, failing with `OpenError::NotRunning` instead of
+    /// launching it
+    pub fn only_if_running(&mut self, only_if_running: bool) -> &mut OpenOptions {
+        self.only_if_running = only_if_running;
+        self
+    }
+
+    /// Bring the app to the front when launched (the default). Without activation the app
+    /// stays behind the current one, `LaunchedApp::activate` orders it front later. Same as
+    /// clearing `LSLaunchFlags::DONT_SWITCH`.
+    pub fn activates(&mut self, activates: bool) -> &mut OpenOptions {
+        self.flags.set(LSLaunchFlags::DONT_SWITCH, !activates);
+        self
+    }
+
+    /// When the app is activated, bring all its windows to the front instead of the most
+    /// recently used one, e.g. for windows on other displays. Best-effort: the windows are
+    /// activated in the background once the app checks in, the launch doesn't wait for it.
+    pub fn activate_all_windows(&mut self, all_windows: bool) -> &mut OpenOptions {
+        self.activate_all_windows = all_windows;
+        self
+    }
+
+    /// Hide every other app once the app is launched, so it's the only one visible. Same as
+    /// `LSLaunchFlags::AND_HIDE_OTHERS`.
+    pub fn hide_others(&mut self, hide_others: bool) -> &mut OpenOptions {
+        self.flags.set(LSLaunchFlags::AND_HIDE_OTHERS, hide_others);
+        self
+    }
+
+    /// Without an app, ask the server the content type of an http(s) url with a HEAD request
+    /// and open it with the default handler of its type, e.g. a link to a PDF in Preview. Web
+    /// pages, unknown types and unreachable urls still go to the browser, so do the urls opened
+    /// together with other ones.
+    #[cfg(feature = "probe")]
+    pub fn probe_remote(&mut self, probe: bool) -> &mut OpenOptions {
+        self.probe_remote = probe;
+        self
+    }
+
+    /// Hand the urls to the app in launches of at most `size` urls (500 by default, 0 for a
+    /// single launch), as Launch Services misbehaves with thousands of urls at once. Only the
+    /// first launch starts a new instance, the next ones reach the same app. A launch started
+    /// by this crate (with arguments, environment, ...) passes all the urls to a single
+    /// process instead. When some chunks fail the others are still delivered, and the error
+    /// lists the failed ones.
+    pub fn chunk_size(&mut self, size: usize) -> &mut OpenOptions {
+        self.chunk_size = size;
+        self
+    }
+
+    /// Refuse the urls denied by the policy with `OpenError::Denied` before launching anything.
+    /// Aliases and location files are vetted by what they point to when `follow_aliases` is
+    /// set.
+    pub fn policy(&mut self, policy: OpenPolicy) -> &mut OpenOptions {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Report the progress of the launches: once the urls are converted, then after each
+    /// chunk (see `chunk_size`) is delivered, the smaller batches making a single chunk
+    pub fn progress<F>(&mut self, callback: F) -> &mut OpenOptions
+    where
+        F: Fn(&OpenProgress) + Send + Sync + 'static,
+    {
+        self.progress = Some(ProgressCallback(Arc::new(callback)));
+        self
+    }
+
+    /// Count the urls as delivered and invoke the progress callback, if any
+    fn report(&self, progress: &mut OpenProgress, delivered: usize, current: Option<&CFURL>) {
+        if let Some(ref callback) = self.progress {
+            progress.delivered += delivered;
+            progress.current = current.map(|url| url.get_string().to_string());
+            (callback.0)(progress);
+        }
+    }
+
+    /// Open the urls with these options, then block until the app exits (like `open -W`) or
+    /// the timeout or the cancel token make it give up
+    pub fn open_and_wait<T: MultiOpenable + ?Sized>(&self, urls: &T) -> OpenResult<()> {
+        let deadline = self.deadline();
+        let launched = self.launch_urls_until(remap_multiopenable(Some(urls))?, &deadline)?;
+        match launched.wait_with(&deadline) {
+            Err(err) => {
+                self.abort(&launched);
+                Err(err)
+            }
+            ok => ok,
+        }
+    }
+
+    fn deadline(&self) -> Deadline {
+        Deadline::new(self.timeout, self.cancel.as_ref())
+    }
+
+    /// Clean up after giving up on a launch, killing the app if asked to
+    fn abort(&self, launched: &LaunchedApp) {
+        if self.terminate_on_abort && launched.already_running() == Some(false) {
+            let _ = launched.terminate(true);
+        }
+    }
+
+    /// Open the urls with these options
+    pub fn open<T: MultiOpenable + ?Sized>(&self, urls: &T) -> OpenResult<LaunchedApp> {
+        self.launch_urls(remap_multiopenable(Some(urls))?)
+    }
+
+    /// Launch the configured app without any url
+    pub fn launch(&self) -> OpenResult<LaunchedApp> {
+        self.launch_urls(None)
+    }
+
+    fn redirects(&self) -> bool {
+        self.stdin.is_some() || self.stdout.is_some() || self.stderr.is_some()
+    }
+
+    fn needs_spawn(&self) -> bool {
+        !self.arguments().is_empty()
+            || !self.env.is_empty()
+            || self.arch.is_some()
+            || self.redirects()
+    }
+
+    /// The arguments passed to the app, including the ones implied by the options
+    pub(crate) fn arguments(&self) -> Vec<String> {
+        let mut args = self.args.clone();
+        if self.fresh {
+            args.push("-ApplePersistenceIgnoreState".to_string());
+            args.push("YES".to_string());
+        }
+        args
+    }
+
+    /// The Launch Services flags implied by the options, failing like `LaunchOptions::to_flags`
+    /// when they contradict each other
+    pub(crate) fn launch_flags(&self) -> OpenResult<LSLaunchFlags> {
+        let mut flags = self.flags;
+        if self.new_instance {
+            flags |= LSLaunchFlags::NEW_INSTANCE;
+        }
+        if self.verb == OpenVerb::Print {
+            flags |= LSLaunchFlags::AND_PRINT;
+        }
+        check_flags(flags)
+    }
+
+    /// Hand the urls to an app which is already running
+    pub(crate) fn deliver(
+        &self,
+        app: CFURL,
+        running: &RunningApp,
+        urls: CFArray<CFURL>,
+    ) -> OpenResult<()> {
+        match self.verb.event() {
+            Some((class, id)) => apple_event::send(running.pid(), class, id, &urls),
+            None => {
+                let spec = LSLaunchURLSpec {
+                    app: Some(app),
+                    urls: Some(urls),
+                    flags: self.launch_flags()?,
+                    ..Default::default()
+                };
+                open_from_url_spec(spec)
+                    .map(|_| ())
+                    .map_err(OpenError::Status)
+            }
+        }
+    }
+
+    /// Launch the app (the default handler of the first url if none is configured), then send
+    /// it the custom event
+    fn launch_with_event(
+        &self,
+        app: Option<CFURL>,
+        urls: CFArray<CFURL>,
+    ) -> OpenResult<LaunchedApp> {
+        let app = match app {
+            Some(app) => app,
+            None => {
+                let url = urls.get(0).ok_or(OpenError::InvalidUrls)?;
+                default_application_url_for_url(&url, LSRolesMask::all())
+                    .map_err(|_| OpenError::AppNotFound(url.get_string().to_string()))?
+            }
+        };
+
+        let spec = LSLaunchURLSpec {
+            app: Some(app.clone()),
+            urls: None,
+            flags: self.launch_flags()?,
+            ..Default::default()
+        };
+        let launched = match open_from_url_spec(spec) {
+            Ok(app) => LaunchedApp::new(app.to_path(), None),
+            Err(code) => return Err(OpenError::Status(code)),
+        };
+
+        let running = launched
+            .wait_running(CHECK_IN_TIMEOUT)
+            .ok_or_else(|| OpenError::NotRunning(app.get_string().to_string()))?;
+        self.deliver(app, &running, urls)?;
+        Ok(launched)
+    }
+
+    /// Run the Gatekeeper assessments of the app and of the quarantined files
+    fn preflight(&self, app: Option<&CFURL>, urls: Option<&CFArray<CFURL>>) -> OpenResult<()> {
+        if let Some(app) = app {
+            assess(app, true)?;
+        }
+        if let Some(urls) = urls {
+            for url in urls.iter() {
+                let path = match url.to_path() {
+                    Some(path) => path,
+                    None => continue,
+                };
+                if path.extension().map_or(false, |ext| ext == "app") {
+                    assess(&url, true)?;
+                } else if quarantine_status(&path).is_some() {
+                    assess(&url, false)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify the signature of the app, or of the default handlers of the urls without one
+    fn verify_team(&self, team_id: &str, urls: Option<&CFArray<CFURL>>) -> OpenResult<()> {
+        if let Some(ref app) = self.app {
+            return verify_team_id(app, team_id);
+        }
+        if let Some(urls) = urls {
+            for url in urls.iter() {
+                let handler = default_application_url_for_url(&url, LSRolesMask::all())
+                    .ok()
+                    .and_then(|handler| handler.to_path())
+                    .ok_or_else(|| OpenError::AppNotFound(url.get_string().to_string()))?;
+                verify_team_id(&handler, team_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The app which is going to receive the urls: the configured one or the default handler
+    /// of the first url
+    fn handler(&self, urls: Option<&CFArray<CFURL>>) -> Option<PathBuf> {
+        match self.app {
+            Some(ref app) => Some(app.clone()),
+            None => urls
+                .and_then(|urls| urls.get(0))
+                .and_then(|url| default_application_url_for_url(&url, LSRolesMask::all()).ok())
+                .and_then(|handler| handler.to_path()),
+        }
+    }
+
+    /// The pids of the running instances of the app which is going to receive the urls
+    fn running_handlers(&self, urls: Option<&CFArray<CFURL>>) -> Vec<pid_t> {
+        self.handler(urls)
+            .map(|handler| running_instances(&handler))
+            .unwrap_or_default()
+            .iter()
+            .map(RunningApp::pid)
+            .collect()
+    }
+
+    pub(crate) fn launch_urls(&self, urls: Option<CFArray<CFURL>>) -> OpenResult<LaunchedApp> {
+        self.launch_urls_until(urls, &self.deadline())
+    }
+
+    /// Launch the urls, the whole call (downloads, chunks and launch) sharing the deadline
+    fn launch_urls_until(
+        &self,
+        urls: Option<CFArray<CFURL>>,
+        deadline: &Deadline,
+    ) -> OpenResult<LaunchedApp> {
+        // every url is vetted before the first chunk is launched
+        let urls = match urls {
+            Some(urls) if self.follow_aliases => Some(follow_aliases(urls)?),
+            urls => urls,
+        };
+        if let (Some(policy), Some(urls)) = (self.policy.as_ref(), urls.as_ref()) {
+            policy.vet(urls)?;
+        }
+
+        let total = urls.as_ref().map_or(0, |urls| urls.len() as usize);
+        let mut progress = OpenProgress {
+            total,
+            converted: total,
+            delivered: 0,
+            current: None,
+        };
+        self.report(&mut progress, 0, None);
+
+        match urls {
+            Some(urls)
+                if self.chunk_size > 0
+                    && urls.len() as usize > self.chunk_size
+                    && !self.needs_spawn() =>
+            {
+                self.launch_chunks(urls, deadline, &mut progress)
+            }
+            urls => {
+                let last = self
+                    .progress
+                    .as_ref()
+                    .and(urls.as_ref())
+                    .and_then(|urls| urls.iter().last().map(|url| url.clone()));
+                let res = self.launch_urls_whole(urls, deadline);
+                self.report(&mut progress, total, last.as_ref());
+                res
+            }
+        }
+    }
+
+    /// Launch every chunk of urls in order. The first successful launch picks the app and its
+    /// instance for the next chunks, and is returned when every chunk is delivered.
+    fn launch_chunks(
+        &self,
+        urls: CFArray<CFURL>,
+        deadline: &Deadline,
+        progress: &mut OpenProgress,
+    ) -> OpenResult<LaunchedApp> {
+        let urls = urls.iter().map(|url| url.clone()).collect::<Vec<_>>();
+        let chunks = urls.chunks(self.chunk_size);
+        let count = chunks.len();
+        let mut options = self.clone();
+        let mut first = None;
+        let mut failed = Vec::new();
+        for (index, chunk) in chunks.enumerate() {
+            let res = options.launch_urls_whole(Some(CFArray::from_CFTypes(chunk)), deadline);
+            self.report(progress, chunk.len(), chunk.last());
+            match res {
+                Ok(launched) if first.is_none() => {
+                    // send the next chunks to the instance which got the first one
+                    options.new_instance = false;
+                    options.flags.remove(LSLaunchFlags::NEW_INSTANCE);
+                    options.merge_tabs = false;
+                    options.app = launched.path().map(Path::to_path_buf).or(options.app);
+                    first = Some(launched);
+                }
+                Ok(_) => (),
+                Err(err) => failed.push((index, err)),
+            }
+        }
+
+        match first {
+            Some(first) if failed.is_empty() => Ok(first),
+            _ => Err(OpenError::ChunksFailed {
+                chunks: count,
+                failed,
+            }),
+        }
+    }
+
+    fn launch_urls_whole(
+        &self,
+        urls: Option<CFArray<CFURL>>,
+        deadline: &Deadline,
+    ) -> OpenResult<LaunchedApp> {
+        traced!(
+            "launch",
+            {
+                urls = ?crate::macos::trace::urls(urls.as_ref()),
+                app = ?self.app,
+                flags = ?self.launch_flags().ok(),
+            },
+            {
+                if self.materialize_before_open {
+                    if let Some(ref urls) = urls {
+                        materialize(urls, &deadline.or_timeout(DOWNLOAD_TIMEOUT))?;
+                    }
+                }
+                if deadline.is_unbounded() {
+                    self.launch_urls_now(urls, deadline)
+                } else {
+                    self.launch_urls_bounded(urls, deadline)
+                }
+            }
+        )
+    }
+
+    fn launch_urls_now(
+        &self,
+        urls: Option<CFArray<CFURL>>,
+        deadline: &Deadline,
+    ) -> OpenResult<LaunchedApp> {
+        match self.retry {
+            Some(ref policy) => policy.run(deadline, || {
+                // every attempt consumes its urls, share the same immutable array
+                let urls = urls.as_ref().map(|urls| unsafe {
+                    CFArray::wrap_under_get_rule(urls.as_concrete_TypeRef())
+                });
+                self.launch_urls_once(urls)
+            }),
+            None => self.launch_urls_once(urls),
+        }
+    }
+
+    fn launch_urls_once(&self, urls: Option<CFArray<CFURL>>) -> OpenResult<LaunchedApp> {
+        let before = if self.detect_already_running || self.terminate_on_abort {
+            Some(self.running_handlers(urls.as_ref()))
+        } else {
+            None
+        };
+        let mut launched = self.launch_urls_unchecked(urls)?;
+        if let Some(running) = launched.running() {
+            launched.already_running = before.map(|before| before.contains(&running.pid()));
+            launched.psn = ProcessSerialNumber::for_pid(running.pid());
+        }
+        if self.activate_all_windows && !self.launch_flags()?.contains(LSLaunchFlags::DONT_SWITCH) {
+            // the system only fronts the main window, the others need an explicit activation
+            let app = LaunchedApp::new(launched.path.clone(), launched.pid);
+            thread::spawn(move || {
+                if let Some(running) = app.wait_running(CHECK_IN_TIMEOUT) {
+                    let _ = running.activate();
+                }
+            });
+        }
+        Ok(launched)
+    }
+
+    /// Launch on another thread to stop waiting for a wedged launch when the deadline passes,
+    /// the launch itself can't be interrupted and may still complete later: its app is then
+    /// cleaned up like the ones `open_and_wait` gives up on
+    fn launch_urls_bounded(
+        &self,
+        urls: Option<CFArray<CFURL>>,
+        deadline: &Deadline,
+    ) -> OpenResult<LaunchedApp> {
+        deadline.check()?;
+        let urls = SendUrls(urls);
+        let options = self.clone();
+        let until = deadline.clone();
+        let abandoned = Arc::new(Mutex::new(false));
+        let (tx, rx) = mpsc::channel();
+        let given_up = abandoned.clone();
+        thread::spawn(move || {
+            let urls = urls;
+            // the retries stop with the deadline, not only the wait for them
+            let res = options.launch_urls_now(urls.0, &until);
+            let given_up = given_up.lock().unwrap_or_else(PoisonError::into_inner);
+            match res {
+                Ok(ref launched) if *given_up => options.abort(launched),
+                res => {
+                    let _ = tx.send(res);
+                }
+            }
+        });
+
+        loop {
+            match rx.recv_timeout(deadline.step(LAUNCH_POLL_INTERVAL)) {
+                Ok(res) => return res,
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Err(err) = deadline.check() {
+                        *abandoned.lock().unwrap_or_else(PoisonError::into_inner) = true;
+                        // the launch may have completed since the last poll
+                        if let Ok(Ok(launched)) = rx.try_recv() {
+                            self.abort(&launched);
+                        }
+                        return Err(err);
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(OpenError::LaunchPanicked),
+            }
+        }
+    }
+
+    /// The options sending every url to the same app, launched in the tabbing mode merging
+    /// the new windows as tabs unless it's already running
+    fn merging_tabs(&self, urls: Option<&CFArray<CFURL>>) -> OpenResult<OpenOptions> {
+        let handler = self
+            .handler(urls)
+            .ok_or_else(|| OpenError::AppNotFound(first_url(urls)))?;
+        let mut options = self.clone();
+        options.merge_tabs = false;
+        if running_instances(&handler).is_empty() {
+            options.args.push("-AppleWindowTabbingMode".to_string());
+            options.args.push("always".to_string());
+        }
+        options.app = Some(handler);
+        Ok(options)
+    }
+
+    fn launch_urls_unchecked(&self, urls: Option<CFArray<CFURL>>) -> OpenResult<LaunchedApp> {
+        #[cfg(feature = "probe")]
+        {
+            // a batch may mix documents and web pages, only single urls are probed
+            let single = urls.as_ref().filter(|urls| urls.len() == 1);
+            if self.probe_remote && self.app.is_none() {
+                let first = single.and_then(|urls| urls.get(0));
+                if let Some(handler) = first.and_then(|url| handler_for_remote(&url)) {
+                    let mut options = self.clone();
+                    options.probe_remote = false;
+                    options.app = Some(handler);
+                    return options.launch_urls_unchecked(urls);
+                }
+            }
+        }
+        if self.merge_tabs {
+            return self
+                .merging_tabs(urls.as_ref())?
+                .launch_urls_unchecked(urls);
+        }
+        if self.only_if_running {
+            match self.handler(urls.as_ref()) {
+                Some(ref handler) if running_instances(handler).is_empty() => {
+                    return Err(OpenError::NotRunning(handler.display().to_string()))
+                }
+                Some(_) => (),
+                None => return Err(OpenError::AppNotFound(first_url(urls.as_ref()))),
+            }
+        }
+        let app = remap_app(self.app.as_ref().map(PathBuf::as_path))?;
+        if self.assess_before_open {
+            self.preflight(app.as_ref(), urls.as_ref())?;
+        }
+        if let Some(ref team_id) = self.team_id {
+            self.verify_team(team_id, urls.as_ref())?;
+        }
+
+        #[cfg(feature = "workspace")]
+        {
+            let supported = (app.is_some() || urls.as_ref().map(CFArray::len) == Some(1))
+                && !self.redirects()
+                && self.verb == OpenVerb::Open;
+            let allowed = matches!(self.backend, Backend::Auto | Backend::Workspace);
+            if allowed && supported && workspace::available() {
+                return workspace::open(self, app, urls);
+            }
+        }
+
+        if self.backend == Backend::Workspace {
+            return Err(OpenError::Unsupported(
+                "NSWorkspace backend is not available",
+            ));
+        }
+        if self.needs_spawn() || self.backend == Backend::Spawn {
+            // Launch Services can't forward any of these, start the executable ourselves
+            return match self.app {
+                Some(ref app) => spawn(app, urls, self),
+                None => Err(OpenError::Unsupported(
+                    "arguments, environment, architecture and redirections require an app",
+                )),
+            };
+        }
+
+        if self.verb.event().is_some() {
+            if let Some(urls) = urls {
+                return self.launch_with_event(app, urls);
+            }
+        }
+
+        let spec = LSLaunchURLSpec {
+            app,
+            urls,
+            flags: self.launch_flags()?,
+            ..Default::default()
+        };
+
+        match open_from_url_spec(spec) {
+            Ok(app) => Ok(LaunchedApp::new(app.to_path(), None)),
+            Err(code) => Err(OpenError::Status(code)),
+        }
+    }
+}
+
+/// The app which received the opened urls. Clones, comparisons and the serialized form leave
+/// out the pipes of the standard streams, only the original can take them.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LaunchedApp {
+    path: Option<PathBuf>,
+    pid: Option<pid_t>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    stdin: Option<File>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    stdout: Option<File>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    stderr: Option<File>,
+    already_running: Option<bool>,
+    psn: Option<ProcessSerialNumber>,
+    backend: Backend,
+}
+
+impl Clone for LaunchedApp {
+    fn clone(&self) -> LaunchedApp {
+        LaunchedApp {
+            path: self.path.clone(),
+            pid: self.pid,
+            stdin: None,
+            stdout: None,
+            stderr: None,
+            already_running: self.already_running,
+            psn: self.psn,
+            backend: self.backend,
+        }
+    }
+}
+
+impl PartialEq for LaunchedApp {
+    fn eq(&self, other: &LaunchedApp) -> bool {
+        self.path == other.path
+            && self.pid == other.pid
+            && self.already_running == other.already_running
+            && self.psn == other.psn
+            && self.backend == other.backend
+    }
+}
+
+impl Eq for LaunchedApp {}
+
+impl LaunchedApp {
+    pub(crate) fn new(path: Option<PathBuf>, pid: Option<pid_t>) -> LaunchedApp {
+        LaunchedApp {
+            path,
+            pid,
+            stdin: None,
+            stdout: None,
+            stderr: None,
+            already_running: None,
+            psn: None,
+            backend: Backend::LaunchServices,
+        }
+    }
+
+    pub(crate) fn with_backend(mut self, backend: Backend) -> LaunchedApp {
+        self.backend = backend;
+        self
+    }
+
+    pub(crate) fn with_stdio(
+        mut self,
+        stdin: Option<File>,
+        stdout: Option<File>,
+        stderr: Option<File>,
+    ) -> LaunchedApp {
+        self.stdin = stdin;
+        self.stdout = stdout;
+        self.stderr = stderr;
+        self
+    }
+
+    /// Take the writing end of the standard input pipe, if `Redirect::Piped` was requested
+    pub fn take_stdin(&mut self) -> Option<File> {
+        self.stdin.take()
+    }
+
+    /// Take the reading end of the standard output pipe, if `Redirect::Piped` was requested
+    pub fn take_stdout(&mut self) -> Option<File> {
+        self.stdout.take()
+    }
+
+    /// Take the reading end of the standard error pipe, if `Redirect::Piped` was requested
+    pub fn take_stderr(&mut self) -> Option<File> {
+        self.stderr.take()
+    }
+
+    /// The process identifier of the app, only the NSWorkspace backend reports it
+    pub fn pid(&self) -> Option<pid_t> {
+        self.pid
+    }
+
+    /// The path of the app, if the system reported it
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_ref().map(PathBuf::as_path)
+    }
+
+    /// If the urls were handed to an instance which was already running instead of a newly
+    /// launched one, `None` unless `OpenOptions::detect_already_running` asked for it
+    pub fn already_running(&self) -> Option<bool> {
+        self.already_running
+    }
+
+    /// The process serial number of the instance which received the urls, if it was found
+    /// between the running apps right after the launch
+    pub fn psn(&self) -> Option<ProcessSerialNumber> {
+        self.psn
+    }
+
+    /// The system API which opened the urls, never `Backend::Auto`
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Consume the handle returning the path of the app
+    pub fn into_path(self) -> Option<PathBuf> {
+        self.path
+    }
+
+    /// Find the running instance of the app
+    pub fn running(&self) -> Option<RunningApp> {
+        if let Some(pid) = self.pid {
+            return running_app_for_pid(pid);
+        }
+
+        running_instances(self.path()?)
+            .into_iter()
+            .max_by_key(RunningApp::pid)
+    }
+
+    /// Wait for the app to show up between the running apps
+    pub(crate) fn wait_running(&self, timeout: Duration) -> Option<RunningApp> {
+        let start = Instant::now();
+        loop {
+            if let Some(running) = self.running() {
+                return Some(running);
+            }
+            if start.elapsed() > timeout {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    /// Block until the app exits, like `open -W`. Returns immediately if the app isn't running
+    pub fn wait(&self) {
+        if let Some(app) = self.running() {
+            app.wait();
+        }
+    }
+
+    /// Block until the app exits, giving up after the timeout (`OpenError::TimedOut`) or when
+    /// the token is cancelled (`OpenError::Cancelled`)
+    pub fn wait_timeout(
+        &self,
+        timeout: Option<Duration>,
+        cancel: Option<&CancelToken>,
+    ) -> OpenResult<()> {
+        self.wait_with(&Deadline::new(timeout, cancel))
+    }
+
+    fn wait_with(&self, deadline: &Deadline) -> OpenResult<()> {
+        match self.running() {
+            Some(app) => app.wait_with(deadline),
+            None => Ok(()),
+        }
+    }
+
+    /// Bring all the windows of the app to the front, e.g. after launching it with
+    /// `OpenOptions::activates(false)`
+    pub fn activate(&self) -> OpenResult<()> {
+        match self.running() {
+            Some(app) => app.activate(),
+            None => Err(OpenError::NotRunning(
+                self.path
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+            )),
+        }
+    }
+
+    /// Quit the app (see `RunningApp::terminate`)
+    pub fn terminate(&self, force: bool) -> OpenResult<()> {
+        match self.running() {
+            Some(app) => app.terminate(force),
+            None => Err(OpenError::NotRunning(
+                self.path
+                    .as_ref()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+/// The first url, to report that no app handles it
+fn first_url(urls: Option<&CFArray<CFURL>>) -> String {
+    urls.and_then(|urls| urls.get(0))
+        .map_or_else(String::new, |url| url.get_string().to_string())
+}
+
+/// The running instances of the app at the given path
+fn running_instances(path: &Path) -> Vec<RunningApp> {
+    let apps = match bundle_identifier(path) {
+        Some(bundle_id) => running_apps_for_bundle_id(&bundle_id),
+        None => running_apps(),
+    };
+    apps.into_iter()
+        .filter(|app| app.path() == Some(path))
+        .collect()
+}
+
+/// Open every item on its own with the given options (with its default handler if no app is
+/// configured), so a bad item doesn't prevent the others from being opened. Results are in
+/// the same order of the items.
+pub fn open_each<T: Openable>(items: &[T], options: &OpenOptions) -> Vec<OpenResult<LaunchedApp>> {
+    open_each_with_progress(items, options, |_| ())
+}
+
+/// Urls moved to the thread waiting for the launch to complete
+struct SendUrls(Option<CFArray<CFURL>>);
+
+// CFArray and CFURL are immutable, they can be shared between threads
+unsafe impl Send for SendUrls {}
+
+/// Open the urls in the background and invoke the callback once the system reports the outcome
+/// of the launch, with the launched app or the error. Invalid urls and apps are reported
+/// immediately.
+///
+/// `LSLaunchFlags::ASYNC` is ignored: the callback always receives the final result.
+pub fn open_with_completion<T, F>(urls: &T, options: &OpenOptions, callback: F) -> OpenResult<()>
+where
+    T: MultiOpenable + ?Sized,
+    F: FnOnce(OpenResult<LaunchedApp>) + Send + 'static,
+{
+    remap_app(options.app.as_ref().map(PathBuf::as_path))?;
+    let urls = SendUrls(remap_multiopenable(Some(urls))?);
+
+    let mut options = options.clone();
+    options.flags.remove(LSLaunchFlags::ASYNC);
+
+    thread::spawn(move || {
+        let urls = urls;
+        callback(options.launch_urls(urls.0));
+    });
+    Ok(())
+}
+
+/// Open the url with its default handler, then block until the handler exits (like
+/// `open -W`)
+pub fn open_and_wait<T: Openable + ?Sized>(url: &T) -> OpenResult<()> {
+    let url = Openable::into_openable(url).ok_or(OpenError::InvalidUrl)?;
+    Opener::global()
+        .options()
+        .launch_urls(Some(CFArray::<CFURL>::from_CFTypes(&[url])))?
+        .wait();
+    Ok(())
+}
+
+/// Open the url with its default handler in the front, hiding every other app, e.g. for a
+/// presentation or a kiosk
+pub fn focus_mode_open<T: Openable + ?Sized>(url: &T) -> OpenResult<LaunchedApp> {
+    let url = Openable::into_openable(url).ok_or(OpenError::InvalidUrl)?;
+    Opener::global()
+        .options()
+        .clone()
+        .activates(true)
+        .hide_others(true)
+        .launch_urls(Some(CFArray::<CFURL>::from_CFTypes(&[url])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_each() {
+        let res = open_each(
+            &["https://www.google.com/", "not a valid url"],
+            &OpenOptions::new(),
+        );
+        assert_eq!(res.len(), 2);
+        assert!(res[0].is_ok());
+        match res[1] {
+            Err(OpenError::InvalidUrl) => (),
+            ref other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_open_options_policy() {
+        let dir =
+            std::env::temp_dir().join(format!("macos-open-test-policy-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let webloc = dir.join("script.webloc");
+        std::fs::write(
+            &webloc,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <plist version=\"1.0\"><dict><key>URL</key>\
+             <string>javascript:alert(1)</string></dict></plist>",
+        )
+        .unwrap();
+
+        let mut policy = OpenPolicy::new();
+        policy.allow("file").deny("javascript");
+        let res = OpenOptions::new()
+            .policy(policy)
+            .follow_aliases(true)
+            .open(webloc.as_path());
+        std::fs::remove_dir_all(&dir).unwrap();
+        match res {
+            Err(OpenError::Denied(url)) => assert_eq!(url, "javascript:alert(1)"),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_open_options_chunk_size() {
+        let mut urls = (0..5)
+            .map(|i| format!("https://www.example.com/{}", i))
+            .collect::<Vec<_>>();
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let seen = delivered.clone();
+        let app = OpenOptions::new()
+            .app("/Applications/Safari.app")
+            .chunk_size(2)
+            .progress(move |progress| seen.lock().unwrap().push(progress.delivered()))
+            .open(&urls)
+            .unwrap();
+        assert_eq!(app.path(), Some(Path::new("/Applications/Safari.app")));
+        assert_eq!(*delivered.lock().unwrap(), vec![0, 2, 4, 5]);
+
+        // a url of the second chunk is denied, so not even the first chunk is opened
+        urls[3] = "javascript:alert(1)".to_string();
+        let mut policy = OpenPolicy::new();
+        policy.deny("javascript");
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let seen = delivered.clone();
+        let res = OpenOptions::new()
+            .app("/Applications/Safari.app")
+            .chunk_size(2)
+            .policy(policy)
+            .progress(move |progress| seen.lock().unwrap().push(progress.delivered()))
+            .open(&urls);
+        match res {
+            Err(OpenError::Denied(url)) => assert_eq!(url, "javascript:alert(1)"),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(delivered.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_open_with_completion() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        open_with_completion(
+            "https://www.google.com/",
+            OpenOptions::new().flags(LSLaunchFlags::ASYNC),
+            move |res| tx.send(res.is_ok()).unwrap(),
+        )
+        .unwrap();
+        assert!(rx.recv().unwrap());
+    }
+
+    #[test]
+    fn test_open_options_arch() {
+        let app = OpenOptions::new()
+            .app("/System/Applications/Calculator.app")
+            .arch(Architecture::Arm64)
+            .launch()
+            .unwrap();
+        assert!(app.pid().is_some());
+        app.terminate(true).unwrap();
+    }
+
+    #[test]
+    fn test_open_options_stdout() {
+        use std::io::Read;
+        use std::os::unix::fs::PermissionsExt;
+
+        // an app whose executable echoes its arguments, so the output is known
+        let dir = std::env::temp_dir().join(format!("macos-open-stdout-{}", std::process::id()));
+        let app = dir.join("Echo.app");
+        let macos = app.join("Contents").join("MacOS");
+        std::fs::create_dir_all(&macos).unwrap();
+        std::fs::write(
+            app.join("Contents").join("Info.plist"),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<plist version=\"1.0\"><dict>\
+             <key>CFBundleExecutable</key><string>echo</string>\
+             <key>CFBundleIdentifier</key><string>org.example.echo</string>\
+             </dict></plist>",
+        )
+        .unwrap();
+        let executable = macos.join("echo");
+        std::fs::write(&executable, "#!/bin/sh\necho \"$@\"\n").unwrap();
+        std::fs::set_permissions(&executable, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut launched = OpenOptions::new()
+            .app(&app)
+            .arg("hello")
+            .stdout(Redirect::Piped)
+            .stderr(Redirect::Null)
+            .launch()
+            .unwrap();
+        assert_eq!(launched.backend(), Backend::Spawn);
+        assert!(launched.clone().take_stdout().is_none());
+        assert_eq!(launched.clone(), launched);
+        let mut stdout = launched.take_stdout().unwrap();
+        assert!(launched.take_stdout().is_none());
+        let mut out = String::new();
+        stdout.read_to_string(&mut out).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(out, "hello\n");
+    }
+
+    #[test]
+    fn test_open_options_fresh() {
+        let mut options = OpenOptions::new();
+        options.arg("--verbose").fresh(true);
+        assert_eq!(
+            options.arguments(),
+            vec!["--verbose", "-ApplePersistenceIgnoreState", "YES"]
+        );
+    }
+
+    #[test]
+    fn test_open_options_hide_from_recents() {
+        let mut options = OpenOptions::new();
+        assert!(!options
+            .launch_flags()
+            .unwrap()
+            .contains(LSLaunchFlags::DONT_ADD_TO_RECENTS));
+        options.hide_from_recents(true);
+        assert!(options
+            .launch_flags()
+            .unwrap()
+            .contains(LSLaunchFlags::DONT_ADD_TO_RECENTS));
+        options.hide_from_recents(false);
+        assert!(!options.flags.contains(LSLaunchFlags::DONT_ADD_TO_RECENTS));
+    }
+
+    #[test]
+    fn test_open_options_only_if_running() {
+        let res = OpenOptions::new()
+            .app("/System/Applications/Chess.app")
+            .only_if_running(true)
+            .launch();
+        match res {
+            Err(OpenError::NotRunning(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_open_options_activates() {
+        let app = OpenOptions::new()
+            .app("/System/Applications/Calculator.app")
+            .activates(false)
+            .launch()
+            .unwrap();
+        app.wait_running(CHECK_IN_TIMEOUT).unwrap();
+        app.activate().unwrap();
+        app.terminate(true).unwrap();
+    }
+
+    #[test]
+    fn test_open_options_hide_others() {
+        let mut options = OpenOptions::new();
+        assert!(!options
+            .launch_flags()
+            .unwrap()
+            .contains(LSLaunchFlags::AND_HIDE_OTHERS));
+        options.hide_others(true);
+        assert!(options
+            .launch_flags()
+            .unwrap()
+            .contains(LSLaunchFlags::AND_HIDE_OTHERS));
+        options.activates(false);
+        match options.launch_flags() {
+            Err(OpenError::IncompatibleOptions(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_open_options_app() {
+        let app = OpenOptions::new()
+            .app("/Applications/Safari.app")
+            .open("https://news.ycombinator.com/")
+            .unwrap();
+        assert_eq!(app.path(), Some(Path::new("/Applications/Safari.app")));
+
+        let app = OpenOptions::new()
+            .app("/Applications/Safari.app")
+            .backend(Backend::LaunchServices)
+            .open("https://news.ycombinator.com/")
+            .unwrap();
+        assert_eq!(app.backend(), Backend::LaunchServices);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_open_options_serde() {
+        let mut options = OpenOptions::new();
+        options
+            .app("/Applications/Safari.app")
+            .flags(LSLaunchFlags::DEFAULTS | LSLaunchFlags::AND_HIDE)
+            .timeout(Duration::from_secs(3))
+            .cancel_token(CancelToken::new())
+            .retry(RetryPolicy::new(3))
+            .policy(OpenPolicy::untrusted())
+            .progress(|_| ());
+        let json = serde_json::to_value(&options).unwrap();
+        assert_eq!(json["flags"], 0x0010_0001);
+        for skipped in &["cancel", "retry", "policy", "progress"] {
+            assert!(json.get(skipped).is_none(), "{} is serialized", skipped);
+        }
+
+        let back: OpenOptions = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            back.flags,
+            LSLaunchFlags::DEFAULTS | LSLaunchFlags::AND_HIDE
+        );
+        assert!(back.cancel.is_none() && back.retry.is_none() && back.policy.is_none());
+        assert!(back.progress.is_none());
+        assert_eq!(serde_json::to_value(&back).unwrap(), json);
+        let partial: OpenOptions = serde_json::from_str("{\"chunk_size\":2}").unwrap();
+        assert_eq!(partial.flags, LSLaunchFlags::DEFAULTS);
+        assert_eq!(partial.chunk_size, 2);
+    }
+
+    #[test]
+    fn test_open_options_timeout() {
+        let res = OpenOptions::new()
+            .timeout(Duration::from_secs(1))
+            .terminate_on_abort(true)
+            .open_and_wait("/System/Applications/Chess.app");
+        match res {
+            Err(OpenError::TimedOut) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_open_options_merge_tabs() {
+        let app = OpenOptions::new()
+            .app("/System/Applications/TextEdit.app")
+            .merge_tabs(true)
+            .open(&["/etc/hosts", "/etc/shells"][..])
+            .unwrap();
+        assert_eq!(
+            app.path(),
+            Some(Path::new("/System/Applications/TextEdit.app"))
+        );
+    }
+
+    #[test]
+    fn test_open_options_already_running() {
+        let mut options = OpenOptions::new();
+        options.app("/System/Library/CoreServices/Finder.app");
+        let app = options.launch().unwrap();
+        assert_eq!(app.already_running(), None);
+        assert!(app.psn().is_some());
+        let app = options.detect_already_running(true).launch().unwrap();
+        assert_eq!(app.already_running(), Some(true));
+    }
+}
@@ -0,0 +1,180 @@
+//! Notifications about applications being launched and terminated.
+
+use crate::macos::bundle::bundle_identifier;
+use crate::macos::running::RunningApp;
+use crate::macos::watcher::Poller;
+
+use libc::pid_t;
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::os::raw::{c_int, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const PROC_PIDPATHINFO_MAXSIZE: usize = 4096;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+extern "C" {
+    fn proc_listallpids(buffer: *mut c_void, buffersize: c_int) -> c_int;
+    fn proc_pidpath(pid: c_int, buffer: *mut c_void, buffersize: u32) -> c_int;
+}
+
+/// Something happened to an application
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppEvent {
+    /// The app has been launched
+    Launched(RunningApp),
+    /// The app has terminated
+    Terminated(RunningApp),
+}
+
+fn all_pids() -> Vec<pid_t> {
+    let count = unsafe { proc_listallpids(std::ptr::null_mut(), 0) };
+    if count <= 0 {
+        return Vec::new();
+    }
+
+    // leave room for processes spawned in the meantime
+    let mut pids: Vec<pid_t> = vec![0; count as usize + 32];
+    let size = (pids.len() * std::mem::size_of::<pid_t>()) as c_int;
+    let count = unsafe { proc_listallpids(pids.as_mut_ptr() as *mut c_void, size) };
+    pids.truncate(count.max(0) as usize);
+    pids
+}
+
+fn executable_path(pid: pid_t) -> Option<PathBuf> {
+    let mut buf = [0u8; PROC_PIDPATHINFO_MAXSIZE];
+    let len = unsafe { proc_pidpath(pid, buf.as_mut_ptr() as *mut c_void, buf.len() as u32) };
+    if len <= 0 {
+        None
+    } else {
+        Some(PathBuf::from(OsStr::from_bytes(&buf[..len as usize])))
+    }
+}
+
+/// The innermost app bundle containing the path
+fn enclosing_app(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|ancestor| {
+        ancestor
+            .extension()
+            .map(|ext| ext == "app")
+            .unwrap_or(false)
+    })
+}
+
+/// Build the app for a process, None for processes that are not part of an app bundle
+fn app_for_pid(pid: pid_t) -> Option<RunningApp> {
+    let executable = executable_path(pid)?;
+    let app = enclosing_app(&executable)?.to_path_buf();
+    Some(RunningApp::new(pid, bundle_identifier(&app), Some(app)))
+}
+
+/// A running watcher, the watch stops when dropped
+pub struct LifecycleWatcher(Poller);
+
+impl LifecycleWatcher {
+    /// Stop watching and wait for the watcher thread to finish
+    pub fn stop(self) {
+        self.0.stop();
+    }
+}
+
+/// Call `callback` from a background thread every time an app (optionally only the ones with
+/// the given bundle identifier) is launched or terminates. Apps already running when the watch
+/// starts are not reported.
+///
+/// The process table is polled, so this works in processes not running an AppKit main run
+/// loop; events are reported with up to half a second of delay.
+pub fn watch_app_lifecycle<F>(bundle_id: Option<&str>, mut callback: F) -> LifecycleWatcher
+where
+    F: FnMut(AppEvent) + Send + 'static,
+{
+    let bundle_id = bundle_id.map(str::to_lowercase);
+
+    let matches = move |app: &RunningApp| match bundle_id {
+        Some(ref bundle_id) => app
+            .bundle_id()
+            .map(|id| id.to_lowercase() == *bundle_id)
+            .unwrap_or(false),
+        None => true,
+    };
+
+    LifecycleWatcher(Poller::spawn(POLL_INTERVAL, move || {
+        let mut known: HashMap<pid_t, Option<RunningApp>> = all_pids()
+            .into_iter()
+            .map(|pid| (pid, app_for_pid(pid).filter(|app| matches(app))))
+            .collect();
+
+        move || {
+            let pids = all_pids();
+            let mut current = HashMap::with_capacity(pids.len());
+            for pid in pids {
+                let app = match known.remove(&pid) {
+                    Some(app) => app,
+                    None => {
+                        let app = app_for_pid(pid).filter(|app| matches(app));
+                        if let Some(ref app) = app {
+                            callback(AppEvent::Launched(app.clone()));
+                        }
+                        app
+                    }
+                };
+                current.insert(pid, app);
+            }
+
+            for (_, app) in known.drain() {
+                if let Some(app) = app {
+                    callback(AppEvent::Terminated(app));
+                }
+            }
+            known = current;
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macos::running::quit;
+    use crate::macos::OpenOptions;
+
+    use std::sync::mpsc::channel;
+    use std::thread;
+
+    #[test]
+    fn test_enclosing_app() {
+        assert_eq!(
+            enclosing_app(Path::new("/Applications/Safari.app/Contents/MacOS/Safari")),
+            Some(Path::new("/Applications/Safari.app"))
+        );
+        assert_eq!(enclosing_app(Path::new("/usr/bin/open")), None);
+    }
+
+    #[test]
+    fn test_watch_app_lifecycle() {
+        let (tx, rx) = channel();
+        let watcher = watch_app_lifecycle(Some("com.apple.calculator"), move |event| {
+            tx.send(event).unwrap();
+        });
+        thread::sleep(POLL_INTERVAL);
+
+        OpenOptions::new()
+            .app("/System/Applications/Calculator.app")
+            .launch()
+            .unwrap();
+        match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            AppEvent::Launched(app) => assert_eq!(app.bundle_id(), Some("com.apple.calculator")),
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        quit("com.apple.calculator", true).unwrap();
+        match rx.recv_timeout(Duration::from_secs(5)).unwrap() {
+            AppEvent::Terminated(app) => assert_eq!(app.bundle_id(), Some("com.apple.calculator")),
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        watcher.stop();
+    }
+}
@@ -0,0 +1,173 @@
+//! Well known folders, resolved through the system folder lookup APIs.
+
+use crate::macos::{open_default, OpenError, OpenResult};
+
+use std::ffi::{CStr, OsStr};
+use std::os::raw::{c_char, c_uint};
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+
+const PATH_MAX: usize = 1024;
+
+const SYSDIR_DIRECTORY_APPLICATION: c_uint = 1;
+const SYSDIR_DIRECTORY_LIBRARY: c_uint = 5;
+const SYSDIR_DIRECTORY_DOCUMENT: c_uint = 9;
+const SYSDIR_DIRECTORY_DESKTOP: c_uint = 12;
+const SYSDIR_DIRECTORY_CACHES: c_uint = 13;
+const SYSDIR_DIRECTORY_APPLICATION_SUPPORT: c_uint = 14;
+const SYSDIR_DIRECTORY_DOWNLOADS: c_uint = 15;
+const SYSDIR_DIRECTORY_MOVIES: c_uint = 17;
+const SYSDIR_DIRECTORY_MUSIC: c_uint = 18;
+const SYSDIR_DIRECTORY_PICTURES: c_uint = 19;
+const SYSDIR_DIRECTORY_SHARED_PUBLIC: c_uint = 21;
+
+const SYSDIR_DOMAIN_MASK_USER: c_uint = 1;
+const SYSDIR_DOMAIN_MASK_LOCAL: c_uint = 2;
+
+const K_USER_DOMAIN: i16 = -32763;
+const K_TRASH_FOLDER_TYPE: u32 = 0x7472_7368; // 'trsh'
+
+#[repr(C)]
+struct FSRef {
+    hidden: [u8; 80],
+}
+
+extern "C" {
+    fn sysdir_start_search_path_enumeration(dir: c_uint, domain_mask: c_uint) -> c_uint;
+    fn sysdir_get_next_search_path_enumeration(state: c_uint, path: *mut c_char) -> c_uint;
+}
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn FSFindFolder(vRefNum: i16, folderType: u32, createFolder: u8, foundRef: *mut FSRef) -> i16;
+    fn FSRefMakePath(fsRef: *const FSRef, path: *mut u8, pathBufferSize: u32) -> i32;
+}
+
+/// A well known folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpecialLocation {
+    /// The system wide applications folder (`/Applications`)
+    Applications,
+    /// The user's applications folder (`~/Applications`)
+    UserApplications,
+    /// The user's home folder
+    Home,
+    /// The user's desktop
+    Desktop,
+    /// The user's documents folder
+    Documents,
+    /// The user's downloads folder
+    Downloads,
+    /// The user's library folder
+    Library,
+    /// The user's application support folder
+    ApplicationSupport,
+    /// The user's caches folder
+    Caches,
+    /// The user's movies folder
+    Movies,
+    /// The user's music folder
+    Music,
+    /// The user's pictures folder
+    Pictures,
+    /// The user's public folder
+    Public,
+    /// The user's Trash
+    Trash,
+}
+
+fn home() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(PathBuf::from)
+}
+
+fn sysdir(dir: c_uint, domain_mask: c_uint) -> Option<PathBuf> {
+    let mut buf = [0 as c_char; PATH_MAX];
+
+    let state = unsafe { sysdir_start_search_path_enumeration(dir, domain_mask) };
+    if unsafe { sysdir_get_next_search_path_enumeration(state, buf.as_mut_ptr()) } == 0 {
+        return None;
+    }
+
+    let path = OsStr::from_bytes(unsafe { CStr::from_ptr(buf.as_ptr()) }.to_bytes());
+    match path.as_bytes() {
+        b"~" => home(),
+        bytes if bytes.starts_with(b"~/") => Some(home()?.join(OsStr::from_bytes(&bytes[2..]))),
+        _ => Some(PathBuf::from(path)),
+    }
+}
+
+fn user_sysdir(dir: c_uint) -> Option<PathBuf> {
+    sysdir(dir, SYSDIR_DOMAIN_MASK_USER)
+}
+
+fn trash() -> Option<PathBuf> {
+    let mut folder = FSRef { hidden: [0; 80] };
+    if unsafe { FSFindFolder(K_USER_DOMAIN, K_TRASH_FOLDER_TYPE, 0, &mut folder) } != 0 {
+        return None;
+    }
+
+    let mut buf = [0u8; PATH_MAX];
+    if unsafe { FSRefMakePath(&folder, buf.as_mut_ptr(), buf.len() as u32) } != 0 {
+        return None;
+    }
+
+    let path = unsafe { CStr::from_ptr(buf.as_ptr() as *const c_char) };
+    Some(PathBuf::from(OsStr::from_bytes(path.to_bytes())))
+}
+
+/// Get the path of a well known folder
+pub fn path_of(location: SpecialLocation) -> Option<PathBuf> {
+    match location {
+        SpecialLocation::Applications => {
+            sysdir(SYSDIR_DIRECTORY_APPLICATION, SYSDIR_DOMAIN_MASK_LOCAL)
+        }
+        SpecialLocation::UserApplications => user_sysdir(SYSDIR_DIRECTORY_APPLICATION),
+        SpecialLocation::Home => home(),
+        SpecialLocation::Desktop => user_sysdir(SYSDIR_DIRECTORY_DESKTOP),
+        SpecialLocation::Documents => user_sysdir(SYSDIR_DIRECTORY_DOCUMENT),
+        SpecialLocation::Downloads => user_sysdir(SYSDIR_DIRECTORY_DOWNLOADS),
+        SpecialLocation::Library => user_sysdir(SYSDIR_DIRECTORY_LIBRARY),
+        SpecialLocation::ApplicationSupport => user_sysdir(SYSDIR_DIRECTORY_APPLICATION_SUPPORT),
+        SpecialLocation::Caches => user_sysdir(SYSDIR_DIRECTORY_CACHES),
+        SpecialLocation::Movies => user_sysdir(SYSDIR_DIRECTORY_MOVIES),
+        SpecialLocation::Music => user_sysdir(SYSDIR_DIRECTORY_MUSIC),
+        SpecialLocation::Pictures => user_sysdir(SYSDIR_DIRECTORY_PICTURES),
+        SpecialLocation::Public => user_sysdir(SYSDIR_DIRECTORY_SHARED_PUBLIC),
+        SpecialLocation::Trash => trash(),
+    }
+}
+
+/// Open a well known folder in Finder
+pub fn open_location(location: SpecialLocation) -> OpenResult<Option<PathBuf>> {
+    match path_of(location) {
+        Some(path) => open_default(&path),
+        None => Err(OpenError::InvalidUrl),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_of() {
+        assert_eq!(
+            path_of(SpecialLocation::Applications),
+            Some(PathBuf::from("/Applications"))
+        );
+        let home = home().unwrap();
+        assert_eq!(
+            path_of(SpecialLocation::Library),
+            Some(home.join("Library"))
+        );
+        assert!(path_of(SpecialLocation::Downloads)
+            .unwrap()
+            .starts_with(&home));
+        assert_eq!(path_of(SpecialLocation::Trash), Some(home.join(".Trash")));
+    }
+
+    #[test]
+    fn test_open_location() {
+        assert!(open_location(SpecialLocation::Downloads).is_ok());
+    }
+}
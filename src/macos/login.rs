@@ -0,0 +1,153 @@
+//! Apps opened automatically at login.
+//!
+//! Since macOS 13 the login items are managed by `SMAppService`, which only lets an app
+//! register itself: the running app can still be added and removed, any other app fails with
+//! `OpenError::Unsupported`.
+
+use crate::macos::ns::{to_string, AutoreleasePool, Id};
+use crate::macos::os::macos_version;
+use crate::macos::shared_file_list::{
+    kLSSharedFileListLoginItemHidden, kLSSharedFileListSessionLoginItems, SharedFileList,
+};
+use crate::macos::{OpenError, OpenResult};
+
+use core_foundation::base::TCFType;
+use core_foundation::boolean::CFBoolean;
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::string::CFString;
+use core_foundation::url::CFURL;
+use objc::runtime::{BOOL, NO};
+
+use std::path::{Path, PathBuf};
+
+/// The first macOS version managing the login items with `SMAppService`
+const APP_SERVICE_VERSION: u64 = 13;
+
+#[link(name = "ServiceManagement", kind = "framework")]
+extern "C" {}
+
+fn login_items() -> OpenResult<SharedFileList> {
+    SharedFileList::new(unsafe { kLSSharedFileListSessionLoginItems })
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// If the login items are managed by `SMAppService` instead of the shared file list
+fn uses_app_service() -> bool {
+    matches!(macos_version(), Some(ref version) if version.major() >= APP_SERVICE_VERSION)
+}
+
+/// If the path is the bundle of the running app, the only one `SMAppService` can register
+fn is_main_bundle(app_path: &Path) -> bool {
+    let _pool = AutoreleasePool::new();
+    let path = unsafe {
+        let bundle: Id = msg_send![class!(NSBundle), mainBundle];
+        let path: Id = msg_send![bundle, bundlePath];
+        to_string(path)
+    };
+    path.map(|path| canonical(Path::new(&path))).as_deref() == Some(app_path)
+}
+
+/// Register the running app as a login item, or unregister it
+fn main_app_service(register: bool) -> OpenResult<()> {
+    let _pool = AutoreleasePool::new();
+    unsafe {
+        let service: Id = msg_send![class!(SMAppService), mainAppService];
+        let mut error: Id = std::ptr::null_mut();
+        let done: BOOL = if register {
+            msg_send![service, registerAndReturnError: &mut error]
+        } else {
+            msg_send![service, unregisterAndReturnError: &mut error]
+        };
+        if done != NO {
+            return Ok(());
+        }
+        let code: isize = if error.is_null() {
+            0
+        } else {
+            msg_send![error, code]
+        };
+        Err(OpenError::Status(code as i32))
+    }
+}
+
+/// Login items of other apps can't be changed anymore
+fn unsupported() -> OpenError {
+    OpenError::Unsupported("only the running app can change its login item on macOS 13 and later")
+}
+
+/// Open the app at login, with its windows hidden when `hidden` is true. Nothing is added if
+/// the app is already a login item. On macOS 13 and later only the running app can be added,
+/// through `SMAppService`, and `hidden` is ignored.
+pub fn add_login_item(app_path: &Path, hidden: bool) -> OpenResult<()> {
+    let app_path = canonical(app_path);
+    if uses_app_service() {
+        return if is_main_bundle(&app_path) {
+            main_app_service(true)
+        } else {
+            Err(unsupported())
+        };
+    }
+
+    let list = login_items()?;
+    if list
+        .items()
+        .iter()
+        .any(|item| item.path().as_ref() == Some(&app_path))
+    {
+        return Ok(());
+    }
+
+    let url = CFURL::from_path(&app_path, true).ok_or(OpenError::InvalidApp)?;
+    let properties = CFDictionary::from_CFType_pairs(&[(
+        unsafe { CFString::wrap_under_get_rule(kLSSharedFileListLoginItemHidden) },
+        CFBoolean::from(hidden),
+    )]);
+    list.insert(&url, &properties)
+}
+
+/// Stop opening the app at login. Fails with `OpenError::AppNotFound` if the app isn't a login
+/// item. On macOS 13 and later only the running app can be removed, through `SMAppService`.
+pub fn remove_login_item(app_path: &Path) -> OpenResult<()> {
+    let app_path = canonical(app_path);
+    if uses_app_service() {
+        return if is_main_bundle(&app_path) {
+            main_app_service(false)
+        } else {
+            Err(unsupported())
+        };
+    }
+
+    let list = login_items()?;
+    let items = list.items();
+    let item = items
+        .iter()
+        .find(|item| item.path().as_ref() == Some(&app_path))
+        .ok_or_else(|| OpenError::AppNotFound(app_path.display().to_string()))?;
+    list.remove(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "it changes the login items of the user"]
+    fn test_login_items() {
+        let app = Path::new("/System/Applications/Calculator.app");
+        if uses_app_service() {
+            match add_login_item(app, true) {
+                Err(OpenError::Unsupported(_)) => return,
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        add_login_item(app, true).unwrap();
+        remove_login_item(app).unwrap();
+        match remove_login_item(app) {
+            Err(OpenError::AppNotFound(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
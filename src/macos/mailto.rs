@@ -0,0 +1,161 @@
+//! `mailto:` URLs (RFC 6068).
+
+use crate::macos::{open_default, OpenResult};
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// A `mailto:` URL builder.
+///
+/// ```no_run
+/// use macos_open::{open_email, Mailto};
+///
+/// open_email(
+///     Mailto::new()
+///         .to("someone@example.com")
+///         .subject("Grüße")
+///         .body("Hi,\nhow are you?"),
+/// )
+/// .unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mailto {
+    to: Vec<String>,
+    cc: Vec<String>,
+    bcc: Vec<String>,
+    subject: Option<String>,
+    body: Option<String>,
+}
+
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~".contains(&byte)
+}
+
+/// Percent-encode every byte which isn't unreserved nor in `keep`
+pub(crate) fn encode(value: &str, keep: &[u8], f: &mut fmt::Formatter) -> fmt::Result {
+    for &byte in value.as_bytes() {
+        if is_unreserved(byte) || keep.contains(&byte) {
+            write!(f, "{}", byte as char)?;
+        } else {
+            write!(f, "%{:02X}", byte)?;
+        }
+    }
+    Ok(())
+}
+
+/// Line breaks must be encoded as CRLF
+fn normalize_newlines(value: &str) -> String {
+    value.replace("\r\n", "\n").replace('\n', "\r\n")
+}
+
+impl Mailto {
+    /// An empty message
+    pub fn new() -> Mailto {
+        Default::default()
+    }
+
+    /// Add a recipient
+    pub fn to<S: Into<String>>(&mut self, address: S) -> &mut Mailto {
+        self.to.push(address.into());
+        self
+    }
+
+    /// Add a carbon copy recipient
+    pub fn cc<S: Into<String>>(&mut self, address: S) -> &mut Mailto {
+        self.cc.push(address.into());
+        self
+    }
+
+    /// Add a blind carbon copy recipient
+    pub fn bcc<S: Into<String>>(&mut self, address: S) -> &mut Mailto {
+        self.bcc.push(address.into());
+        self
+    }
+
+    /// Set the subject
+    pub fn subject<S: Into<String>>(&mut self, subject: S) -> &mut Mailto {
+        self.subject = Some(subject.into());
+        self
+    }
+
+    /// Set the body
+    pub fn body<S: Into<String>>(&mut self, body: S) -> &mut Mailto {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Build the URL
+    pub fn url(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for Mailto {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("mailto:")?;
+        for (i, address) in self.to.iter().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
+            }
+            encode(address, b"@", f)?;
+        }
+
+        let mut separator = "?";
+        for (name, addresses) in [("cc", &self.cc), ("bcc", &self.bcc)].iter() {
+            if addresses.is_empty() {
+                continue;
+            }
+            write!(f, "{}{}=", separator, name)?;
+            separator = "&";
+            for (i, address) in addresses.iter().enumerate() {
+                if i != 0 {
+                    f.write_str(",")?;
+                }
+                encode(address, b"@", f)?;
+            }
+        }
+
+        for (name, value) in [("subject", &self.subject), ("body", &self.body)].iter() {
+            if let Some(value) = value {
+                write!(f, "{}{}=", separator, name)?;
+                separator = "&";
+                encode(&normalize_newlines(value), b"", f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Compose a message with the default mail client
+pub fn open_email(mail: &Mailto) -> OpenResult<Option<PathBuf>> {
+    open_default(&mail.url())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mailto_url() {
+        assert_eq!(Mailto::new().url(), "mailto:");
+        assert_eq!(
+            Mailto::new()
+                .to("a@example.com")
+                .to("b+tag@example.com")
+                .url(),
+            "mailto:a@example.com,b%2Btag@example.com"
+        );
+        assert_eq!(
+            Mailto::new()
+                .to("a@example.com")
+                .cc("c@example.com")
+                .bcc("d@example.com")
+                .subject("Grüße & more")
+                .body("line 1\nline 2")
+                .url(),
+            "mailto:a@example.com?cc=c@example.com&bcc=d@example.com\
+             &subject=Gr%C3%BC%C3%9Fe%20%26%20more&body=line%201%0D%0Aline%202"
+        );
+    }
+}
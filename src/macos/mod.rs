@@ -0,0 +1,1184 @@
+//! The implementation on macOS, a wrapper around Core Foundation, Launch Services and File
+//! Metadata frameworks.
+
+use core_foundation::array::{CFArray, CFArrayRef};
+use core_foundation::base::{CFIndex, TCFType};
+use core_foundation::error::{CFError, CFErrorRef};
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation::url::{CFURLRef, CFURL};
+use core_foundation_sys::base::{kCFAllocatorDefault, CFAllocatorRef};
+use launch_services::{
+    application_urls_for_bundle_identifier, can_url_accept_url, default_application_url_for_url,
+    LSAcceptanceFlags,
+};
+
+pub use launch_services::{LSLaunchFlags, LSRolesMask};
+
+use std::collections::HashMap;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub use core_foundation_sys::base::OSStatus;
+
+use fast_escape::Escaper;
+use file_metadata::mditem::attributes;
+use void::ResultVoidExt;
+
+use url::{ParseError, Url};
+
+// first, so its macros are visible to the other modules
+#[macro_use]
+mod trace;
+
+mod accept;
+mod alias;
+mod apple_event;
+mod associations;
+mod bookmark;
+mod browser;
+mod bundle;
+mod cache;
+mod cancel;
+mod claims;
+mod cloud;
+mod codesign;
+mod data;
+mod editor;
+mod find;
+mod flags;
+mod handlers;
+mod launch;
+mod lifecycle;
+mod location;
+mod login;
+mod mailto;
+#[cfg(feature = "async")]
+mod nonblocking;
+mod ns;
+mod opener;
+mod os;
+mod plan;
+mod policy;
+#[cfg(feature = "probe")]
+mod probe;
+mod progress;
+mod quarantine;
+mod rank;
+mod recents;
+mod remote;
+mod retry;
+mod reveal;
+#[cfg(feature = "router")]
+mod router;
+pub mod running;
+mod session;
+mod settings;
+mod shared_file_list;
+mod spawn;
+mod spotlight;
+mod tags;
+mod target;
+mod terminal;
+mod trash;
+mod version;
+mod volume;
+mod watcher;
+#[cfg(feature = "workspace")]
+mod workspace;
+mod xcallback;
+
+pub use crate::error::{OpenError, OpenResult};
+pub use accept::app_acceptance;
+pub use alias::resolve_alias;
+pub use associations::{apply_associations, export_associations, AssociationMap, RoleHandlers};
+pub use bookmark::{bookmark_for_path, open_bookmark, resolve_bookmark, SecurityScope};
+pub use browser::{default_browser, open_in_browser, open_in_default_browser, BrowserTarget};
+pub use bundle::{
+    declared_document_types_for_app, declared_schemes_for_app, AppInfo, DocumentType,
+};
+pub use cache::LookupCache;
+pub use cancel::CancelToken;
+pub use claims::AcceptanceCache;
+pub use cloud::{download_status, DownloadStatus};
+pub use codesign::{verify_signature, SignatureInfo};
+pub use data::{open_data, TempDocument};
+pub use editor::{open_at, open_at_with};
+pub use find::{find_apps, installed_apps, AppFilter, AppScope};
+pub use flags::LaunchOptions;
+pub use handlers::{
+    register_app, scheme_has_handler, set_default_handler_for_scheme, set_default_handler_for_type,
+    uti_has_handler, watch_default_handler, HandlerWatcher,
+};
+pub use launch::{
+    focus_mode_open, open_and_wait, open_each, open_with_completion, Architecture, Backend,
+    LaunchedApp, OpenOptions, OpenVerb, ProcessSerialNumber, Redirect,
+};
+pub use lifecycle::{watch_app_lifecycle, AppEvent, LifecycleWatcher};
+pub use location::{open_location, path_of, SpecialLocation};
+pub use login::{add_login_item, remove_login_item};
+pub use mailto::{open_email, Mailto};
+#[cfg(feature = "async")]
+pub use nonblocking::{
+    apps_for_bundle_id_async, apps_for_name_async, open_and_wait_async, open_and_wait_with_async,
+    open_async,
+};
+pub use opener::Opener;
+pub use plan::{execute_plan, plan_open, HandlerInfo, OpenPlan};
+pub use policy::OpenPolicy;
+use progress::ProgressCallback;
+pub use progress::{open_each_with_progress, OpenProgress};
+pub use quarantine::{quarantine_status, QuarantineInfo};
+use rank::rank_apps;
+pub use recents::{clear_recent_documents, recent_apps, recent_documents};
+pub use remote::{open_remote, RemoteTarget};
+pub use retry::RetryPolicy;
+pub use reveal::{open_folder, reveal, reveal_selecting, FinderView};
+#[cfg(feature = "router")]
+pub use router::{RouteMatch, RouteRule, RouteTarget, Router};
+pub use session::{SavedTarget, Workspace, WorkspaceItem};
+pub use settings::{open_settings_pane, PrivacyPane, SettingsPane};
+use spotlight::run_query;
+pub use spotlight::{spotlight_query, Attribute, MDValue, QueryResult};
+pub use tags::{files_with_tag, set_tags, tags_for_path};
+pub use target::{OpenTarget, OpenableOptions, ResolvedTarget};
+pub use terminal::{open_terminal_at, TerminalApp};
+pub use trash::trash;
+use version::is_at_least;
+pub use version::Version;
+pub use volume::{eject, mounted_volumes};
+pub use xcallback::{XCallback, XCallbackFuture, XCallbackResponse, XCallbackRouter};
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn CFURLCreateWithString(
+        allocator: CFAllocatorRef,
+        urlString: CFStringRef,
+        baseURL: CFURLRef,
+    ) -> CFURLRef;
+}
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn LSCopyApplicationURLsForURL(
+        inURL: CFURLRef,
+        inRoleMask: u32,
+        outError: *mut CFErrorRef,
+    ) -> CFArrayRef;
+}
+
+/// A type implementing this trait can may be transformed in a CFURL and so opened.
+pub trait Openable {
+    /// Transform this type in a CFURL (Core Foundation URL).
+    fn into_openable(&self) -> Option<CFURL>;
+}
+
+fn url(value: &str) -> Option<CFURL> {
+    match Url::parse(value) {
+        Ok(u) => _url(&u.into_string()),
+        Err(ParseError::RelativeUrlWithoutBase) => {
+            let path = Path::new(value);
+            if path.exists() {
+                Openable::into_openable(&path)
+            } else {
+                _url(value).or_else(|| path_url(path))
+            }
+        }
+        Err(_) => _url(value),
+    }
+}
+
+fn _url(value: &str) -> Option<CFURL> {
+    let url = CFString::new(value);
+
+    let ptr = unsafe {
+        CFURLCreateWithString(
+            kCFAllocatorDefault,
+            url.as_concrete_TypeRef(),
+            std::ptr::null(),
+        )
+    };
+
+    if ptr.is_null() {
+        None
+    } else {
+        Some(unsafe { TCFType::wrap_under_create_rule(ptr) })
+    }
+}
+
+/// Encode a path which doesn't exist (yet) in a file url, relative paths are resolved against
+/// the current directory. Unlike `CFURLCreateWithString` spaces and non-ASCII bytes are fine.
+fn path_url(path: &Path) -> Option<CFURL> {
+    if path.is_relative() {
+        let path = std::env::current_dir().ok()?.join(path);
+        CFURL::from_path(&path, false)
+    } else {
+        CFURL::from_path(path, false)
+    }
+}
+
+impl Openable for &str {
+    fn into_openable(&self) -> Option<CFURL> {
+        url(self)
+    }
+}
+
+impl Openable for str {
+    fn into_openable(&self) -> Option<CFURL> {
+        url(self)
+    }
+}
+
+impl Openable for &String {
+    fn into_openable(&self) -> Option<CFURL> {
+        url(self)
+    }
+}
+
+impl Openable for String {
+    fn into_openable(&self) -> Option<CFURL> {
+        url(self)
+    }
+}
+
+impl Openable for &Path {
+    fn into_openable(&self) -> Option<CFURL> {
+        OpenableOptions::new().to_url(self)
+    }
+}
+
+impl Openable for Path {
+    fn into_openable(&self) -> Option<CFURL> {
+        OpenableOptions::new().to_url(self)
+    }
+}
+
+impl Openable for &PathBuf {
+    fn into_openable(&self) -> Option<CFURL> {
+        OpenableOptions::new().to_url(self)
+    }
+}
+
+impl Openable for PathBuf {
+    fn into_openable(&self) -> Option<CFURL> {
+        OpenableOptions::new().to_url(self)
+    }
+}
+
+/// A type implementing this trait can may be transformed in a CFArray<CFURL> and so opened.
+pub trait MultiOpenable {
+    /// Transform this type in a CFArray (Core Foundation array) of CFURL (Core Foundation URL).
+    fn into_openable(&self) -> Option<CFArray<CFURL>>;
+
+    /// The index and the debug representation of the items which can't be transformed in a
+    /// CFURL, used to report which ones are wrong when `into_openable` fails
+    fn invalid_items(&self) -> Vec<(usize, String)> {
+        Vec::new()
+    }
+}
+
+/// The items of a collection which aren't `Openable`
+fn invalid_items<'a, T, I>(items: I) -> Vec<(usize, String)>
+where
+    T: Openable + std::fmt::Debug + ?Sized + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(_, item)| Openable::into_openable(*item).is_none())
+        .map(|(index, item)| (index, format!("{:?}", item)))
+        .collect()
+}
+
+macro_rules! def_multiopenable_vec {
+    ( $type:ty ) => {
+        impl MultiOpenable for Vec<$type> {
+            fn into_openable(&self) -> Option<CFArray<CFURL>> {
+                let mut res: Vec<CFURL> = Vec::new();
+
+                for el in self.iter() {
+                    match Openable::into_openable(el) {
+                        None => return None,
+                        Some(url) => res.push(url),
+                    };
+                }
+
+                Some(CFArray::<CFURL>::from_CFTypes(&res[..]))
+            }
+
+            fn invalid_items(&self) -> Vec<(usize, String)> {
+                invalid_items(self.iter())
+            }
+        }
+
+        impl MultiOpenable for &[$type] {
+            fn into_openable(&self) -> Option<CFArray<CFURL>> {
+                let mut res: Vec<CFURL> = Vec::new();
+
+                for el in self.iter() {
+                    match Openable::into_openable(el) {
+                        None => return None,
+                        Some(url) => res.push(url),
+                    };
+                }
+
+                Some(CFArray::<CFURL>::from_CFTypes(&res[..]))
+            }
+
+            fn invalid_items(&self) -> Vec<(usize, String)> {
+                invalid_items(self.iter())
+            }
+        }
+
+        impl MultiOpenable for [$type] {
+            fn into_openable(&self) -> Option<CFArray<CFURL>> {
+                let mut res: Vec<CFURL> = Vec::new();
+
+                for el in self.iter() {
+                    match Openable::into_openable(el) {
+                        None => return None,
+                        Some(url) => res.push(url),
+                    };
+                }
+
+                Some(CFArray::<CFURL>::from_CFTypes(&res[..]))
+            }
+
+            fn invalid_items(&self) -> Vec<(usize, String)> {
+                invalid_items(self.iter())
+            }
+        }
+    };
+}
+
+macro_rules! def_multiopenable_type {
+    ( $type:ty ) => {
+        impl MultiOpenable for $type {
+            fn into_openable(&self) -> Option<CFArray<CFURL>> {
+                let v = vec![Openable::into_openable(self)?];
+                Some(CFArray::<CFURL>::from_CFTypes(&v[..]))
+            }
+
+            fn invalid_items(&self) -> Vec<(usize, String)> {
+                invalid_items(std::iter::once(self))
+            }
+        }
+    };
+}
+
+macro_rules! def_multiopenable {
+    ( $type:ty ) => {
+        def_multiopenable_vec!($type);
+        def_multiopenable_type!($type);
+    };
+}
+
+def_multiopenable!(&str);
+def_multiopenable_vec!(&String);
+def_multiopenable_type!(str);
+def_multiopenable!(String);
+def_multiopenable!(&Path);
+def_multiopenable_vec!(&PathBuf);
+def_multiopenable_type!(Path);
+def_multiopenable!(PathBuf);
+
+/// Open an Openable value with default handler
+pub fn open<T: Openable + ?Sized>(url: &T) -> Result<Option<PathBuf>> {
+    Ok(open_default(url)?)
+}
+
+fn open_default<T: Openable + ?Sized>(url: &T) -> OpenResult<Option<PathBuf>> {
+    if let Some(openable) = Openable::into_openable(url) {
+        traced!("open", { url = %openable.get_string() }, {
+            let opener = Opener::global();
+            let urls = CFArray::<CFURL>::from_CFTypes(&[openable]);
+            Ok(opener.launch_urls(opener.options(), Some(urls))?.into_path())
+        })
+    } else {
+        Err(OpenError::InvalidUrl)
+    }
+}
+
+#[inline]
+fn remap_app(app: Option<&Path>) -> OpenResult<Option<CFURL>> {
+    if let Some(app) = app {
+        match CFURL::from_path(app, true) {
+            None => Err(OpenError::InvalidApp),
+            res => Ok(res),
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+#[inline]
+fn remap_multiopenable<T: MultiOpenable + ?Sized>(
+    urls: Option<&T>,
+) -> OpenResult<Option<CFArray<CFURL>>> {
+    if let Some(urls) = urls {
+        match MultiOpenable::into_openable(urls) {
+            None => match urls.invalid_items() {
+                ref invalid if invalid.is_empty() => Err(OpenError::InvalidUrls),
+                invalid => Err(OpenError::InvalidItems(invalid)),
+            },
+            res => Ok(res),
+        }
+    } else {
+        Ok(None)
+    }
+}
+
+/// Open the app if no urls provided, open the urls in app if both provided and open urls in
+/// default handlers if no app is provided.
+pub fn open_complex<T: MultiOpenable + ?Sized>(
+    app: Option<&Path>,
+    urls: Option<&T>,
+    flags: LSLaunchFlags,
+) -> Result<Option<PathBuf>> {
+    Ok(launch(app, urls, flags, None)?)
+}
+
+/// `open_complex`, invoking the callback once the urls are converted and after each chunk of
+/// a large batch is delivered (see `OpenOptions::progress`)
+pub fn open_complex_with_progress<T, F>(
+    app: Option<&Path>,
+    urls: Option<&T>,
+    flags: LSLaunchFlags,
+    progress: F,
+) -> Result<Option<PathBuf>>
+where
+    T: MultiOpenable + ?Sized,
+    F: Fn(&OpenProgress) + Send + Sync + 'static,
+{
+    let progress = ProgressCallback(Arc::new(progress));
+    Ok(launch(app, urls, flags, Some(progress))?)
+}
+
+fn launch<T: MultiOpenable + ?Sized>(
+    app: Option<&Path>,
+    urls: Option<&T>,
+    flags: LSLaunchFlags,
+    progress: Option<ProgressCallback>,
+) -> OpenResult<Option<PathBuf>> {
+    let opener = Opener::global();
+    let mut options = opener.options().clone();
+    options.flags(flags);
+    if let Some(app) = app {
+        options.app(app);
+    }
+    if progress.is_some() {
+        options.progress = progress;
+    }
+
+    Ok(opener
+        .launch_urls(&options, remap_multiopenable(urls)?)?
+        .into_path())
+}
+
+/// Open the urls with the best installed app having the given bundle identifier (see
+/// `apps_for_bundle_id`).
+pub fn open_with_bundle_id<T: MultiOpenable + ?Sized>(
+    bundle_id: &str,
+    urls: &T,
+    flags: LSLaunchFlags,
+) -> OpenResult<Option<PathBuf>> {
+    match Opener::global().app_for_bundle_id(bundle_id) {
+        Some(app) => launch(Some(&app), Some(urls), flags, None),
+        None => Err(OpenError::AppNotFound(bundle_id.to_string())),
+    }
+}
+
+/// Open the urls with the first app matching the name in current locale that can open all of
+/// them (see `app_for_name_accepting_urls`).
+pub fn open_with_name<T: MultiOpenable + ?Sized>(
+    name: &str,
+    urls: &T,
+    flags: LSLaunchFlags,
+) -> OpenResult<Option<PathBuf>> {
+    match app_for_name_accepting_urls(name, urls) {
+        Some(app) => launch(Some(&app), Some(urls), flags, None),
+        None => match app_for_name(name) {
+            Some(app) => Err(OpenError::NotAccepted {
+                app: name.to_string(),
+                rejected: accept::rejected(&app, urls),
+            }),
+            None => Err(OpenError::AppNotFound(name.to_string())),
+        },
+    }
+}
+
+/// Open the urls with the given app, after checking it can handle all of them: instead of
+/// failing with a Launch Services status, urls the app can't open are reported with
+/// `OpenError::NotAccepted`.
+pub fn open_with<T: MultiOpenable + ?Sized>(app: &Path, urls: &T) -> OpenResult<Option<PathBuf>> {
+    let cfurls = remap_multiopenable(Some(urls))?;
+    remap_app(Some(app))?;
+    if !app_accept_urls(app, urls) {
+        return Err(OpenError::NotAccepted {
+            app: app.display().to_string(),
+            rejected: accept::rejected(app, urls),
+        });
+    }
+
+    let opener = Opener::global();
+    let mut options = opener.options().clone();
+    options.app(app);
+    Ok(opener.launch_urls(&options, cfurls)?.into_path())
+}
+
+/// Get all the app that can handle the given scheme
+pub fn apps_for_scheme(scheme: &str) -> Option<Vec<PathBuf>> {
+    try_apps_for_scheme(scheme)
+        .ok()
+        .filter(|apps| !apps.is_empty())
+}
+
+/// The apps which can open the url, `None` if there are none
+fn copy_apps_for_url(url: &CFURL, roles: LSRolesMask) -> OpenResult<Option<CFArray<CFURL>>> {
+    let mut error: CFErrorRef = std::ptr::null_mut();
+    let apps =
+        unsafe { LSCopyApplicationURLsForURL(url.as_concrete_TypeRef(), roles.bits(), &mut error) };
+    if !apps.is_null() {
+        return Ok(Some(unsafe { CFArray::wrap_under_create_rule(apps) }));
+    }
+    if error.is_null() {
+        return Ok(None);
+    }
+    match unsafe { CFError::wrap_under_create_rule(error) }.code() as OSStatus {
+        K_LS_APPLICATION_NOT_FOUND_ERR => Ok(None),
+        code => Err(OpenError::Status(code)),
+    }
+}
+
+/// Lazily converts the urls of an array of apps in paths
+struct AppPaths {
+    apps: Option<CFArray<CFURL>>,
+    index: CFIndex,
+}
+
+impl Iterator for AppPaths {
+    type Item = PathBuf;
+
+    fn next(&mut self) -> Option<PathBuf> {
+        let apps = self.apps.as_ref()?;
+        while self.index < apps.len() {
+            let path = apps.get(self.index).and_then(|app| app.to_path());
+            self.index += 1;
+            if path.is_some() {
+                return path;
+            }
+        }
+        None
+    }
+}
+
+/// Iterate over the apps that can handle the given scheme, paths are resolved on demand
+pub fn iter_apps_for_scheme(scheme: &str) -> impl Iterator<Item = PathBuf> {
+    let apps = Openable::into_openable(&format!("{}://", scheme))
+        .and_then(|scheme| copy_apps_for_url(&scheme, LSRolesMask::VIEWER).ok()?);
+    AppPaths { apps, index: 0 }
+}
+
+/// Get all the apps that can handle the given scheme, an empty list if there are none and
+/// Launch Services failures an error
+pub fn try_apps_for_scheme(scheme: &str) -> OpenResult<Vec<PathBuf>> {
+    traced!("apps_for_scheme", { scheme }, {
+        let scheme =
+            Openable::into_openable(&format!("{}://", scheme)).ok_or(OpenError::InvalidUrl)?;
+        let apps = copy_apps_for_url(&scheme, LSRolesMask::VIEWER)?;
+        Ok(AppPaths { apps, index: 0 }.collect())
+    })
+}
+
+/// Get the default app handler for defined scheme
+pub fn app_for_scheme(scheme: &str) -> Option<PathBuf> {
+    traced!("app_for_scheme", { scheme }, {
+        let scheme = Openable::into_openable(&format!("{}://", scheme))?;
+        match default_application_url_for_url(&scheme, LSRolesMask::VIEWER) {
+            Ok(url) => url.to_path(),
+            Err(_) => None,
+        }
+    })
+}
+
+/// Get all the app's paths matching the given bundle identifier, copies in the standard
+/// applications folders and newer versions first. Only one path per bundle identifier is
+/// returned.
+pub fn apps_for_bundle_id(bundle_id: &str) -> Option<Vec<PathBuf>> {
+    apps_for_bundle_id_unranked(bundle_id).map(rank_apps)
+}
+
+/// Get all the app's paths matching the given bundle identifier as reported by Launch Services,
+/// without ranking nor deduplication
+pub fn apps_for_bundle_id_unranked(bundle_id: &str) -> Option<Vec<PathBuf>> {
+    try_apps_for_bundle_id_unranked(bundle_id)
+        .ok()
+        .filter(|apps| !apps.is_empty())
+}
+
+/// Get all the app's paths matching the given bundle identifier, like `apps_for_bundle_id`.
+/// Unknown bundle identifiers give an empty list, Launch Services failures an error.
+pub fn try_apps_for_bundle_id(bundle_id: &str) -> OpenResult<Vec<PathBuf>> {
+    try_apps_for_bundle_id_unranked(bundle_id).map(rank_apps)
+}
+
+fn try_apps_for_bundle_id_unranked(bundle_id: &str) -> OpenResult<Vec<PathBuf>> {
+    traced!("apps_for_bundle_id", { bundle_id }, {
+        let bundle_id = CFString::new(bundle_id);
+        match application_urls_for_bundle_identifier(&bundle_id) {
+            Ok(apps) => Ok(apps.iter().filter_map(|v| v.to_path()).collect()),
+            Err(K_LS_APPLICATION_NOT_FOUND_ERR) => Ok(Vec::new()),
+            Err(code) => Err(OpenError::Status(code)),
+        }
+    })
+}
+
+/// Iterate over the app's paths matching the given bundle identifier, in the order of
+/// `apps_for_bundle_id`. Ranking needs all the paths, so the lookup itself isn't lazy: stop
+/// early to skip the work done on each path (e.g. acceptance checks)
+pub fn iter_apps_for_bundle_id(bundle_id: &str) -> impl Iterator<Item = PathBuf> {
+    apps_for_bundle_id(bundle_id)
+        .unwrap_or_default()
+        .into_iter()
+}
+
+/// Get first app's paths matching the given bundle identifier
+pub fn app_for_bundle_id(bundle_id: &str) -> Option<PathBuf> {
+    let mut apps = apps_for_bundle_id(bundle_id)?;
+    if apps.is_empty() {
+        None
+    } else {
+        Some(apps.remove(0))
+    }
+}
+
+/// Get all the app's paths matching the given bundle identifier, like `apps_for_bundle_id`,
+/// leaving out the copies older than `min_version` (and the ones without a version)
+pub fn apps_for_bundle_id_with_min_version(
+    bundle_id: &str,
+    min_version: Option<&Version>,
+) -> Option<Vec<PathBuf>> {
+    let apps = apps_for_bundle_id_unranked(bundle_id)?
+        .into_iter()
+        .filter(|app| is_at_least(app, min_version))
+        .collect::<Vec<_>>();
+    if apps.is_empty() {
+        None
+    } else {
+        Some(rank_apps(apps))
+    }
+}
+
+/// Get first app's paths matching the given bundle identifier which is at least `min_version`
+pub fn app_for_bundle_id_with_min_version(
+    bundle_id: &str,
+    min_version: Option<&Version>,
+) -> Option<PathBuf> {
+    apps_for_bundle_id_with_min_version(bundle_id, min_version)?
+        .into_iter()
+        .next()
+}
+
+const K_LS_APPLICATION_NOT_FOUND_ERR: OSStatus = -10814;
+
+const MQ_STRING_SPECIAL_CHARS: [char; 4] = ['?', '*', '\\', '"'];
+
+#[inline]
+fn mq_escaper() -> Escaper<&'static [char]> {
+    Escaper::new('\\', &MQ_STRING_SPECIAL_CHARS)
+}
+
+/// How an app name is compared against the display name of installed apps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameMatch {
+    /// The display name must be equal to the given name
+    Exact,
+    /// The display name must start with the given name
+    Prefix,
+    /// The display name must contain the given name
+    Contains,
+    /// The display name must contain all the characters of the given name, in order
+    Fuzzy,
+}
+
+impl Default for NameMatch {
+    fn default() -> NameMatch {
+        NameMatch::Exact
+    }
+}
+
+impl NameMatch {
+    fn pattern(self, name: &str) -> String {
+        let mut pattern = String::new();
+
+        match self {
+            NameMatch::Exact => {
+                fwrite!(&mut pattern, name.transformed(mq_escaper())).void_unwrap();
+            }
+            NameMatch::Prefix => {
+                fwrite!(&mut pattern, name.transformed(mq_escaper()), "*").void_unwrap();
+            }
+            NameMatch::Contains => {
+                fwrite!(&mut pattern, "*", name.transformed(mq_escaper()), "*").void_unwrap();
+            }
+            NameMatch::Fuzzy => {
+                let mut buf = [0u8; 4];
+                for c in name.chars().filter(|c| !c.is_whitespace()) {
+                    let c: &str = c.encode_utf8(&mut buf);
+                    fwrite!(&mut pattern, "*", c.transformed(mq_escaper())).void_unwrap();
+                }
+                pattern.push('*');
+            }
+        }
+
+        pattern
+    }
+
+    /// Compare an app name with the given name outside of Spotlight, ignoring the case
+    fn matches(self, display_name: &str, name: &str) -> bool {
+        let display_name = display_name.to_lowercase();
+        let name = name.to_lowercase();
+
+        match self {
+            NameMatch::Exact => display_name == name,
+            NameMatch::Prefix => display_name.starts_with(&name),
+            NameMatch::Contains => display_name.contains(&name),
+            NameMatch::Fuzzy => {
+                let mut chars = display_name.chars();
+                name.chars()
+                    .filter(|c| !c.is_whitespace())
+                    .all(|c| chars.any(|d| d == c))
+            }
+        }
+    }
+}
+
+/// Which localization of the app names is compared with the given name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameLocale<'a> {
+    /// The current locale, as indexed by Spotlight
+    Current,
+    /// Every localization the apps ship with
+    All,
+    /// The given localization (e.g. `de` or `pt_BR`), or the unlocalized name for the apps
+    /// which aren't localized in it
+    Locale(&'a str),
+}
+
+impl<'a> Default for NameLocale<'a> {
+    fn default() -> NameLocale<'a> {
+        NameLocale::Current
+    }
+}
+
+/// Score how well an app's display name matches the searched name, lower is better
+fn name_match_score(display_name: &str, name: &str) -> usize {
+    let display_name = display_name.to_lowercase();
+    let name = name.to_lowercase();
+
+    if display_name == name {
+        0
+    } else if display_name.starts_with(&name) {
+        1
+    } else if display_name
+        .split_whitespace()
+        .any(|word| word.starts_with(&name))
+    {
+        2
+    } else if display_name.contains(&name) {
+        3
+    } else {
+        4
+    }
+}
+
+fn display_name_of(path: &Path) -> String {
+    path.file_stem()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Get all the app's paths matching the given name in current locale
+pub fn apps_for_name(app_name: &str) -> Option<Vec<PathBuf>> {
+    apps_for_name_matching(app_name, NameMatch::Exact)
+}
+
+/// Get all the app's paths matching the given name in current locale with the given match mode.
+/// Results are ranked from the best to the worst match, then like `apps_for_bundle_id`.
+pub fn apps_for_name_matching(app_name: &str, mode: NameMatch) -> Option<Vec<PathBuf>> {
+    let apps = apps_for_name_matching_unranked(app_name, mode)?;
+    Some(rank_by_name(apps, app_name, mode))
+}
+
+fn rank_by_name(apps: Vec<PathBuf>, app_name: &str, mode: NameMatch) -> Vec<PathBuf> {
+    let mut res = rank_apps(apps);
+    if mode != NameMatch::Exact {
+        res.sort_by_cached_key(|path| {
+            let display_name = display_name_of(path);
+            (
+                name_match_score(&display_name, app_name),
+                display_name.len(),
+            )
+        });
+    }
+    res
+}
+
+/// Get all the app's paths matching the given name in current locale, like `apps_for_name`.
+/// No match gives an empty list, a failing Spotlight query an error.
+pub fn try_apps_for_name(app_name: &str) -> OpenResult<Vec<PathBuf>> {
+    try_apps_for_name_matching(app_name, NameMatch::Exact)
+}
+
+/// Get all the app's paths matching the given name in current locale with the given match
+/// mode, like `apps_for_name_matching`. No match gives an empty list, a failing Spotlight
+/// query an error.
+pub fn try_apps_for_name_matching(app_name: &str, mode: NameMatch) -> OpenResult<Vec<PathBuf>> {
+    let apps = try_apps_for_name_matching_unranked(app_name, mode)?;
+    Ok(rank_by_name(apps, app_name, mode))
+}
+
+/// Get all the app's paths matching the given name in current locale with the given match mode
+/// as reported by Spotlight, without ranking nor deduplication
+pub fn apps_for_name_matching_unranked(app_name: &str, mode: NameMatch) -> Option<Vec<PathBuf>> {
+    try_apps_for_name_matching_unranked(app_name, mode)
+        .ok()
+        .filter(|apps| !apps.is_empty())
+}
+
+fn try_apps_for_name_matching_unranked(
+    app_name: &str,
+    mode: NameMatch,
+) -> OpenResult<Vec<PathBuf>> {
+    let pattern = mode.pattern(app_name);
+    let mut query_string = String::new();
+    fwrite!(
+        &mut query_string,
+        "kMDItemContentTypeTree == \"com.apple.application\"c && kMDItemDisplayName == \"",
+        pattern.as_str(),
+        "\"cd"
+    )
+    .void_unwrap();
+    spotlight_paths(&query_string)
+}
+
+/// Run the Spotlight query synchronously and collect the paths of the results
+pub(crate) fn spotlight_paths(query_string: &str) -> OpenResult<Vec<PathBuf>> {
+    traced!("spotlight", { query = query_string }, {
+        Ok(run_query(query_string)?
+            .iter()
+            .filter_map(|v| {
+                v.get(attributes::Path)
+                    .map(|a| PathBuf::from(a.to_string()))
+            })
+            .collect())
+    })
+}
+
+/// Get all the app's paths matching the given name in the given localization with the given
+/// match mode, ranked like `apps_for_name_matching`. Looking outside the current locale reads
+/// the localized names out of every installed app, so it's much slower.
+pub fn apps_for_name_in(
+    app_name: &str,
+    mode: NameMatch,
+    locale: NameLocale,
+) -> OpenResult<Vec<PathBuf>> {
+    let localization = match locale {
+        NameLocale::Current => return try_apps_for_name_matching(app_name, mode),
+        NameLocale::All => None,
+        NameLocale::Locale(localization) => Some(localization),
+    };
+
+    let mut scores = HashMap::new();
+    for path in spotlight_paths("kMDItemContentTypeTree == \"com.apple.application\"c")? {
+        let score = bundle::localized_names(&path, localization)
+            .into_iter()
+            .filter(|name| mode.matches(name, app_name))
+            .map(|name| (name_match_score(&name, app_name), name.len()))
+            .min();
+        if let Some(score) = score {
+            scores.insert(path, score);
+        }
+    }
+
+    let mut res = rank_apps(scores.keys().cloned().collect());
+    res.sort_by_key(|path| scores[path]);
+    Ok(res)
+}
+
+/// Iterate over the app's paths matching the given name in current locale, in the order of
+/// `apps_for_name`. Ranking needs all the paths, so the Spotlight query itself isn't lazy: stop
+/// early to skip the work done on each path (e.g. acceptance checks)
+pub fn iter_apps_for_name(app_name: &str) -> impl Iterator<Item = PathBuf> {
+    apps_for_name(app_name).unwrap_or_default().into_iter()
+}
+
+/// Get first app's paths matching the given name in current locale
+pub fn app_for_name(name: &str) -> Option<PathBuf> {
+    app_for_name_matching(name, NameMatch::Exact)
+}
+
+/// Get the best app's path matching the given name in current locale with the given match mode
+pub fn app_for_name_matching(name: &str, mode: NameMatch) -> Option<PathBuf> {
+    let mut apps = apps_for_name_matching(name, mode)?;
+    if apps.is_empty() {
+        None
+    } else {
+        Some(apps.remove(0))
+    }
+}
+
+/// Check if the app can handle the given url
+pub fn app_accept_url<T: Openable + ?Sized>(app: &Path, url: &T) -> bool {
+    if let Some(app) = CFURL::from_path(app, true) {
+        match Openable::into_openable(url) {
+            None => return false,
+            Some(url) => match can_url_accept_url(
+                &url,
+                &app,
+                LSRolesMask::VIEWER,
+                LSAcceptanceFlags::DEFAULT,
+            ) {
+                Err(_) => false,
+                Ok(res) => res,
+            },
+        }
+    } else {
+        false
+    }
+}
+
+/// Check if the app can handle all the given urls. Large batches are checked in parallel and
+/// the check stops at the first url the app can't handle.
+pub fn app_accept_urls<T: MultiOpenable + ?Sized>(app: &Path, urls: &T) -> bool {
+    accept::all_accepted(app, urls)
+}
+
+/// Get all the apps matching the name in current locale that can open the given urls
+pub fn apps_for_name_accepting_urls<T: MultiOpenable + ?Sized>(
+    name: &str,
+    urls: &T,
+) -> Option<Vec<PathBuf>> {
+    let res: Vec<PathBuf> = apps_for_name(name)?
+        .into_iter()
+        .filter(|v| app_accept_urls(v, urls))
+        .collect();
+
+    if res.is_empty() {
+        None
+    } else {
+        Some(res)
+    }
+}
+
+/// Get the first app matching the name in current locale that can open the given urls
+pub fn app_for_name_accepting_urls<T: MultiOpenable + ?Sized>(
+    name: &str,
+    urls: &T,
+) -> Option<PathBuf> {
+    iter_apps_for_name(name).find(|app| app_accept_urls(app, urls))
+}
+
+/// Get all the apps matching the bundle identifier that can open the given urls
+pub fn apps_for_bundle_id_accepting_urls<T: MultiOpenable + ?Sized>(
+    bundle_id: &str,
+    urls: &T,
+) -> Option<Vec<PathBuf>> {
+    let res: Vec<PathBuf> = apps_for_bundle_id(bundle_id)?
+        .into_iter()
+        .filter(|v| app_accept_urls(v, urls))
+        .collect();
+
+    if res.is_empty() {
+        None
+    } else {
+        Some(res)
+    }
+}
+
+/// Get the first app matching the bundle identifier that can open the given urls
+pub fn app_for_bundle_id_accepting_urls<T: MultiOpenable + ?Sized>(
+    bundle_id: &str,
+    urls: &T,
+) -> Option<PathBuf> {
+    iter_apps_for_bundle_id(bundle_id).find(|app| app_accept_urls(app, urls))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iter_apps_for_scheme() {
+        let first = iter_apps_for_scheme("https").next();
+        assert!(first.is_some());
+        assert_eq!(
+            iter_apps_for_scheme("surely-nobody-handles-this").next(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_try_apps_for_scheme() {
+        let apps = try_apps_for_scheme("https").unwrap();
+        assert!(!apps.is_empty());
+        assert_eq!(apps_for_scheme("https"), Some(apps));
+        assert!(try_apps_for_scheme("surely-nobody-handles-this")
+            .unwrap()
+            .is_empty());
+        assert_eq!(apps_for_scheme("surely-nobody-handles-this"), None);
+    }
+
+    #[test]
+    fn test_try_apps_for_bundle_id() {
+        assert!(!try_apps_for_bundle_id("com.apple.Safari")
+            .unwrap()
+            .is_empty());
+        assert!(try_apps_for_bundle_id("com.example.surely.not.installed")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_try_apps_for_name() {
+        let apps = try_apps_for_name("Safari").unwrap();
+        assert_eq!(
+            apps.first().map(PathBuf::as_path),
+            Some(Path::new("/Applications/Safari.app"))
+        );
+        assert!(try_apps_for_name("Surely Not An Installed App")
+            .unwrap()
+            .is_empty());
+        assert!(!try_apps_for_name_matching("Safar", NameMatch::Prefix)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_open_default() {
+        assert!(open("https://www.google.com/").is_ok());
+    }
+
+    #[test]
+    fn test_open_default_non_ascii() {
+        assert!(open("http://github.com?dummy_query1=0&dummy_query2=ｎｏｎａｓｃｉｉ").is_ok());
+    }
+
+    #[test]
+    fn test_open_complex_safari() {
+        assert!(open_complex(
+            Some(Path::new("/Applications/Safari.app")),
+            Some(&["https://news.ycombinator.com/", "https://www.google.com/"][..]),
+            LSLaunchFlags::DEFAULTS,
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_open_with_bundle_id() {
+        assert!(open_with_bundle_id(
+            "com.apple.safari",
+            &["https://www.google.com/"][..],
+            LSLaunchFlags::DEFAULTS,
+        )
+        .is_ok());
+        match open_with_bundle_id(
+            "com.example.not-installed",
+            "https://www.google.com/",
+            LSLaunchFlags::DEFAULTS,
+        ) {
+            Err(OpenError::AppNotFound(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_open_with_name() {
+        assert!(
+            open_with_name("Safari", "https://www.google.com/", LSLaunchFlags::DEFAULTS).is_ok()
+        );
+        match open_with_name(
+            "Calculator",
+            "https://www.google.com/",
+            LSLaunchFlags::DEFAULTS,
+        ) {
+            Err(OpenError::NotAccepted { .. }) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_open_with() {
+        let urls = ["https://www.google.com/"];
+        match open_with(Path::new("/System/Applications/Calculator.app"), &urls[..]) {
+            Err(OpenError::NotAccepted { rejected, .. }) => {
+                assert_eq!(rejected, vec![Url::parse(urls[0]).unwrap()])
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_get_safari_by_bundle_id() {
+        assert!(apps_for_bundle_id("com.apple.safari").is_some());
+        assert!(app_for_bundle_id("com.apple.safari").is_some());
+    }
+
+    #[test]
+    fn test_get_chrome_by_partial_name() {
+        assert!(app_for_name_matching("chrome", NameMatch::Contains).is_some());
+        assert!(app_for_name_matching("Goo", NameMatch::Prefix).is_some());
+        assert!(app_for_name_matching("gchrm", NameMatch::Fuzzy).is_some());
+    }
+
+    #[test]
+    fn test_name_match_score() {
+        assert_eq!(name_match_score("Safari", "safari"), 0);
+        assert_eq!(name_match_score("Google Chrome", "goo"), 1);
+        assert_eq!(name_match_score("Google Chrome", "chrome"), 2);
+        assert_eq!(name_match_score("Google Chrome", "hrom"), 3);
+        assert_eq!(name_match_score("Google Chrome", "gchrm"), 4);
+    }
+
+    #[test]
+    fn test_remap_multiopenable_invalid_items() {
+        let urls = vec!["https://www.example.com/", "http://exa mple.com/", "/tmp"];
+        match remap_multiopenable(Some(&urls)) {
+            Err(OpenError::InvalidItems(invalid)) => {
+                assert_eq!(invalid, vec![(1, "\"http://exa mple.com/\"".to_string())])
+            }
+            Err(other) => panic!("unexpected error {:?}", other),
+            Ok(_) => panic!("invalid urls accepted"),
+        }
+    }
+
+    #[test]
+    fn test_name_match_matches() {
+        assert!(NameMatch::Exact.matches("Vorschau", "vorschau"));
+        assert!(NameMatch::Prefix.matches("Google Chrome", "goo"));
+        assert!(NameMatch::Contains.matches("Google Chrome", "hrom"));
+        assert!(NameMatch::Fuzzy.matches("Google Chrome", "g chrm"));
+        assert!(!NameMatch::Fuzzy.matches("Google Chrome", "mrhc"));
+    }
+
+    #[test]
+    fn test_apps_for_name_in_all_locales() {
+        let apps = apps_for_name_in("Vorschau", NameMatch::Exact, NameLocale::All).unwrap();
+        assert!(apps.contains(&PathBuf::from("/System/Applications/Preview.app")));
+    }
+
+    #[test]
+    fn test_get_safari_by_name_accepting_google_url() {
+        assert!(app_for_name_accepting_urls("Safari", &["http://www.google.com/"][..]).is_some());
+    }
+
+    #[test]
+    fn test_get_safari_by_bundle_id_accepting_google_url() {
+        assert!(app_for_bundle_id_accepting_urls(
+            "com.google.chrome",
+            &["http://www.google.com/"][..]
+        )
+        .is_some());
+    }
+}
@@ -0,0 +1,66 @@
+//! Async variants of the blocking functions, they run on the Tokio blocking thread pool.
+
+use crate::macos::{
+    apps_for_bundle_id, apps_for_name_matching, open_and_wait, open_default, MultiOpenable,
+    NameMatch, OpenOptions, OpenResult, Openable,
+};
+
+use std::path::PathBuf;
+
+async fn blocking<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(res) => res,
+        Err(err) => std::panic::resume_unwind(err.into_panic()),
+    }
+}
+
+/// Async version of `open`
+pub async fn open_async<T: Openable + Send + 'static>(url: T) -> OpenResult<Option<PathBuf>> {
+    blocking(move || open_default(&url)).await
+}
+
+/// Async version of `open_and_wait`, the future resolves when the handler exits
+pub async fn open_and_wait_async<T: Openable + Send + 'static>(url: T) -> OpenResult<()> {
+    blocking(move || open_and_wait(&url)).await
+}
+
+/// Async version of `OpenOptions::open_and_wait`. Dropping the future doesn't stop the
+/// blocking wait, give the options a timeout or a `CancelToken` to bound it.
+pub async fn open_and_wait_with_async<T>(urls: T, options: OpenOptions) -> OpenResult<()>
+where
+    T: MultiOpenable + Send + 'static,
+{
+    blocking(move || options.open_and_wait(&urls)).await
+}
+
+/// Async version of `apps_for_name_matching`
+pub async fn apps_for_name_async(name: String, mode: NameMatch) -> Option<Vec<PathBuf>> {
+    blocking(move || apps_for_name_matching(&name, mode)).await
+}
+
+/// Async version of `apps_for_bundle_id`
+pub async fn apps_for_bundle_id_async(bundle_id: String) -> Option<Vec<PathBuf>> {
+    blocking(move || apps_for_bundle_id(&bundle_id)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_async() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            assert!(open_async("https://www.google.com/").await.is_ok());
+            assert!(apps_for_name_async("Safari".to_string(), NameMatch::Exact)
+                .await
+                .is_some());
+        });
+    }
+}
@@ -0,0 +1,73 @@
+//! Minimal helpers to talk with Foundation and AppKit objects.
+
+use core_foundation::base::TCFType;
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation::url::{CFURLRef, CFURL};
+use objc::runtime::Object;
+
+use std::path::PathBuf;
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {}
+
+/// An Objective-C object pointer
+pub(crate) type Id = *mut Object;
+
+/// Drains every object autoreleased while alive
+pub(crate) struct AutoreleasePool(Id);
+
+impl AutoreleasePool {
+    pub(crate) fn new() -> AutoreleasePool {
+        unsafe {
+            let pool: Id = msg_send![class!(NSAutoreleasePool), alloc];
+            AutoreleasePool(msg_send![pool, init])
+        }
+    }
+}
+
+impl Drop for AutoreleasePool {
+    fn drop(&mut self) {
+        unsafe {
+            let _: () = msg_send![self.0, drain];
+        }
+    }
+}
+
+/// The shared NSWorkspace
+pub(crate) fn shared_workspace() -> Id {
+    unsafe { msg_send![class!(NSWorkspace), sharedWorkspace] }
+}
+
+/// Borrow a CFString as an NSString (they are toll-free bridged)
+pub(crate) fn nsstring(string: &CFString) -> Id {
+    string.as_concrete_TypeRef() as Id
+}
+
+/// Copy an NSString, nil gives None
+pub(crate) fn to_string(string: Id) -> Option<String> {
+    if string.is_null() {
+        None
+    } else {
+        Some(unsafe { CFString::wrap_under_get_rule(string as CFStringRef) }.to_string())
+    }
+}
+
+/// Get the file system path of an NSURL, nil gives None
+pub(crate) fn to_path(url: Id) -> Option<PathBuf> {
+    if url.is_null() {
+        None
+    } else {
+        unsafe { CFURL::wrap_under_get_rule(url as CFURLRef) }.to_path()
+    }
+}
+
+/// Iterate over an NSArray
+pub(crate) fn array_iter(array: Id) -> impl Iterator<Item = Id> {
+    let count: usize = if array.is_null() {
+        0
+    } else {
+        unsafe { msg_send![array, count] }
+    };
+
+    (0..count).map(move |i| unsafe { msg_send![array, objectAtIndex: i] })
+}
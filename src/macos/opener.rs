@@ -0,0 +1,352 @@
+//! A reusable handle sharing the options and the lookup cache between calls and threads.
+
+use crate::macos::{
+    app_for_bundle_id, app_for_scheme, apps_for_name, apps_for_scheme, remap_multiopenable,
+    LaunchedApp, LookupCache, MultiOpenable, OpenError, OpenOptions, OpenPolicy, OpenResult,
+    OpenTarget, ResolvedTarget,
+};
+
+use core_foundation::array::CFArray;
+use core_foundation::url::CFURL;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+static GLOBAL: OnceLock<Opener> = OnceLock::new();
+
+/// The opens seen within the dedupe window, by app and urls
+#[derive(Debug, Default)]
+struct Dedupe {
+    window: Option<Duration>,
+    recent: HashMap<String, Instant>,
+}
+
+/// A resolver registered with `Opener::add_resolver`
+type Resolver = Arc<dyn Fn(&str) -> Option<ResolvedTarget> + Send + Sync>;
+
+/// The resolvers, in the order they are tried
+#[derive(Default)]
+struct Resolvers(Vec<Resolver>);
+
+impl fmt::Debug for Resolvers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Resolvers({})", self.0.len())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    options: OpenOptions,
+    cache: Option<LookupCache>,
+    dedupe: Mutex<Dedupe>,
+    resolvers: Mutex<Resolvers>,
+}
+
+/// Options and an optional `LookupCache` built once and shared by every call. It's `Send` and
+/// `Sync` and clones share the same state, so a daemon can hand it to all its threads. Cocoa
+/// objects (e.g. the NSWorkspace) aren't thread safe and are still fetched on each call.
+///
+/// The `open*` free functions (`open`, `open_complex`, `open_with`, `open_with_bundle_id`,
+/// `open_with_name`) go through `Opener::global()`: they start from its options and policy and
+/// honour its dedupe window. The other launching functions (`open_and_wait`,
+/// `open_in_browser`, `open_at_with`, ...) start from its options too, and `Workspace::reopen`
+/// applies its policy. The lookup free functions (`app_for_scheme`, ...) don't use its cache,
+/// call them on the opener instead.
+///
+/// ```no_run
+/// use macos_open::{LookupCache, OpenOptions, Opener};
+///
+/// let opener = Opener::new(OpenOptions::new(), Some(LookupCache::new()));
+/// opener.open_with_bundle_id("com.apple.Safari", "https://www.example.com/").unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Opener {
+    inner: Arc<Inner>,
+}
+
+impl Opener {
+    /// Create an opener launching with the given options, caching the lookups if a cache is
+    /// given
+    pub fn new(options: OpenOptions, cache: Option<LookupCache>) -> Opener {
+        Opener {
+            inner: Arc::new(Inner {
+                options,
+                cache,
+                dedupe: Mutex::default(),
+                resolvers: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Create an opener refusing the urls denied by the policy with `OpenError::Denied`
+    /// before launching anything, like `OpenOptions::policy`
+    pub fn with_policy(
+        mut options: OpenOptions,
+        cache: Option<LookupCache>,
+        policy: OpenPolicy,
+    ) -> Opener {
+        options.policy(policy);
+        Opener::new(options, cache)
+    }
+
+    /// The opener used by the free functions, with default options and no cache unless set
+    /// with `set_global`
+    pub fn global() -> &'static Opener {
+        GLOBAL.get_or_init(Opener::default)
+    }
+
+    /// Replace the default opener, only possible before it's first used. The opener is handed
+    /// back if it's too late.
+    pub fn set_global(opener: Opener) -> Result<(), Opener> {
+        GLOBAL.set(opener)
+    }
+
+    /// The options every launch starts from
+    pub fn options(&self) -> &OpenOptions {
+        &self.inner.options
+    }
+
+    /// The cache of the lookups, if any
+    pub fn cache(&self) -> Option<&LookupCache> {
+        self.inner.cache.as_ref()
+    }
+
+    /// The policy vetting the urls, if any
+    pub fn policy(&self) -> Option<&OpenPolicy> {
+        self.inner.options.policy.as_ref()
+    }
+
+    fn dedupe(&self) -> MutexGuard<'_, Dedupe> {
+        // a panicking open can't leave the map half updated
+        self.inner
+            .dedupe
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn resolvers(&self) -> MutexGuard<'_, Resolvers> {
+        self.inner
+            .resolvers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Suppress the opens of the same urls with the same app fired within the window after
+    /// the first one, failing with `OpenError::AlreadyOpened` (e.g. against double clicks and
+    /// retry storms). Failed opens aren't remembered. It's shared by all the clones, `None`
+    /// (the default) disables it.
+    pub fn set_dedupe_window(&self, window: Option<Duration>) {
+        let mut dedupe = self.dedupe();
+        dedupe.window = window;
+        dedupe.recent.clear();
+    }
+
+    /// The window within which identical opens are suppressed, if any
+    pub fn dedupe_window(&self) -> Option<Duration> {
+        self.dedupe().window
+    }
+
+    /// Launch with the options unless the same urls were opened with the same app within the
+    /// dedupe window, the entry point of every open going through the opener
+    pub(crate) fn launch_urls(
+        &self,
+        options: &OpenOptions,
+        urls: Option<CFArray<CFURL>>,
+    ) -> OpenResult<LaunchedApp> {
+        let listed: Vec<String> = match (self.dedupe_window(), urls.as_ref()) {
+            (Some(_), Some(urls)) => urls
+                .iter()
+                .map(|url| url.get_string().to_string())
+                .collect(),
+            _ => return options.launch_urls(urls),
+        };
+        let app = options
+            .app
+            .as_ref()
+            .map(|app| app.display().to_string())
+            .unwrap_or_default();
+        let key = format!("{}\n{}", app, listed.join("\n"));
+
+        {
+            let mut dedupe = self.dedupe();
+            let window = match dedupe.window {
+                Some(window) => window,
+                None => {
+                    drop(dedupe);
+                    return options.launch_urls(urls);
+                }
+            };
+            dedupe.recent.retain(|_, at| at.elapsed() < window);
+            if dedupe.recent.contains_key(&key) {
+                return Err(OpenError::AlreadyOpened(listed.join(", ")));
+            }
+            dedupe.recent.insert(key.clone(), Instant::now());
+        }
+
+        let res = options.launch_urls(urls);
+        if res.is_err() {
+            self.dedupe().recent.remove(&key);
+        }
+        res
+    }
+
+    /// Register a resolver mapping some inputs of `open_input` to what they open, before the
+    /// built-in url and path guess. Resolvers are tried in the order they are added, the first
+    /// one returning a target wins. They are shared by all the clones.
+    ///
+    /// ```no_run
+    /// use macos_open::{Opener, ResolvedTarget};
+    ///
+    /// let opener = Opener::default();
+    /// opener.add_resolver(|input| {
+    ///     let issue = input.strip_prefix("jira:")?;
+    ///     Some(ResolvedTarget::Url(format!(
+    ///         "https://jira.example.com/browse/{}",
+    ///         issue
+    ///     )))
+    /// });
+    /// opener.open_input("jira:ABC-123").unwrap();
+    /// ```
+    pub fn add_resolver<F>(&self, resolver: F) -> &Opener
+    where
+        F: Fn(&str) -> Option<ResolvedTarget> + Send + Sync + 'static,
+    {
+        self.resolvers().0.push(Arc::new(resolver));
+        self
+    }
+
+    /// What the registered resolvers map the input to, `None` if none of them handles it
+    pub fn resolve(&self, input: &str) -> Option<ResolvedTarget> {
+        // resolvers may use the opener, don't keep it locked while they run
+        let resolvers = self.resolvers().0.clone();
+        resolvers.iter().find_map(|resolver| resolver(input))
+    }
+
+    /// Open what the resolvers map the input to, or the input itself guessing between a url
+    /// and a path like the `Openable` implementation of strings
+    pub fn open_input(&self, input: &str) -> OpenResult<LaunchedApp> {
+        match self.resolve(input) {
+            Some(target) => self.open(&target.as_target()),
+            None => self.open(&OpenTarget::Auto(input)),
+        }
+    }
+
+    /// Open the urls with the options of the opener
+    pub fn open<T: MultiOpenable + ?Sized>(&self, urls: &T) -> OpenResult<LaunchedApp> {
+        self.launch_urls(&self.inner.options, remap_multiopenable(Some(urls))?)
+    }
+
+    /// Open the urls with the best installed app having the given bundle identifier
+    pub fn open_with_bundle_id<T: MultiOpenable + ?Sized>(
+        &self,
+        bundle_id: &str,
+        urls: &T,
+    ) -> OpenResult<LaunchedApp> {
+        match self.app_for_bundle_id(bundle_id) {
+            Some(app) => self.launch_urls(
+                self.inner.options.clone().app(app),
+                remap_multiopenable(Some(urls))?,
+            ),
+            None => Err(OpenError::AppNotFound(bundle_id.to_string())),
+        }
+    }
+
+    /// `app_for_scheme`, cached if the opener has a cache
+    pub fn app_for_scheme(&self, scheme: &str) -> Option<PathBuf> {
+        match self.cache() {
+            Some(cache) => cache.app_for_scheme(scheme),
+            None => app_for_scheme(scheme),
+        }
+    }
+
+    /// `apps_for_scheme`, cached if the opener has a cache
+    pub fn apps_for_scheme(&self, scheme: &str) -> Option<Vec<PathBuf>> {
+        match self.cache() {
+            Some(cache) => cache.apps_for_scheme(scheme),
+            None => apps_for_scheme(scheme),
+        }
+    }
+
+    /// `app_for_bundle_id`, cached if the opener has a cache
+    pub fn app_for_bundle_id(&self, bundle_id: &str) -> Option<PathBuf> {
+        match self.cache() {
+            Some(cache) => cache.app_for_bundle_id(bundle_id),
+            None => app_for_bundle_id(bundle_id),
+        }
+    }
+
+    /// `apps_for_name`, cached if the opener has a cache
+    pub fn apps_for_name(&self, name: &str) -> Option<Vec<PathBuf>> {
+        match self.cache() {
+            Some(cache) => cache.apps_for_name(name),
+            None => apps_for_name(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opener_shared() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Opener>();
+
+        let opener = Opener::new(OpenOptions::new(), Some(LookupCache::new()));
+        let clone = opener.clone();
+        assert!(Arc::ptr_eq(&opener.inner, &clone.inner));
+        assert!(clone.cache().is_some());
+        assert!(Opener::global().cache().is_none());
+    }
+
+    #[test]
+    fn test_opener_dedupe_window() {
+        let opener = Opener::new(OpenOptions::new(), None);
+        opener.set_dedupe_window(Some(Duration::from_secs(60)));
+        assert_eq!(opener.dedupe_window(), Some(Duration::from_secs(60)));
+        opener.open("https://www.example.com/").unwrap();
+        match opener.clone().open("https://www.example.com/") {
+            Err(OpenError::AlreadyOpened(url)) => assert_eq!(url, "https://www.example.com/"),
+            other => panic!("unexpected result {:?}", other),
+        }
+        opener
+            .open_with_bundle_id("com.apple.Safari", "https://www.example.com/")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_opener_policy() {
+        let opener = Opener::with_policy(OpenOptions::new(), None, OpenPolicy::untrusted());
+        assert!(opener.policy().is_some());
+        match opener.open("javascript:alert(1)") {
+            Err(OpenError::Denied(url)) => assert_eq!(url, "javascript:alert(1)"),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_opener_resolvers() {
+        let opener = Opener::new(OpenOptions::new(), None);
+        opener
+            .add_resolver(|input| {
+                let repo = input.strip_prefix("gh:")?;
+                Some(ResolvedTarget::Url(format!("https://github.com/{}", repo)))
+            })
+            .add_resolver(|_| Some(ResolvedTarget::Path(PathBuf::from("/etc/hosts"))));
+        assert_eq!(
+            opener.clone().resolve("gh:owner/repo"),
+            Some(ResolvedTarget::Url(
+                "https://github.com/owner/repo".to_string()
+            ))
+        );
+        assert_eq!(
+            opener.resolve("anything"),
+            Some(ResolvedTarget::Path(PathBuf::from("/etc/hosts")))
+        );
+        assert_eq!(Opener::default().resolve("gh:owner/repo"), None);
+    }
+}
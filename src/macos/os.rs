@@ -0,0 +1,47 @@
+//! Informations about the running operating system.
+
+use crate::macos::version::Version;
+
+use std::os::raw::{c_char, c_int, c_void};
+
+extern "C" {
+    fn sysctlbyname(
+        name: *const c_char,
+        oldp: *mut c_void,
+        oldlenp: *mut usize,
+        newp: *mut c_void,
+        newlen: usize,
+    ) -> c_int;
+}
+
+/// The version of the running macOS, as reported by the `kern.osproductversion` sysctl
+pub(crate) fn macos_version() -> Option<Version> {
+    let mut buf = [0u8; 32];
+    let mut len = buf.len();
+
+    let res = unsafe {
+        sysctlbyname(
+            b"kern.osproductversion\0".as_ptr() as *const c_char,
+            buf.as_mut_ptr() as *mut c_void,
+            &mut len,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if res != 0 || len == 0 {
+        return None;
+    }
+
+    let version = std::str::from_utf8(&buf[..len - 1]).ok()?;
+    Version::parse(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_macos_version() {
+        assert!(macos_version().unwrap().major() >= 10);
+    }
+}
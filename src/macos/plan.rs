@@ -0,0 +1,122 @@
+//! Resolve the handler of every url before opening, so urls sharing the same app are delivered
+//! with a single launch.
+
+use crate::macos::bundle::bundle_identifier;
+use crate::macos::{
+    remap_multiopenable, LaunchedApp, MultiOpenable, OpenError, OpenOptions, OpenResult,
+};
+
+use core_foundation::array::CFArray;
+use core_foundation::url::CFURL;
+use launch_services::{default_application_url_for_url, LSRolesMask};
+
+use std::path::{Path, PathBuf};
+
+/// The app which would open an url
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HandlerInfo {
+    path: PathBuf,
+    bundle_id: Option<String>,
+}
+
+impl HandlerInfo {
+    pub(crate) fn from_path(path: PathBuf) -> HandlerInfo {
+        HandlerInfo {
+            bundle_id: bundle_identifier(&path),
+            path,
+        }
+    }
+
+    /// The path of the app
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The bundle identifier of the app, if it has one
+    pub fn bundle_id(&self) -> Option<&str> {
+        self.bundle_id.as_ref().map(String::as_str)
+    }
+}
+
+/// Urls paired with the app that would open them, urls with the same app are adjacent
+pub type OpenPlan = Vec<(CFURL, Option<HandlerInfo>)>;
+
+fn handler_for(url: &CFURL) -> Option<HandlerInfo> {
+    default_application_url_for_url(url, LSRolesMask::all())
+        .ok()?
+        .to_path()
+        .map(HandlerInfo::from_path)
+}
+
+/// Compute which app each url would be opened with. Urls are grouped by handler in the order
+/// each handler is first needed.
+pub fn plan_open<T: MultiOpenable + ?Sized>(urls: &T) -> OpenResult<OpenPlan> {
+    let urls = remap_multiopenable(Some(urls))?.ok_or(OpenError::InvalidUrls)?;
+
+    let mut groups: Vec<(Option<HandlerInfo>, Vec<CFURL>)> = Vec::new();
+    for url in urls.iter() {
+        let handler = handler_for(&url);
+        match groups.iter_mut().find(|group| group.0 == handler) {
+            Some(group) => group.1.push(url.clone()),
+            None => groups.push((handler, vec![url.clone()])),
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .flat_map(|(handler, urls)| urls.into_iter().map(move |url| (url, handler.clone())))
+        .collect())
+}
+
+/// Execute a plan with one launch per app, every app receiving all of its urls at once.
+/// Configured app in options is overridden by the handler of each group. Urls without a
+/// handler are reported as `OpenError::AppNotFound`.
+pub fn execute_plan(
+    plan: &[(CFURL, Option<HandlerInfo>)],
+    options: &OpenOptions,
+) -> Vec<(Option<HandlerInfo>, OpenResult<LaunchedApp>)> {
+    let mut res = Vec::new();
+    let mut rest = plan;
+
+    while let Some((_, handler)) = rest.first() {
+        let len = rest
+            .iter()
+            .take_while(|(_, other)| other == handler)
+            .count();
+        let (group, tail) = rest.split_at(len);
+        rest = tail;
+
+        let urls = group.iter().map(|(url, _)| url.clone()).collect::<Vec<_>>();
+        let result = match handler {
+            Some(handler) => options
+                .clone()
+                .app(handler.path())
+                .launch_urls(Some(CFArray::<CFURL>::from_CFTypes(&urls[..]))),
+            None => Err(OpenError::AppNotFound(urls[0].get_string().to_string())),
+        };
+        res.push((handler.clone(), result));
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_groups_by_handler() {
+        let plan = plan_open(
+            &[
+                "https://www.google.com/",
+                "mailto:someone@example.com",
+                "https://www.example.com/",
+            ][..],
+        )
+        .unwrap();
+        assert_eq!(plan.len(), 3);
+        assert_eq!(plan[0].1, plan[1].1);
+        assert_ne!(plan[1].1, plan[2].1);
+    }
+}
@@ -0,0 +1,154 @@
+//! Vet the urls before they are opened, for apps opening links found in untrusted content.
+
+use crate::macos::{OpenError, OpenResult};
+
+use core_foundation::array::CFArray;
+use core_foundation::url::CFURL;
+use url::Url;
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Schemes running code, connecting to other machines or reading local files, denied by
+/// `OpenPolicy::untrusted`
+const DANGEROUS_SCHEMES: &[&str] = &[
+    "afp",
+    "applescript",
+    "data",
+    "file",
+    "ftp",
+    "javascript",
+    "smb",
+    "ssh",
+    "telnet",
+    "vbscript",
+    "vnc",
+    "x-apple-helpviewer",
+];
+
+/// Which urls `OpenOptions` (and the `Opener` built with them) are allowed to open. A url is
+/// denied if its scheme is denied, if there is an allow-list and its scheme isn't in it, or if
+/// the callback refuses it. Schemes are case insensitive.
+///
+/// ```no_run
+/// use macos_open::{OpenOptions, OpenPolicy, Opener};
+///
+/// let mut policy = OpenPolicy::untrusted();
+/// policy.check(|url| url.host_str() != Some("evil.example.com"));
+/// let opener = Opener::with_policy(OpenOptions::new(), None, policy);
+/// assert!(opener.open("javascript:alert(1)").is_err());
+/// ```
+#[derive(Clone, Default)]
+pub struct OpenPolicy {
+    allowed: Option<Vec<String>>,
+    denied: Vec<String>,
+    check: Option<Arc<dyn Fn(&Url) -> bool + Send + Sync>>,
+}
+
+impl OpenPolicy {
+    /// Create a policy allowing everything
+    pub fn new() -> OpenPolicy {
+        OpenPolicy::default()
+    }
+
+    /// Create a policy for urls from untrusted input: only `http`, `https` and `mailto` are
+    /// allowed and the schemes running code or reaching local files (`file`, `javascript`,
+    /// `vnc`, ...) are denied even if allowed later
+    pub fn untrusted() -> OpenPolicy {
+        let mut policy = OpenPolicy::new();
+        for scheme in &["http", "https", "mailto"] {
+            policy.allow(scheme);
+        }
+        for scheme in DANGEROUS_SCHEMES {
+            policy.deny(scheme);
+        }
+        policy
+    }
+
+    /// Add the scheme to the allow-list, once there is one the other schemes are denied
+    pub fn allow(&mut self, scheme: &str) -> &mut OpenPolicy {
+        self.allowed
+            .get_or_insert_with(Vec::new)
+            .push(scheme.to_ascii_lowercase());
+        self
+    }
+
+    /// Deny the scheme, whether it's allowed or not
+    pub fn deny(&mut self, scheme: &str) -> &mut OpenPolicy {
+        self.denied.push(scheme.to_ascii_lowercase());
+        self
+    }
+
+    /// Vet the urls passing the scheme checks with a callback, returning false to deny them
+    pub fn check<F>(&mut self, check: F) -> &mut OpenPolicy
+    where
+        F: Fn(&Url) -> bool + Send + Sync + 'static,
+    {
+        self.check = Some(Arc::new(check));
+        self
+    }
+
+    /// Check if the url may be opened
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let scheme = url.scheme();
+        if self.denied.iter().any(|denied| denied == scheme) {
+            return false;
+        }
+        if let Some(ref allowed) = self.allowed {
+            if !allowed.iter().any(|allowed| allowed == scheme) {
+                return false;
+            }
+        }
+        self.check.as_ref().map_or(true, |check| check(url))
+    }
+
+    /// Fail with `OpenError::Denied` at the first url which may not be opened, urls which
+    /// can't be parsed are denied
+    pub(crate) fn vet(&self, urls: &CFArray<CFURL>) -> OpenResult<()> {
+        for url in urls.iter() {
+            let raw = url.get_string().to_string();
+            match Url::parse(&raw) {
+                Ok(ref parsed) if self.is_allowed(parsed) => (),
+                _ => return Err(OpenError::Denied(raw)),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for OpenPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OpenPolicy")
+            .field("allowed", &self.allowed)
+            .field("denied", &self.denied)
+            .field("check", &self.check.as_ref().map(|_| "Fn(&Url) -> bool"))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_is_allowed() {
+        let allowed = |policy: &OpenPolicy, url: &str| policy.is_allowed(&Url::parse(url).unwrap());
+
+        let everything = OpenPolicy::new();
+        assert!(allowed(&everything, "file:///etc/hosts"));
+
+        let mut untrusted = OpenPolicy::untrusted();
+        assert!(allowed(&untrusted, "HTTPS://www.example.com/"));
+        assert!(allowed(&untrusted, "mailto:someone@example.com"));
+        assert!(!allowed(&untrusted, "javascript:alert(1)"));
+        assert!(!allowed(&untrusted, "file:///etc/hosts"));
+        assert!(!allowed(&untrusted, "slack://open"));
+
+        untrusted
+            .allow("file")
+            .check(|url| url.host_str() != Some("evil.example.com"));
+        assert!(!allowed(&untrusted, "file:///etc/hosts"));
+        assert!(!allowed(&untrusted, "https://evil.example.com/"));
+        assert!(allowed(&untrusted, "https://www.example.com/"));
+    }
+}
@@ -0,0 +1,125 @@
+//! Probe the content type of http(s) urls, to open a direct link to a document with the
+//! handler of its type instead of the browser.
+
+use crate::macos::handlers::default_handler_for_type;
+use crate::macos::ns::{nsstring, to_string, AutoreleasePool, Id};
+use crate::macos::Opener;
+
+use block::ConcreteBlock;
+use core_foundation::base::TCFType;
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation::url::CFURL;
+use core_foundation_sys::base::Boolean;
+use launch_services::LSRolesMask;
+
+use std::path::PathBuf;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+/// How long the HEAD request may take, in seconds
+const PROBE_TIMEOUT: f64 = 5.0;
+/// NSURLRequestUseProtocolCachePolicy
+const NS_URL_REQUEST_USE_PROTOCOL_CACHE_POLICY: usize = 0;
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    static kUTTagClassMIMEType: CFStringRef;
+
+    fn UTTypeCreatePreferredIdentifierForTag(
+        inTagClass: CFStringRef,
+        inTag: CFStringRef,
+        inConformingToUTI: CFStringRef,
+    ) -> CFStringRef;
+    fn UTTypeConformsTo(inUTI: CFStringRef, inConformsToUTI: CFStringRef) -> Boolean;
+}
+
+/// The MIME type reported by a HEAD request, without its parameters
+fn content_type(url: &CFURL) -> Option<String> {
+    let method = CFString::new("HEAD");
+    let _pool = AutoreleasePool::new();
+    let (tx, rx) = channel::<Option<String>>();
+
+    let handler = ConcreteBlock::new(move |_data: Id, response: Id, _error: Id| {
+        let mime = if response.is_null() {
+            None
+        } else {
+            unsafe {
+                let mime: Id = msg_send![response, MIMEType];
+                to_string(mime)
+            }
+        };
+        let _ = tx.send(mime);
+    })
+    .copy();
+
+    unsafe {
+        let request: Id = msg_send![
+            class!(NSMutableURLRequest),
+            requestWithURL: url.as_concrete_TypeRef() as Id
+            cachePolicy: NS_URL_REQUEST_USE_PROTOCOL_CACHE_POLICY
+            timeoutInterval: PROBE_TIMEOUT
+        ];
+        let _: () = msg_send![request, setHTTPMethod: nsstring(&method)];
+
+        let session: Id = msg_send![class!(NSURLSession), sharedSession];
+        let task: Id =
+            msg_send![session, dataTaskWithRequest: request completionHandler: &*handler];
+        let _: () = msg_send![task, resume];
+    }
+
+    // the session enforces the timeout, the margin covers a handler which is never called
+    rx.recv_timeout(Duration::from_secs_f64(PROBE_TIMEOUT + 1.0))
+        .ok()
+        .flatten()
+}
+
+/// The uniform type identifier of the MIME type, `None` for the unknown ones
+fn uti_for_mime(mime: &str) -> Option<CFString> {
+    let mime = CFString::new(mime);
+    let uti = unsafe {
+        UTTypeCreatePreferredIdentifierForTag(
+            kUTTagClassMIMEType,
+            mime.as_concrete_TypeRef(),
+            std::ptr::null(),
+        )
+    };
+    if uti.is_null() {
+        return None;
+    }
+    let uti = unsafe { CFString::wrap_under_create_rule(uti) };
+    if uti.to_string().starts_with("dyn.") {
+        None
+    } else {
+        Some(uti)
+    }
+}
+
+/// The app opening the document behind an http(s) url, `None` for web pages, unknown types
+/// and urls which can't be reached: they are left to the handler of the scheme
+pub(crate) fn handler_for_remote(url: &CFURL) -> Option<PathBuf> {
+    let raw = url.get_string().to_string().to_ascii_lowercase();
+    if !raw.starts_with("http://") && !raw.starts_with("https://") {
+        return None;
+    }
+    let uti = uti_for_mime(&content_type(url)?)?;
+    let html = CFString::new("public.html");
+    if unsafe { UTTypeConformsTo(uti.as_concrete_TypeRef(), html.as_concrete_TypeRef()) } != 0 {
+        return None;
+    }
+    let bundle_id = default_handler_for_type(&uti.to_string(), LSRolesMask::ALL)?;
+    Opener::global().app_for_bundle_id(&bundle_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uti_for_mime() {
+        assert_eq!(
+            uti_for_mime("application/pdf").map(|uti| uti.to_string()),
+            Some("com.adobe.pdf".to_string())
+        );
+        assert_eq!(uti_for_mime("application/x-nothing-at-all"), None);
+    }
+}
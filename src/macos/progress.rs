@@ -0,0 +1,154 @@
+//! Report the progress of large batch opens, e.g. to show a progress bar.
+
+use crate::macos::{LaunchedApp, OpenError, OpenOptions, OpenResult, Openable};
+
+use core_foundation::array::CFArray;
+use core_foundation::url::CFURL;
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Where a batch open is: every item is converted to a url first, then delivered to its app
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenProgress {
+    pub(crate) total: usize,
+    pub(crate) converted: usize,
+    pub(crate) delivered: usize,
+    pub(crate) current: Option<String>,
+}
+
+impl OpenProgress {
+    /// The number of items of the batch
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The number of items converted to urls so far, the invalid ones included
+    pub fn converted(&self) -> usize {
+        self.converted
+    }
+
+    /// The number of items delivered to their app so far, the failed launches included
+    pub fn delivered(&self) -> usize {
+        self.delivered
+    }
+
+    /// The url of the item just converted or delivered, `None` for an invalid item
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// How much of the work is done, from 0 to 1, counting the conversion and the delivery of
+    /// an item as much
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.converted + self.delivered) as f64 / (2 * self.total) as f64
+        }
+    }
+}
+
+/// The progress callback of `OpenOptions`, shared by its clones
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(pub(crate) Arc<dyn Fn(&OpenProgress) + Send + Sync>);
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Fn(&OpenProgress)")
+    }
+}
+
+/// `open_each`, invoking the callback after each item is converted and after each item is
+/// delivered. Cancelling the cancel token of the options (e.g. from the callback) stops the
+/// batch, the items not delivered yet fail with `OpenError::Cancelled`.
+///
+/// ```no_run
+/// use macos_open::{open_each_with_progress, OpenOptions};
+///
+/// let paths = vec!["/etc/hosts", "/etc/shells"];
+/// open_each_with_progress(&paths, &OpenOptions::new(), |progress| {
+///     println!("{:.0}%", progress.fraction() * 100.0);
+/// });
+/// ```
+pub fn open_each_with_progress<T, F>(
+    items: &[T],
+    options: &OpenOptions,
+    mut progress: F,
+) -> Vec<OpenResult<LaunchedApp>>
+where
+    T: Openable,
+    F: FnMut(&OpenProgress),
+{
+    let cancelled = || options.cancel.as_ref().map(|token| token.is_cancelled()) == Some(true);
+    let mut state = OpenProgress {
+        total: items.len(),
+        converted: 0,
+        delivered: 0,
+        current: None,
+    };
+
+    let mut urls = Vec::with_capacity(items.len());
+    for item in items {
+        if cancelled() {
+            break;
+        }
+        let url = Openable::into_openable(item);
+        state.converted += 1;
+        state.current = url.as_ref().map(|url| url.get_string().to_string());
+        progress(&state);
+        urls.push(url);
+    }
+
+    let mut results = Vec::with_capacity(items.len());
+    for url in urls {
+        if cancelled() {
+            break;
+        }
+        state.current = url.as_ref().map(|url| url.get_string().to_string());
+        results.push(match url {
+            Some(url) => options.launch_urls(Some(CFArray::<CFURL>::from_CFTypes(&[url]))),
+            None => Err(OpenError::InvalidUrl),
+        });
+        state.delivered += 1;
+        progress(&state);
+    }
+    results.resize_with(items.len(), || Err(OpenError::Cancelled));
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macos::CancelToken;
+
+    #[test]
+    fn test_open_each_with_progress() {
+        let token = CancelToken::new();
+        let mut options = OpenOptions::new();
+        options.cancel_token(token.clone());
+
+        let mut seen = Vec::new();
+        let res = open_each_with_progress(
+            &["https://www.google.com/", "not a valid url", "/etc/hosts"],
+            &options,
+            |progress| {
+                seen.push((progress.converted(), progress.delivered()));
+                if progress.delivered() == 2 {
+                    token.cancel();
+                }
+            },
+        );
+        assert_eq!(seen, vec![(1, 0), (2, 0), (3, 0), (3, 1), (3, 2)]);
+        assert_eq!(res.len(), 3);
+        assert!(res[0].is_ok());
+        match res[1] {
+            Err(OpenError::InvalidUrl) => (),
+            ref other => panic!("unexpected result {:?}", other),
+        }
+        match res[2] {
+            Err(OpenError::Cancelled) => (),
+            ref other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Quarantine attributes and Gatekeeper assessments.
+
+use crate::macos::bundle::{dictionary_string, dictionary_value};
+use crate::macos::{OpenError, OpenResult};
+
+use core_foundation::base::{CFTypeRef, TCFType};
+use core_foundation::boolean::CFBoolean;
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::error::CFErrorRef;
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation::url::{CFURLRef, CFURL};
+use core_foundation_sys::base::CFRelease;
+use libc::{c_char, c_int, c_void, size_t, ssize_t};
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const QUARANTINE_ATTRIBUTE: &str = "com.apple.quarantine";
+
+type SecAssessmentRef = CFTypeRef;
+
+extern "C" {
+    fn getxattr(
+        path: *const c_char,
+        name: *const c_char,
+        value: *mut c_void,
+        size: size_t,
+        position: u32,
+        options: c_int,
+    ) -> ssize_t;
+}
+
+#[link(name = "Security", kind = "framework")]
+extern "C" {
+    static kSecAssessmentContextKeyOperation: CFStringRef;
+    static kSecAssessmentOperationTypeExecute: CFStringRef;
+    static kSecAssessmentOperationTypeOpenDocument: CFStringRef;
+    static kSecAssessmentAssessmentVerdict: CFStringRef;
+    static kSecAssessmentAssessmentAuthority: CFStringRef;
+    static kSecAssessmentAssessmentSource: CFStringRef;
+
+    fn SecAssessmentCreate(
+        path: CFURLRef,
+        flags: u32,
+        context: CFDictionaryRef,
+        errors: *mut CFErrorRef,
+    ) -> SecAssessmentRef;
+    fn SecAssessmentCopyResult(assessment: SecAssessmentRef, flags: u32) -> CFDictionaryRef;
+}
+
+/// The quarantine attribute macOS attaches to downloaded files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuarantineInfo {
+    flags: u32,
+    timestamp: Option<SystemTime>,
+    agent: Option<String>,
+    event_id: Option<String>,
+}
+
+impl QuarantineInfo {
+    fn parse(value: &str) -> Option<QuarantineInfo> {
+        let mut fields = value.split(';');
+        let flags = u32::from_str_radix(fields.next()?, 16).ok()?;
+        let timestamp = fields
+            .next()
+            .and_then(|timestamp| u64::from_str_radix(timestamp, 16).ok())
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        let agent = fields.next().filter(|s| !s.is_empty()).map(str::to_string);
+        let event_id = fields.next().filter(|s| !s.is_empty()).map(str::to_string);
+
+        Some(QuarantineInfo {
+            flags,
+            timestamp,
+            agent,
+            event_id,
+        })
+    }
+
+    /// The raw quarantine flags
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// When the file was quarantined
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        self.timestamp
+    }
+
+    /// The name of the app which downloaded the file
+    pub fn agent(&self) -> Option<&str> {
+        self.agent.as_ref().map(String::as_str)
+    }
+
+    /// The identifier of the download in the quarantine events database
+    pub fn event_id(&self) -> Option<&str> {
+        self.event_id.as_ref().map(String::as_str)
+    }
+
+    /// If the user already approved the file in a Gatekeeper dialog
+    pub fn is_approved(&self) -> bool {
+        self.flags & 0x0040 != 0
+    }
+}
+
+/// Read the quarantine attribute of the file at the given path
+pub fn quarantine_status(path: &Path) -> Option<QuarantineInfo> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let name = CString::new(QUARANTINE_ATTRIBUTE).ok()?;
+    let mut value = vec![0u8; 1024];
+
+    let len = unsafe {
+        getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_mut_ptr() as *mut c_void,
+            value.len(),
+            0,
+            0,
+        )
+    };
+    if len < 0 {
+        return None;
+    }
+    value.truncate(len as usize);
+    QuarantineInfo::parse(std::str::from_utf8(&value).ok()?)
+}
+
+/// Ask Gatekeeper if the app (`execute`) or the document at the url can be opened, without
+/// showing any dialog
+pub(crate) fn assess(url: &CFURL, execute: bool) -> OpenResult<()> {
+    let name = || url.get_string().to_string();
+
+    let operation = unsafe {
+        if execute {
+            kSecAssessmentOperationTypeExecute
+        } else {
+            kSecAssessmentOperationTypeOpenDocument
+        }
+    };
+    let context = unsafe {
+        CFDictionary::from_CFType_pairs(&[(
+            CFString::wrap_under_get_rule(kSecAssessmentContextKeyOperation),
+            CFString::wrap_under_get_rule(operation),
+        )])
+    };
+
+    let mut error: CFErrorRef = std::ptr::null_mut();
+    let assessment = unsafe {
+        SecAssessmentCreate(
+            url.as_concrete_TypeRef(),
+            0,
+            context.as_concrete_TypeRef(),
+            &mut error,
+        )
+    };
+    if assessment.is_null() {
+        if !error.is_null() {
+            unsafe { CFRelease(error as CFTypeRef) };
+        }
+        return Err(OpenError::Blocked(name()));
+    }
+
+    let result = unsafe { SecAssessmentCopyResult(assessment, 0) };
+    unsafe { CFRelease(assessment) };
+    if result.is_null() {
+        return Err(OpenError::Blocked(name()));
+    }
+    let result: CFDictionary = unsafe { CFDictionary::wrap_under_create_rule(result) };
+
+    let key = |key: CFStringRef| unsafe { CFString::wrap_under_get_rule(key) }.to_string();
+    let verdict = dictionary_value(&result, &key(unsafe { kSecAssessmentAssessmentVerdict }))
+        .and_then(|verdict| verdict.downcast::<CFBoolean>())
+        .map(bool::from)
+        .unwrap_or(false);
+    if verdict {
+        return Ok(());
+    }
+
+    let source = dictionary_value(&result, &key(unsafe { kSecAssessmentAssessmentAuthority }))
+        .and_then(|authority| authority.downcast::<CFDictionary>())
+        .and_then(|authority| {
+            dictionary_string(&authority, &key(unsafe { kSecAssessmentAssessmentSource }))
+        });
+    match source {
+        Some(ref source) if source.contains("Unnotarized") => Err(OpenError::Unnotarized(name())),
+        _ => Err(OpenError::Blocked(name())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quarantine() {
+        let info =
+            QuarantineInfo::parse("0083;5f5b5c3a;Safari;C0FFEE00-0000-0000-0000-000000000000")
+                .unwrap();
+        assert_eq!(info.flags(), 0x83);
+        assert_eq!(
+            info.timestamp(),
+            Some(UNIX_EPOCH + Duration::from_secs(0x5f5b_5c3a))
+        );
+        assert_eq!(info.agent(), Some("Safari"));
+        assert!(!info.is_approved());
+
+        let info = QuarantineInfo::parse("01c1;5f5b5c3a;;").unwrap();
+        assert_eq!(info.agent(), None);
+        assert_eq!(info.event_id(), None);
+        assert!(info.is_approved());
+    }
+}
@@ -0,0 +1,90 @@
+//! Ranking and deduplication of app lookup results.
+
+use crate::macos::bundle::bundle_identifier;
+use crate::macos::version::Version;
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const SYSTEM_APPLICATIONS_DIRS: [&str; 2] = ["/Applications", "/System/Applications"];
+
+/// Lower is better: system wide applications folders first, then the user's one, then the rest
+/// (Downloads, external volumes, build folders, ...)
+fn location_rank(path: &Path) -> u8 {
+    if SYSTEM_APPLICATIONS_DIRS
+        .iter()
+        .any(|dir| path.starts_with(dir))
+    {
+        0
+    } else if std::env::var_os("HOME")
+        .map(|home| path.starts_with(Path::new(&home).join("Applications")))
+        .unwrap_or(false)
+    {
+        1
+    } else {
+        2
+    }
+}
+
+struct Candidate {
+    path: PathBuf,
+    bundle_id: Option<String>,
+    version: Option<Version>,
+}
+
+impl Candidate {
+    fn new(path: PathBuf) -> Candidate {
+        Candidate {
+            bundle_id: bundle_identifier(&path),
+            version: Version::of_app(&path),
+            path,
+        }
+    }
+
+    fn cmp(&self, other: &Candidate) -> Ordering {
+        location_rank(&self.path)
+            .cmp(&location_rank(&other.path))
+            .then_with(|| match (&self.version, &other.version) {
+                (Some(a), Some(b)) => b.cmp(a),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            })
+    }
+}
+
+/// Sort apps preferring the standard applications folders and newer versions, then remove
+/// every app whose bundle identifier was already seen
+pub(crate) fn rank_apps(apps: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut candidates = apps.into_iter().map(Candidate::new).collect::<Vec<_>>();
+    candidates.sort_by(Candidate::cmp);
+
+    let mut seen_ids = HashSet::new();
+    let mut seen_paths = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|candidate| match candidate.bundle_id {
+            Some(ref id) => seen_ids.insert(id.to_lowercase()),
+            None => seen_paths.insert(candidate.path.clone()),
+        })
+        .map(|candidate| candidate.path)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_rank() {
+        assert!(
+            location_rank(Path::new("/Applications/Safari.app"))
+                < location_rank(Path::new("/Volumes/Backup/Safari.app"))
+        );
+        assert_eq!(
+            location_rank(Path::new("/System/Applications/Preview.app")),
+            0
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Recently opened documents and applications.
+//!
+//! The `LSSharedFileList` recent lists are always empty since macOS 10.11, the lists are kept
+//! as keyed archives of bookmarks (`.sfl2`, `.sfl3` since macOS 14) by `sharedfilelistd`.
+
+use crate::macos::ns::{array_iter, nsstring, to_path, AutoreleasePool, Id};
+use crate::macos::{OpenError, OpenResult};
+
+use core_foundation::base::TCFType;
+use core_foundation::data::{CFData, CFDataRef};
+use core_foundation::string::CFString;
+use objc::runtime::{Object, BOOL, NO};
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::ptr::null_mut;
+
+/// Where the shared file lists are stored, relative to the home folder
+const STORES: &str = "Library/Application Support/com.apple.sharedfilelist";
+const RECENT_DOCUMENTS: &str = "com.apple.LSSharedFileList.RecentDocuments";
+const RECENT_APPS: &str = "com.apple.LSSharedFileList.RecentApplications";
+/// The store extensions, newest format first
+const STORE_EXTENSIONS: [&str; 2] = ["sfl3", "sfl2"];
+
+const NS_URL_BOOKMARK_RESOLUTION_WITHOUT_UI: usize = 1 << 8;
+const NS_URL_BOOKMARK_RESOLUTION_WITHOUT_MOUNTING: usize = 1 << 9;
+
+/// The store of a list, None if the list was never written
+fn store(name: &str) -> OpenResult<Option<PathBuf>> {
+    let home = std::env::var_os("HOME")
+        .ok_or_else(|| OpenError::Io(io::Error::new(io::ErrorKind::NotFound, "HOME")))?;
+    let dir = PathBuf::from(home).join(STORES);
+    Ok(STORE_EXTENSIONS
+        .iter()
+        .map(|extension| dir.join(format!("{}.{}", name, extension)))
+        .find(|path| path.is_file()))
+}
+
+fn key(name: &'static str) -> CFString {
+    CFString::from_static_string(name)
+}
+
+/// Unarchive the root dictionary of a store, autoreleased
+fn read_store(path: &Path) -> OpenResult<Id> {
+    let data = CFData::from_buffer(&fs::read(path).map_err(OpenError::Io)?);
+    let data = data.as_concrete_TypeRef() as Id;
+    let mut error: Id = null_mut();
+    let root: Id = unsafe {
+        msg_send![class!(NSKeyedUnarchiver),
+            unarchiveTopLevelObjectWithData: data
+            error: &mut error]
+    };
+    let is_dictionary: BOOL = if root.is_null() {
+        NO
+    } else {
+        unsafe { msg_send![root, isKindOfClass: class!(NSDictionary)] }
+    };
+    if is_dictionary == NO {
+        return Err(OpenError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a shared file list store",
+        )));
+    }
+    Ok(root)
+}
+
+/// Resolve the bookmark of a store item without user interaction or mounting volumes
+fn resolve_item(item: Id) -> Option<PathBuf> {
+    unsafe {
+        let bookmark: Id = msg_send![item, objectForKey: nsstring(&key("Bookmark"))];
+        if bookmark.is_null() {
+            return None;
+        }
+        let options =
+            NS_URL_BOOKMARK_RESOLUTION_WITHOUT_UI | NS_URL_BOOKMARK_RESOLUTION_WITHOUT_MOUNTING;
+        let mut stale: BOOL = NO;
+        let url: Id = msg_send![class!(NSURL),
+            URLByResolvingBookmarkData: bookmark
+            options: options
+            relativeToURL: null_mut::<Object>()
+            bookmarkDataIsStale: &mut stale
+            error: null_mut::<Id>()];
+        to_path(url)
+    }
+}
+
+/// Get at most `limit` paths out of a store, in the order of the list
+fn store_items(path: &Path, limit: usize) -> OpenResult<Vec<PathBuf>> {
+    let _pool = AutoreleasePool::new();
+    let root = read_store(path)?;
+    let items: Id = unsafe { msg_send![root, objectForKey: nsstring(&key("items"))] };
+    Ok(array_iter(items)
+        .filter_map(resolve_item)
+        .take(limit)
+        .collect())
+}
+
+/// Write a store back without its items, keeping its properties
+fn clear_store(path: &Path) -> OpenResult<()> {
+    let _pool = AutoreleasePool::new();
+    let root = read_store(path)?;
+    let data = unsafe {
+        let root: Id = msg_send![root, mutableCopy];
+        let root: Id = msg_send![root, autorelease];
+        let items: Id = msg_send![class!(NSArray), array];
+        let _: () = msg_send![root, setObject: items forKey: nsstring(&key("items"))];
+        let data: Id = msg_send![class!(NSKeyedArchiver), archivedDataWithRootObject: root];
+        CFData::wrap_under_get_rule(data as CFDataRef)
+    };
+    fs::write(path, data.bytes()).map_err(OpenError::Io)
+}
+
+fn recent_items(name: &str, limit: usize) -> OpenResult<Vec<PathBuf>> {
+    match store(name)? {
+        Some(path) => store_items(&path, limit),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Get at most `limit` paths from the recent documents list (the Apple menu's Recent Items),
+/// skipping the ones which can't be resolved
+pub fn recent_documents(limit: usize) -> OpenResult<Vec<PathBuf>> {
+    recent_items(RECENT_DOCUMENTS, limit)
+}
+
+/// Get at most `limit` paths from the recent applications list, skipping the ones which can't
+/// be resolved
+pub fn recent_apps(limit: usize) -> OpenResult<Vec<PathBuf>> {
+    recent_items(RECENT_APPS, limit)
+}
+
+/// Empty the recent documents list. `sharedfilelistd` reads the store again when it changes.
+pub fn clear_recent_documents() -> OpenResult<()> {
+    match store(RECENT_DOCUMENTS)? {
+        Some(path) => clear_store(&path),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core_foundation::url::CFURL;
+
+    /// Archive a store listing the given files, like sharedfilelistd does
+    fn write_store(path: &Path, files: &[&Path]) {
+        let _pool = AutoreleasePool::new();
+        unsafe {
+            let items: Id = msg_send![class!(NSMutableArray), array];
+            for file in files {
+                let url = CFURL::from_path(file, false).unwrap();
+                let bookmark: Id = msg_send![url.as_concrete_TypeRef() as Id,
+                    bookmarkDataWithOptions: 0usize
+                    includingResourceValuesForKeys: null_mut::<Object>()
+                    relativeToURL: null_mut::<Object>()
+                    error: null_mut::<Id>()];
+                let name = key("Bookmark");
+                let item: Id = msg_send![class!(NSDictionary),
+                    dictionaryWithObject: bookmark
+                    forKey: nsstring(&name)];
+                let _: () = msg_send![items, addObject: item];
+            }
+            let name = key("items");
+            let root: Id = msg_send![class!(NSDictionary),
+                dictionaryWithObject: items
+                forKey: nsstring(&name)];
+            let data: Id = msg_send![class!(NSKeyedArchiver), archivedDataWithRootObject: root];
+            fs::write(path, CFData::wrap_under_get_rule(data as CFDataRef).bytes()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_store_items() {
+        let dir = std::env::temp_dir().join(format!("macos-open-recents-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let first = dir.join("first.txt");
+        let second = dir.join("second.txt");
+        fs::write(&first, "first").unwrap();
+        fs::write(&second, "second").unwrap();
+        let store = dir.join("RecentDocuments.sfl2");
+        write_store(&store, &[&first, &second]);
+
+        assert_eq!(
+            store_items(&store, 10).unwrap(),
+            vec![
+                first.canonicalize().unwrap(),
+                second.canonicalize().unwrap()
+            ]
+        );
+        assert_eq!(store_items(&store, 1).unwrap().len(), 1);
+        clear_store(&store).unwrap();
+        assert!(store_items(&store, 10).unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
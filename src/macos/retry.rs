@@ -0,0 +1,244 @@
+//! Retry launches failing with transient Launch Services errors.
+
+use crate::macos::cancel::Deadline;
+use crate::macos::{OpenError, OpenResult};
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the deadline is checked while waiting for the next attempt
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// kLSUnknownErr, returned while the Launch Services database is being rebuilt
+const K_LS_UNKNOWN_ERR: i32 = -10810;
+/// kLSDataUnavailableErr
+const K_LS_DATA_UNAVAILABLE_ERR: i32 = -10813;
+/// kLSDataTooOldErr
+const K_LS_DATA_TOO_OLD_ERR: i32 = -10816;
+/// kLSLaunchInProgressErr
+const K_LS_LAUNCH_IN_PROGRESS_ERR: i32 = -10818;
+/// kLSServerCommunicationErr, `lsd` isn't ready yet (e.g. right after login)
+const K_LS_SERVER_COMMUNICATION_ERR: i32 = -10822;
+/// connectionInvalid, the app quit while receiving the Apple Event
+const CONNECTION_INVALID: i32 = -609;
+/// errAETimeout
+const ERR_AE_TIMEOUT: i32 = -1712;
+
+/// How launches failing with transient errors are retried: up to `max_attempts` attempts in
+/// total, waiting a delay growing by `multiplier` between them.
+///
+/// ```no_run
+/// use macos_open::{OpenOptions, RetryPolicy};
+/// use std::time::Duration;
+///
+/// OpenOptions::new()
+///     .retry(*RetryPolicy::new(5).backoff(Duration::from_millis(100), 2, Duration::from_secs(1)))
+///     .open("https://www.example.com/")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_delay: Duration,
+    multiplier: u32,
+    max_delay: Duration,
+    retryable: fn(&OpenError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new(3)
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy making at most `max_attempts` attempts, waiting 200ms, then 400ms and
+    /// so on (at most 2s) between them, retrying the errors `is_transient` accepts
+    pub fn new(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_delay: Duration::from_millis(200),
+            multiplier: 2,
+            max_delay: Duration::from_secs(2),
+            retryable: RetryPolicy::is_transient,
+        }
+    }
+
+    /// Wait `initial` before the first retry, multiplying the delay by `multiplier` for each
+    /// further retry, up to `max`
+    pub fn backoff(
+        &mut self,
+        initial: Duration,
+        multiplier: u32,
+        max: Duration,
+    ) -> &mut RetryPolicy {
+        self.initial_delay = initial;
+        self.multiplier = multiplier;
+        self.max_delay = max;
+        self
+    }
+
+    /// Choose which errors are retried
+    pub fn retryable(&mut self, classifier: fn(&OpenError) -> bool) -> &mut RetryPolicy {
+        self.retryable = classifier;
+        self
+    }
+
+    /// The default classifier: the Launch Services statuses reported while the system is
+    /// starting up, rebuilding its database or the app is still launching
+    pub fn is_transient(err: &OpenError) -> bool {
+        matches!(
+            err,
+            OpenError::Status(
+                K_LS_UNKNOWN_ERR
+                    | K_LS_DATA_UNAVAILABLE_ERR
+                    | K_LS_DATA_TOO_OLD_ERR
+                    | K_LS_LAUNCH_IN_PROGRESS_ERR
+                    | K_LS_SERVER_COMMUNICATION_ERR
+            )
+        )
+    }
+
+    /// `is_transient`, plus the Apple Event failures (the app quit or didn't answer in time).
+    /// Opt-in with `retryable`: the app may have handled the event anyway, and open the urls
+    /// twice when it's retried.
+    pub fn is_transient_or_event_failure(err: &OpenError) -> bool {
+        RetryPolicy::is_transient(err)
+            || matches!(err, OpenError::Status(CONNECTION_INVALID | ERR_AE_TIMEOUT))
+    }
+
+    /// The delay before the given retry, the first being 0
+    fn delay(&self, retry: u32) -> Duration {
+        let mut delay = self.initial_delay;
+        for _ in 0..retry {
+            delay = delay.checked_mul(self.multiplier).unwrap_or(self.max_delay);
+            if delay >= self.max_delay {
+                return self.max_delay;
+            }
+        }
+        delay.min(self.max_delay)
+    }
+
+    /// Run the operation until it succeeds, fails with an error which isn't retryable or the
+    /// attempts are over. Once the deadline passes no other attempt is made, and its error is
+    /// returned.
+    pub(crate) fn run<T, F>(&self, deadline: &Deadline, mut operation: F) -> OpenResult<T>
+    where
+        F: FnMut() -> OpenResult<T>,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation() {
+                Err(ref err) if attempt < self.max_attempts && (self.retryable)(err) => {
+                    pause(deadline, self.delay(attempt - 1))?;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// Sleep for the delay, failing as soon as the deadline passes or its token is cancelled
+fn pause(deadline: &Deadline, delay: Duration) -> OpenResult<()> {
+    let until = Instant::now() + delay;
+    loop {
+        deadline.check()?;
+        let now = Instant::now();
+        if now >= until {
+            return Ok(());
+        }
+        thread::sleep(deadline.step(POLL_INTERVAL).min(until - now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macos::CancelToken;
+
+    #[test]
+    fn test_retry_policy_delay() {
+        let mut policy = RetryPolicy::new(10);
+        policy.backoff(Duration::from_millis(100), 3, Duration::from_millis(500));
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(300));
+        assert_eq!(policy.delay(2), Duration::from_millis(500));
+        assert_eq!(policy.delay(30), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_retry_policy_run() {
+        let unbounded = Deadline::new(None, None);
+        let mut policy = RetryPolicy::new(3);
+        policy.backoff(Duration::from_millis(0), 1, Duration::from_millis(0));
+
+        let mut attempts = 0;
+        let res: OpenResult<()> = policy.run(&unbounded, || {
+            attempts += 1;
+            Err(OpenError::Status(K_LS_SERVER_COMMUNICATION_ERR))
+        });
+        assert!(res.is_err());
+        assert_eq!(attempts, 3);
+
+        attempts = 0;
+        let res = policy.run(&unbounded, || {
+            attempts += 1;
+            match attempts {
+                1 => Err(OpenError::Status(K_LS_LAUNCH_IN_PROGRESS_ERR)),
+                _ => Ok(attempts),
+            }
+        });
+        assert_eq!(res.unwrap(), 2);
+
+        attempts = 0;
+        let res: OpenResult<()> = policy.run(&unbounded, || {
+            attempts += 1;
+            Err(OpenError::InvalidUrl)
+        });
+        assert!(res.is_err());
+        assert_eq!(attempts, 1);
+
+        attempts = 0;
+        let res: OpenResult<()> = policy.run(&unbounded, || {
+            attempts += 1;
+            Err(OpenError::Status(ERR_AE_TIMEOUT))
+        });
+        assert!(res.is_err());
+        assert_eq!(attempts, 1);
+
+        attempts = 0;
+        policy.retryable(RetryPolicy::is_transient_or_event_failure);
+        let res: OpenResult<()> = policy.run(&unbounded, || {
+            attempts += 1;
+            Err(OpenError::Status(CONNECTION_INVALID))
+        });
+        assert!(res.is_err());
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn test_retry_policy_deadline() {
+        let mut policy = RetryPolicy::new(100);
+        policy.backoff(Duration::from_millis(20), 1, Duration::from_millis(20));
+        let cancel = CancelToken::new();
+        let deadline = Deadline::new(Some(Duration::from_millis(100)), Some(&cancel));
+
+        let mut attempts = 0;
+        let res: OpenResult<()> = policy.run(&deadline, || {
+            attempts += 1;
+            Err(OpenError::Status(K_LS_SERVER_COMMUNICATION_ERR))
+        });
+        assert!(matches!(res, Err(OpenError::TimedOut)));
+        assert!(attempts < 100);
+
+        cancel.cancel();
+        attempts = 0;
+        let res: OpenResult<()> = policy.run(&deadline, || {
+            attempts += 1;
+            Err(OpenError::Status(K_LS_SERVER_COMMUNICATION_ERR))
+        });
+        assert!(matches!(res, Err(OpenError::Cancelled)));
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,329 @@
+//! Reveal and select files in Finder.
+
+use crate::macos::ns::{nsstring, shared_workspace, to_string, AutoreleasePool, Id};
+use crate::macos::{open_default, OpenError, OpenResult};
+
+use core_foundation::array::CFArray;
+use core_foundation::base::TCFType;
+use core_foundation::string::CFString;
+use core_foundation::url::CFURL;
+use libc::c_void;
+use objc::runtime::{BOOL, NO};
+
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+
+/// A dispatch queue, only its address is used
+#[repr(C)]
+struct DispatchQueue {
+    _private: [u8; 0],
+}
+
+extern "C" {
+    /// The main queue, `dispatch_get_main_queue()` is a macro returning its address
+    static _dispatch_main_q: DispatchQueue;
+    fn dispatch_sync_f(
+        queue: *const DispatchQueue,
+        context: *mut c_void,
+        work: extern "C" fn(*mut c_void),
+    );
+}
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    /// The shared NSApplication, nil until an app creates it
+    static NSApp: Id;
+}
+
+/// Serializes the scripts run off the main thread
+static SCRIPT_LOCK: Mutex<()> = Mutex::new(());
+
+fn file_url(path: &Path) -> OpenResult<CFURL> {
+    if !path.exists() {
+        return Err(OpenError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            path.display().to_string(),
+        )));
+    }
+    let path = path.canonicalize().map_err(OpenError::Io)?;
+    CFURL::from_path(&path, path.is_dir()).ok_or(OpenError::InvalidUrl)
+}
+
+/// Select the urls in the Finder windows already showing their folders, opening new ones as
+/// needed
+fn activate_file_viewer(urls: &[CFURL]) {
+    let urls = CFArray::<CFURL>::from_CFTypes(urls);
+    let _pool = AutoreleasePool::new();
+    unsafe {
+        let _: () = msg_send![
+            shared_workspace(),
+            activateFileViewerSelectingURLs: urls.as_concrete_TypeRef() as Id
+        ];
+    }
+}
+
+/// Open a new Finder window on the folder, selecting the file
+fn select_in_new_viewer(file: &CFURL, folder: &Path) -> OpenResult<()> {
+    let file = file.to_path().ok_or(OpenError::InvalidUrl)?;
+    new_viewer(&file.to_string_lossy(), folder)
+}
+
+/// Open a new Finder window on the folder, selecting the file unless it's empty
+fn new_viewer(file: &str, folder: &Path) -> OpenResult<()> {
+    let file = CFString::new(file);
+    let folder = CFString::new(&folder.to_string_lossy());
+
+    let _pool = AutoreleasePool::new();
+    let selected: BOOL = unsafe {
+        msg_send![
+            shared_workspace(),
+            selectFile: nsstring(&file)
+            inFileViewerRootedAtPath: nsstring(&folder)
+        ]
+    };
+    if selected == NO {
+        Err(OpenError::ActivationFailed("Finder".to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Show the file in Finder, selected
+pub fn reveal(path: &Path) -> OpenResult<()> {
+    reveal_selecting(&[path], false)
+}
+
+/// Show the items in Finder, selected, with one window per parent folder. With `new_window`
+/// new windows are opened even if Finder is already showing the folders.
+pub fn reveal_selecting(items: &[&Path], new_window: bool) -> OpenResult<()> {
+    let urls = items
+        .iter()
+        .map(|item| file_url(item))
+        .collect::<OpenResult<Vec<_>>>()?;
+    if urls.is_empty() {
+        return Ok(());
+    }
+    if !new_window {
+        activate_file_viewer(&urls);
+        return Ok(());
+    }
+
+    let mut folders: Vec<(PathBuf, Vec<CFURL>)> = Vec::new();
+    for url in urls {
+        let parent = url
+            .to_path()
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+            .unwrap_or_else(|| PathBuf::from("/"));
+        match folders.iter_mut().find(|(folder, _)| *folder == parent) {
+            Some((_, urls)) => urls.push(url),
+            None => folders.push((parent, vec![url])),
+        }
+    }
+
+    for (folder, urls) in folders {
+        select_in_new_viewer(&urls[0], &folder)?;
+        if urls.len() > 1 {
+            // the new window is the one showing the folder now, select the rest there
+            activate_file_viewer(&urls);
+        }
+    }
+    Ok(())
+}
+
+/// The view style of a Finder window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FinderView {
+    /// Icons
+    Icon,
+    /// A list with the details of the items
+    List,
+    /// Columns
+    Column,
+    /// Large previews
+    Gallery,
+}
+
+impl FinderView {
+    /// The name of the view in Finder's scripting dictionary
+    fn script_name(self) -> &'static str {
+        match self {
+            FinderView::Icon => "icon view",
+            FinderView::List => "list view",
+            FinderView::Column => "column view",
+            FinderView::Gallery => "flow view",
+        }
+    }
+}
+
+/// Escape a value for an AppleScript string literal
+fn script_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The script opening the folder in Finder with the given view
+fn folder_script(folder: &Path, new_window: bool, view: FinderView) -> String {
+    let folder = script_string(&folder.to_string_lossy());
+    let window = if new_window {
+        format!(
+            "set w to make new Finder window to (POSIX file {} as alias)",
+            folder
+        )
+    } else {
+        format!(
+            "open (POSIX file {} as alias)\n    set w to Finder window 1",
+            folder
+        )
+    };
+    format!(
+        "tell application \"Finder\"\n    activate\n    {}\n    set current view of w to {}\nend tell",
+        window,
+        view.script_name()
+    )
+}
+
+/// If an app run loop is serving the main dispatch queue
+fn main_loop_running() -> bool {
+    let app = unsafe { NSApp };
+    if app.is_null() {
+        return false;
+    }
+    let running: BOOL = unsafe { msg_send![app, isRunning] };
+    running != NO
+}
+
+/// Run the closure on the main thread and wait for it, if an app runs its main loop. Without
+/// one nothing would ever serve the main queue (e.g. a command line tool calling from a worker
+/// thread), so the closure runs on the current thread, one at a time.
+fn on_main_thread<T, F: FnOnce() -> T>(f: F) -> T {
+    type Job<F, T> = (Option<F>, Option<thread::Result<T>>);
+
+    extern "C" fn run<T, F: FnOnce() -> T>(context: *mut c_void) {
+        let job = unsafe { &mut *(context as *mut Job<F, T>) };
+        if let Some(f) = job.0.take() {
+            // unwinding out of an extern "C" function aborts, the panic is resumed by the caller
+            job.1 = Some(panic::catch_unwind(AssertUnwindSafe(f)));
+        }
+    }
+
+    let is_main: BOOL = unsafe { msg_send![class!(NSThread), isMainThread] };
+    if is_main != NO {
+        return f();
+    }
+    if !main_loop_running() {
+        let _lock = SCRIPT_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        return f();
+    }
+    let mut job: Job<F, T> = (Some(f), None);
+    unsafe {
+        dispatch_sync_f(
+            &_dispatch_main_q,
+            &mut job as *mut Job<F, T> as *mut c_void,
+            run::<T, F>,
+        )
+    };
+    match job.1.expect("the main queue ran the job") {
+        Ok(value) => value,
+        Err(payload) => panic::resume_unwind(payload),
+    }
+}
+
+/// Run an AppleScript on the main thread, NSAppleScript isn't thread safe
+fn run_script(source: &str) -> OpenResult<()> {
+    on_main_thread(|| execute_script(source))
+}
+
+/// Run an AppleScript on the current thread, failing with its error number or, without one,
+/// its error message
+fn execute_script(source: &str) -> OpenResult<()> {
+    let source = CFString::new(source);
+    let number_key = CFString::new("NSAppleScriptErrorNumber");
+    let message_key = CFString::new("NSAppleScriptErrorMessage");
+    let _pool = AutoreleasePool::new();
+    unsafe {
+        let script: Id = msg_send![class!(NSAppleScript), alloc];
+        let script: Id = msg_send![script, initWithSource: nsstring(&source)];
+        let mut error: Id = std::ptr::null_mut();
+        let result: Id = msg_send![script, executeAndReturnError: &mut error];
+        let _: () = msg_send![script, release];
+        if !result.is_null() {
+            return Ok(());
+        }
+        if error.is_null() {
+            return Err(OpenError::ScriptFailed(String::new()));
+        }
+        let number: Id = msg_send![error, objectForKey: nsstring(&number_key)];
+        if number.is_null() {
+            let message: Id = msg_send![error, objectForKey: nsstring(&message_key)];
+            return Err(OpenError::ScriptFailed(
+                to_string(message).unwrap_or_default(),
+            ));
+        }
+        let code: i32 = msg_send![number, intValue];
+        Err(OpenError::Status(code))
+    }
+}
+
+/// Show the folder in Finder. With `new_window` a new window is opened even if Finder is
+/// already showing the folder or one of its parents. Setting the view asks Finder through
+/// AppleScript, the calling app needs the Automation permission for Finder.
+pub fn open_folder(folder: &Path, new_window: bool, view: Option<FinderView>) -> OpenResult<()> {
+    let url = file_url(folder)?;
+    let folder = url.to_path().ok_or(OpenError::InvalidUrl)?;
+    if !folder.is_dir() {
+        return Err(OpenError::Io(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a folder", folder.display()),
+        )));
+    }
+    match view {
+        Some(view) => run_script(&folder_script(&folder, new_window, view)),
+        None if new_window => new_viewer("", &folder),
+        None => open_default(&folder).map(|_| ()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folder_script() {
+        assert_eq!(
+            folder_script(Path::new("/tmp/a \"b\""), true, FinderView::List),
+            "tell application \"Finder\"\n    activate\n    \
+             set w to make new Finder window to (POSIX file \"/tmp/a \\\"b\\\"\" as alias)\n    \
+             set current view of w to list view\nend tell"
+        );
+    }
+
+    #[test]
+    fn test_on_main_thread() {
+        // no app runs the main loop of the test binary, this must not wait for it
+        let worker = thread::spawn(|| on_main_thread(|| 42));
+        assert_eq!(worker.join().unwrap(), 42);
+        assert!(panic::catch_unwind(|| on_main_thread::<(), _>(|| panic!("in the job"))).is_err());
+    }
+
+    #[test]
+    fn test_reveal_selecting() {
+        reveal_selecting(
+            &[
+                Path::new("/System/Applications/Calculator.app"),
+                Path::new("/System/Applications/Preview.app"),
+                Path::new("/Library"),
+            ],
+            true,
+        )
+        .unwrap();
+        match reveal(Path::new("/surely/not/existing")) {
+            Err(OpenError::Io(ref err)) if err.kind() == io::ErrorKind::NotFound => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
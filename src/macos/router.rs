@@ -0,0 +1,232 @@
+//! Route urls to apps with an ordered list of rules, e.g. to pick a browser per link. Enabled
+//! by the `router` feature.
+
+use crate::macos::{launch, open_default, open_with_bundle_id, OpenError, OpenResult};
+
+use launch_services::LSLaunchFlags;
+use regex::Regex;
+use url::Url;
+
+use std::path::{Path, PathBuf};
+
+/// What a rule matches, case insensitive except for regular expressions
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RouteMatch {
+    /// The scheme of the url, e.g. `mailto`
+    Scheme(String),
+    /// The host of the url or any of its subdomains, e.g. `example.com` matches
+    /// `www.example.com`
+    Host(String),
+    /// A regular expression matching anywhere in the whole url
+    Regex(String),
+    /// The extension of the last path component, without the dot
+    Extension(String),
+}
+
+/// Where the urls matched by a rule are opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RouteTarget {
+    /// The best installed app with the bundle identifier (see `apps_for_bundle_id`)
+    BundleId(String),
+    /// The app at the given path
+    App(PathBuf),
+    /// The default handler of the url
+    Default,
+}
+
+/// A rule of a `Router`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RouteRule {
+    /// What the rule matches
+    pub matcher: RouteMatch,
+    /// Where the matched urls are opened
+    pub target: RouteTarget,
+}
+
+impl RouteRule {
+    /// Create a rule opening the urls matched by `matcher` in `target`
+    pub fn new(matcher: RouteMatch, target: RouteTarget) -> RouteRule {
+        RouteRule { matcher, target }
+    }
+}
+
+/// A rule ready to be matched, with its regular expression compiled
+#[derive(Debug)]
+struct CompiledRule {
+    rule: RouteRule,
+    regex: Option<Regex>,
+}
+
+/// The url being routed, parsed once for all the rules
+struct Candidate<'a> {
+    raw: &'a str,
+    url: Option<Url>,
+}
+
+impl<'a> Candidate<'a> {
+    fn new(raw: &'a str) -> Candidate<'a> {
+        Candidate {
+            raw,
+            url: Url::parse(raw).ok(),
+        }
+    }
+
+    /// The path of the url, or the raw value if it isn't an url
+    fn path(&self) -> &str {
+        match self.url {
+            Some(ref url) => url.path(),
+            None => self.raw,
+        }
+    }
+}
+
+impl CompiledRule {
+    fn matches(&self, candidate: &Candidate) -> bool {
+        match self.rule.matcher {
+            RouteMatch::Scheme(ref scheme) => candidate
+                .url
+                .as_ref()
+                .map_or(false, |url| url.scheme().eq_ignore_ascii_case(scheme)),
+            RouteMatch::Host(ref host) => {
+                let actual = match candidate.url.as_ref().and_then(Url::host_str) {
+                    Some(actual) => actual.to_ascii_lowercase(),
+                    None => return false,
+                };
+                let host = host.to_ascii_lowercase();
+                actual == host || actual.ends_with(&format!(".{}", host))
+            }
+            RouteMatch::Regex(_) => self
+                .regex
+                .as_ref()
+                .map_or(false, |regex| regex.is_match(candidate.raw)),
+            RouteMatch::Extension(ref extension) => Path::new(candidate.path())
+                .extension()
+                .map_or(false, |actual| {
+                    actual.to_string_lossy().eq_ignore_ascii_case(extension)
+                }),
+        }
+    }
+}
+
+/// Open urls with the target of the first matching rule, or with their default handlers if no
+/// rule matches.
+///
+/// ```no_run
+/// use macos_open::{RouteMatch, RouteRule, RouteTarget, Router};
+///
+/// let router = Router::new(vec![
+///     RouteRule::new(
+///         RouteMatch::Host("github.com".to_string()),
+///         RouteTarget::BundleId("com.google.Chrome".to_string()),
+///     ),
+///     RouteRule::new(
+///         RouteMatch::Extension("pdf".to_string()),
+///         RouteTarget::App("/System/Applications/Preview.app".into()),
+///     ),
+/// ])
+/// .unwrap();
+/// router.route_and_open("https://github.com/shurizzle").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Router {
+    rules: Vec<CompiledRule>,
+}
+
+impl Router {
+    /// Create a router applying the rules in order, failing with `OpenError::InvalidRule` if a
+    /// regular expression doesn't compile
+    pub fn new(rules: Vec<RouteRule>) -> OpenResult<Router> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| {
+                let regex = match rule.matcher {
+                    RouteMatch::Regex(ref pattern) => Some(
+                        Regex::new(pattern)
+                            .map_err(|err| OpenError::InvalidRule(err.to_string()))?,
+                    ),
+                    _ => None,
+                };
+                Ok(CompiledRule { rule, regex })
+            })
+            .collect::<OpenResult<_>>()?;
+        Ok(Router { rules })
+    }
+
+    /// The rules of the router, in order
+    pub fn rules(&self) -> impl Iterator<Item = &RouteRule> {
+        self.rules.iter().map(|rule| &rule.rule)
+    }
+
+    /// The target of the first rule matching the url, `RouteTarget::Default` if none matches
+    pub fn route(&self, url: &str) -> &RouteTarget {
+        let candidate = Candidate::new(url);
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(&candidate))
+            .map_or(&RouteTarget::Default, |rule| &rule.rule.target)
+    }
+
+    /// Open the url with the target of the first matching rule, returning the path of the app
+    /// which opened it
+    pub fn route_and_open(&self, url: &str) -> OpenResult<Option<PathBuf>> {
+        match self.route(url) {
+            RouteTarget::BundleId(bundle_id) => {
+                open_with_bundle_id(bundle_id, url, LSLaunchFlags::DEFAULTS)
+            }
+            RouteTarget::App(app) => launch(
+                Some(app.as_path()),
+                Some(url),
+                LSLaunchFlags::DEFAULTS,
+                None,
+            ),
+            RouteTarget::Default => open_default(url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_router_route() {
+        let chrome = RouteTarget::BundleId("com.google.Chrome".to_string());
+        let preview = RouteTarget::App(PathBuf::from("/System/Applications/Preview.app"));
+        let mail = RouteTarget::BundleId("com.apple.mail".to_string());
+        let router = Router::new(vec![
+            RouteRule::new(RouteMatch::Scheme("MAILTO".to_string()), mail.clone()),
+            RouteRule::new(RouteMatch::Host("github.com".to_string()), chrome.clone()),
+            RouteRule::new(RouteMatch::Extension("pdf".to_string()), preview.clone()),
+            RouteRule::new(
+                RouteMatch::Regex(r"^https://meet\.".to_string()),
+                chrome.clone(),
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(router.route("mailto:someone@example.com"), &mail);
+        assert_eq!(router.route("https://gist.github.com/shurizzle"), &chrome);
+        assert_eq!(
+            router.route("https://notgithub.com/"),
+            &RouteTarget::Default
+        );
+        assert_eq!(router.route("https://www.example.com/paper.PDF"), &preview);
+        assert_eq!(router.route("/tmp/paper.pdf"), &preview);
+        assert_eq!(router.route("https://meet.example.com/abc"), &chrome);
+        assert_eq!(router.rules().count(), 4);
+    }
+
+    #[test]
+    fn test_router_invalid_regex() {
+        match Router::new(vec![RouteRule::new(
+            RouteMatch::Regex("(".to_string()),
+            RouteTarget::Default,
+        )]) {
+            Err(OpenError::InvalidRule(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
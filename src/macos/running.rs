@@ -0,0 +1,337 @@
+//! Queries about running applications.
+
+use crate::macos::ns::{
+    array_iter, nsstring, shared_workspace, to_path, to_string, AutoreleasePool, Id,
+};
+use crate::macos::cancel::{CancelToken, Deadline};
+use crate::macos::{app_for_bundle_id, OpenError, OpenResult, Opener};
+
+use core_foundation::string::CFString;
+use libc::pid_t;
+use objc::runtime::{BOOL, NO};
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// An application running in the current user session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunningApp {
+    pid: pid_t,
+    bundle_id: Option<String>,
+    path: Option<PathBuf>,
+}
+
+impl RunningApp {
+    pub(crate) fn new(pid: pid_t, bundle_id: Option<String>, path: Option<PathBuf>) -> RunningApp {
+        RunningApp {
+            pid,
+            bundle_id,
+            path,
+        }
+    }
+
+    /// Must be called inside an autorelease pool
+    pub(crate) unsafe fn from_ns(app: Id) -> RunningApp {
+        let bundle_url: Id = msg_send![app, bundleURL];
+        let path = if bundle_url.is_null() {
+            let executable_url: Id = msg_send![app, executableURL];
+            to_path(executable_url)
+        } else {
+            to_path(bundle_url)
+        };
+
+        RunningApp {
+            pid: msg_send![app, processIdentifier],
+            bundle_id: to_string(msg_send![app, bundleIdentifier]),
+            path,
+        }
+    }
+
+    /// The process identifier
+    pub fn pid(&self) -> pid_t {
+        self.pid
+    }
+
+    /// The bundle identifier, if the app has one
+    pub fn bundle_id(&self) -> Option<&str> {
+        self.bundle_id.as_ref().map(String::as_str)
+    }
+
+    /// The path of the app bundle, or of the executable for apps without bundle
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_ref().map(PathBuf::as_path)
+    }
+
+    fn name(&self) -> String {
+        match (&self.bundle_id, &self.path) {
+            (Some(bundle_id), _) => bundle_id.clone(),
+            (None, Some(path)) => path.display().to_string(),
+            (None, None) => self.pid.to_string(),
+        }
+    }
+
+    /// Must be called inside an autorelease pool
+    unsafe fn ns_app(&self) -> OpenResult<Id> {
+        let app: Id = msg_send![
+            class!(NSRunningApplication),
+            runningApplicationWithProcessIdentifier: self.pid
+        ];
+        if app.is_null() {
+            Err(OpenError::NotRunning(self.name()))
+        } else {
+            Ok(app)
+        }
+    }
+
+    /// Check if the process is still alive
+    pub fn is_running(&self) -> bool {
+        let res = unsafe { libc::kill(self.pid, 0) };
+        res == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+
+    /// Block until the process exits
+    pub fn wait(&self) {
+        while self.is_running() {
+            std::thread::sleep(WAIT_POLL_INTERVAL);
+        }
+    }
+
+    /// Block until the process exits, giving up after the timeout (`OpenError::TimedOut`) or
+    /// when the token is cancelled (`OpenError::Cancelled`)
+    pub fn wait_timeout(
+        &self,
+        timeout: Option<Duration>,
+        cancel: Option<&CancelToken>,
+    ) -> OpenResult<()> {
+        self.wait_with(&Deadline::new(timeout, cancel))
+    }
+
+    pub(crate) fn wait_with(&self, deadline: &Deadline) -> OpenResult<()> {
+        while self.is_running() {
+            deadline.check()?;
+            std::thread::sleep(deadline.step(WAIT_POLL_INTERVAL));
+        }
+        Ok(())
+    }
+
+    /// Bring all the windows of the app to the front
+    pub fn activate(&self) -> OpenResult<()> {
+        let _pool = AutoreleasePool::new();
+        let app = unsafe { self.ns_app()? };
+
+        let activated: BOOL = unsafe {
+            msg_send![
+                app,
+                activateWithOptions: NS_APPLICATION_ACTIVATE_ALL_WINDOWS
+                    | NS_APPLICATION_ACTIVATE_IGNORING_OTHER_APPS
+            ]
+        };
+        if activated == NO {
+            Err(OpenError::ActivationFailed(self.name()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Ask the app to quit with a quit Apple Event, like choosing Quit from its menu (the
+    /// app may still ask to save documents), or kill it with SIGKILL when `force` is true. A
+    /// process which is gone fails with `OpenError::NotRunning`, one which can't be signalled
+    /// (e.g. owned by another user) with the `OpenError::Io` of the system error.
+    pub fn terminate(&self, force: bool) -> OpenResult<()> {
+        if force {
+            if unsafe { libc::kill(self.pid, libc::SIGKILL) } == 0 {
+                return Ok(());
+            }
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::ESRCH) {
+                Err(OpenError::NotRunning(self.name()))
+            } else {
+                Err(OpenError::Io(err))
+            }
+        } else {
+            let _pool = AutoreleasePool::new();
+            let app = unsafe { self.ns_app()? };
+            let terminated: BOOL = unsafe { msg_send![app, terminate] };
+            if terminated == NO {
+                Err(OpenError::TerminationFailed(self.name()))
+            } else {
+                Ok(())
+            }
+        }
+    }
+}
+
+const WAIT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+const NS_APPLICATION_ACTIVATE_ALL_WINDOWS: usize = 1 << 0;
+const NS_APPLICATION_ACTIVATE_IGNORING_OTHER_APPS: usize = 1 << 1;
+
+/// Get all the applications running in the current user session.
+///
+/// The list is maintained by AppKit on the main run loop: processes not running it get a
+/// snapshot taken on first use.
+pub fn running_apps() -> Vec<RunningApp> {
+    let _pool = AutoreleasePool::new();
+    let apps: Id = unsafe { msg_send![shared_workspace(), runningApplications] };
+    array_iter(apps)
+        .map(|app| unsafe { RunningApp::from_ns(app) })
+        .collect()
+}
+
+/// Get all the running instances of the app with the given bundle identifier
+pub fn running_apps_for_bundle_id(bundle_id: &str) -> Vec<RunningApp> {
+    let _pool = AutoreleasePool::new();
+    let bundle_id = CFString::new(bundle_id);
+    let apps: Id = unsafe {
+        msg_send![
+            class!(NSRunningApplication),
+            runningApplicationsWithBundleIdentifier: nsstring(&bundle_id)
+        ]
+    };
+    array_iter(apps)
+        .map(|app| unsafe { RunningApp::from_ns(app) })
+        .collect()
+}
+
+/// Get the app running with the given process identifier
+pub(crate) fn running_app_for_pid(pid: pid_t) -> Option<RunningApp> {
+    let _pool = AutoreleasePool::new();
+    let app: Id = unsafe {
+        msg_send![
+            class!(NSRunningApplication),
+            runningApplicationWithProcessIdentifier: pid
+        ]
+    };
+    if app.is_null() {
+        None
+    } else {
+        Some(unsafe { RunningApp::from_ns(app) })
+    }
+}
+
+/// Check if at least one instance of the app with the given bundle identifier is running
+pub fn is_app_running(bundle_id: &str) -> bool {
+    !running_apps_for_bundle_id(bundle_id).is_empty()
+}
+
+/// Get the app receiving key events
+pub fn frontmost_app() -> Option<RunningApp> {
+    let _pool = AutoreleasePool::new();
+    let app: Id = unsafe { msg_send![shared_workspace(), frontmostApplication] };
+    if app.is_null() {
+        None
+    } else {
+        Some(unsafe { RunningApp::from_ns(app) })
+    }
+}
+
+fn is_path(app: &str) -> bool {
+    app.contains('/') || app.ends_with(".app")
+}
+
+/// Find a running instance of the app, given its bundle identifier or its path
+fn find_running_app(app: &str) -> Option<RunningApp> {
+    if is_path(app) {
+        let path = Path::new(app);
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        running_apps()
+            .into_iter()
+            .find(|running| running.path() == Some(path.as_path()))
+    } else {
+        running_apps_for_bundle_id(app).into_iter().next()
+    }
+}
+
+/// Bring the windows of an already running app to the front, without relaunching it. The app
+/// is given by bundle identifier or by path; if it isn't running it's launched when
+/// `launch_if_needed` is true, otherwise `OpenError::NotRunning` is returned.
+pub fn activate(app: &str, launch_if_needed: bool) -> OpenResult<()> {
+    if let Some(running) = find_running_app(app) {
+        return running.activate();
+    }
+
+    if !launch_if_needed {
+        return Err(OpenError::NotRunning(app.to_string()));
+    }
+
+    let path = if is_path(app) {
+        PathBuf::from(app)
+    } else {
+        app_for_bundle_id(app).ok_or_else(|| OpenError::AppNotFound(app.to_string()))?
+    };
+    let mut options = Opener::global().options().clone();
+    options.app(path).launch().map(|_| ())
+}
+
+/// Quit every running instance of the app with the given bundle identifier (see
+/// `RunningApp::terminate`). Every instance is asked even if some fail, the first failure is
+/// returned.
+pub fn quit(bundle_id: &str, force: bool) -> OpenResult<()> {
+    let apps = running_apps_for_bundle_id(bundle_id);
+    if apps.is_empty() {
+        return Err(OpenError::NotRunning(bundle_id.to_string()));
+    }
+
+    let mut res = Ok(());
+    for app in &apps {
+        let terminated = app.terminate(force);
+        if res.is_ok() {
+            res = terminated;
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macos::OpenOptions;
+
+    #[test]
+    fn test_finder_is_running() {
+        assert!(is_app_running("com.apple.finder"));
+        assert!(running_apps()
+            .iter()
+            .any(|app| app.bundle_id() == Some("com.apple.finder")));
+        assert!(!is_app_running("com.example.not-installed"));
+    }
+
+    #[test]
+    fn test_activate() {
+        assert!(activate("com.apple.finder", false).is_ok());
+        assert!(activate("/System/Library/CoreServices/Finder.app", false).is_ok());
+        match activate("com.example.not-installed", false) {
+            Err(OpenError::NotRunning(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_quit() {
+        let app = OpenOptions::new()
+            .app("/System/Applications/Calculator.app")
+            .launch()
+            .unwrap();
+        assert!(app.terminate(false).is_ok());
+        match quit("com.example.not-installed", true) {
+            Err(OpenError::NotRunning(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // a process which already exited and was reaped
+        let pid = std::process::Command::new("/usr/bin/true")
+            .spawn()
+            .and_then(|mut child| child.wait().map(|_| child.id()))
+            .unwrap();
+        match RunningApp::new(pid as pid_t, None, None).terminate(true) {
+            Err(OpenError::NotRunning(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_frontmost_app() {
+        assert!(frontmost_app().is_some());
+    }
+}
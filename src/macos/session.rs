@@ -0,0 +1,248 @@
+//! Save a set of opened documents with their apps and reopen them later, like a session
+//! restore.
+
+use crate::macos::{
+    bookmark_for_path, resolve_bookmark, LaunchedApp, OpenError, OpenOptions, OpenResult,
+    OpenTarget, Opener,
+};
+
+use std::path::{Path, PathBuf};
+
+/// What a `WorkspaceItem` opens
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SavedTarget {
+    /// A url which isn't a file
+    Url(String),
+    /// A security-scoped bookmark of a file, still found if it's moved or renamed
+    Bookmark(Vec<u8>),
+}
+
+/// A document of a `Workspace`, with the app and the options it's opened with
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorkspaceItem {
+    target: SavedTarget,
+    handler: Option<PathBuf>,
+    #[cfg_attr(feature = "serde", serde(with = "persisted"))]
+    options: OpenOptions,
+}
+
+/// The options without what runs code or overwrites files when a workspace saved elsewhere is
+/// loaded: the arguments, the environment (e.g. `DYLD_INSERT_LIBRARIES`) and the redirections
+#[cfg(feature = "serde")]
+fn persisted_options(options: &OpenOptions) -> OpenOptions {
+    let mut options = options.clone();
+    options.args.clear();
+    options.env.clear();
+    options.stdin = None;
+    options.stdout = None;
+    options.stderr = None;
+    options
+}
+
+/// The options of the items as they are saved and loaded
+#[cfg(feature = "serde")]
+mod persisted {
+    use super::persisted_options;
+    use crate::macos::OpenOptions;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        options: &OpenOptions,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        persisted_options(options).serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<OpenOptions, D::Error> {
+        OpenOptions::deserialize(deserializer).map(|options| persisted_options(&options))
+    }
+}
+
+impl WorkspaceItem {
+    /// What the item opens
+    pub fn target(&self) -> &SavedTarget {
+        &self.target
+    }
+
+    /// The app opening the item, the default handler (or the app of the options) if none
+    pub fn handler(&self) -> Option<&Path> {
+        self.handler.as_deref()
+    }
+
+    /// The options the item is opened with
+    pub fn options(&self) -> &OpenOptions {
+        &self.options
+    }
+
+    /// Open the item, keeping access to bookmarked files until the launch is done
+    fn reopen(&self) -> OpenResult<LaunchedApp> {
+        let mut options = self.options.clone();
+        // the policy isn't saved, the one of the global opener applies
+        if options.policy.is_none() {
+            options.policy = Opener::global().policy().cloned();
+        }
+        if let Some(ref handler) = self.handler {
+            options.app(handler);
+        }
+        match self.target {
+            SavedTarget::Url(ref url) => options.open(&OpenTarget::Url(url)),
+            SavedTarget::Bookmark(ref data) => {
+                let scope = resolve_bookmark(data)?;
+                let path = scope.path().ok_or(OpenError::InvalidUrl)?;
+                options.open(&OpenTarget::Path(&path))
+            }
+        }
+    }
+}
+
+/// A list of documents to reopen together. With the `serde` feature it can be stored and
+/// reopened by a later run, files are saved as security-scoped bookmarks. The arguments, the
+/// environment and the redirections of the options aren't saved, so loading a workspace can't
+/// inject code or overwrite files, but it still opens any app and url it lists: only load
+/// workspaces from trusted locations.
+///
+/// ```no_run
+/// use macos_open::{OpenOptions, OpenTarget, Workspace};
+/// use std::path::Path;
+///
+/// let options = OpenOptions::new();
+/// let workspace = Workspace::capture(vec![
+///     (OpenTarget::Url("https://www.example.com/"), None, &options),
+///     (
+///         OpenTarget::Path(Path::new("/etc/hosts")),
+///         Some(Path::new("/System/Applications/TextEdit.app")),
+///         &options,
+///     ),
+/// ])
+/// .unwrap();
+/// for result in workspace.reopen() {
+///     result.unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Workspace {
+    items: Vec<WorkspaceItem>,
+}
+
+impl Workspace {
+    /// Create an empty workspace
+    pub fn new() -> Workspace {
+        Workspace::default()
+    }
+
+    /// Save the targets with the apps (the default handler if none) and the options they are
+    /// opened with
+    pub fn capture<'a, I>(entries: I) -> OpenResult<Workspace>
+    where
+        I: IntoIterator<Item = (OpenTarget<'a>, Option<&'a Path>, &'a OpenOptions)>,
+    {
+        let mut workspace = Workspace::new();
+        for (target, handler, options) in entries {
+            workspace.push(target, handler, options)?;
+        }
+        Ok(workspace)
+    }
+
+    /// Add a target, failing if it isn't valid or a file can't be bookmarked
+    pub fn push(
+        &mut self,
+        target: OpenTarget,
+        handler: Option<&Path>,
+        options: &OpenOptions,
+    ) -> OpenResult<&mut Workspace> {
+        let url = target.to_url(false)?;
+        let target = match url.to_path() {
+            Some(path) => SavedTarget::Bookmark(bookmark_for_path(&path)?),
+            None => SavedTarget::Url(url.get_string().to_string()),
+        };
+        self.items.push(WorkspaceItem {
+            target,
+            handler: handler.map(Path::to_path_buf),
+            options: options.clone(),
+        });
+        Ok(self)
+    }
+
+    /// The saved items, in order
+    pub fn items(&self) -> &[WorkspaceItem] {
+        &self.items
+    }
+
+    /// Open every item in order, a failure doesn't stop the others. The results are in the
+    /// order of the items.
+    pub fn reopen(&self) -> Vec<OpenResult<LaunchedApp>> {
+        self.items.iter().map(WorkspaceItem::reopen).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workspace_capture() {
+        let options = OpenOptions::new();
+        let textedit = Path::new("/System/Applications/TextEdit.app");
+        let workspace = Workspace::capture(vec![
+            (OpenTarget::Url("https://www.example.com/"), None, &options),
+            (
+                OpenTarget::Path(Path::new("/etc/hosts")),
+                Some(textedit),
+                &options,
+            ),
+        ])
+        .unwrap();
+
+        let items = workspace.items();
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            items[0].target(),
+            &SavedTarget::Url("https://www.example.com/".to_string())
+        );
+        assert_eq!(items[0].handler(), None);
+        match items[1].target() {
+            SavedTarget::Bookmark(data) => assert!(!data.is_empty()),
+            other => panic!("unexpected target {:?}", other),
+        }
+        assert_eq!(items[1].handler(), Some(textedit));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_workspace_serde() {
+        use crate::macos::Redirect;
+
+        let mut options = OpenOptions::new();
+        options
+            .arg("--verbose")
+            .env("DYLD_INSERT_LIBRARIES", "/tmp/inject.dylib")
+            .stdout(Redirect::File(PathBuf::from("/tmp/clobbered")))
+            .new_instance(true);
+        let workspace = Workspace::capture(vec![(
+            OpenTarget::Url("https://www.example.com/"),
+            None,
+            &options,
+        )])
+        .unwrap();
+        assert_eq!(workspace.items()[0].options().args, vec!["--verbose"]);
+
+        let json = serde_json::to_string(&workspace).unwrap();
+        assert!(!json.contains("DYLD_INSERT_LIBRARIES"));
+        assert!(!json.contains("/tmp/clobbered"));
+
+        // a workspace written by hand can't set them either
+        let json = json.replace("\"args\":[]", "\"args\":[\"-P\"]");
+        let loaded: Workspace = serde_json::from_str(&json).unwrap();
+        let options = loaded.items()[0].options();
+        assert!(options.args.is_empty());
+        assert!(options.env.is_empty());
+        assert!(options.stdout.is_none());
+        assert!(options.new_instance);
+    }
+}
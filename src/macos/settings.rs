@@ -0,0 +1,194 @@
+//! Deep links to System Settings (System Preferences before macOS 13) panes.
+
+use crate::macos::os::macos_version;
+use crate::macos::{open_default, OpenResult};
+
+use std::path::PathBuf;
+
+/// A Privacy & Security category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrivacyPane {
+    /// Apps allowed to use the Accessibility APIs
+    Accessibility,
+    /// Apps allowed to send Apple Events to other apps
+    Automation,
+    /// Apps allowed to read calendars
+    Calendars,
+    /// Apps allowed to use the camera
+    Camera,
+    /// Apps allowed to read contacts
+    Contacts,
+    /// Apps allowed to access user folders
+    FilesAndFolders,
+    /// Apps allowed to read any file
+    FullDiskAccess,
+    /// Apps allowed to monitor keyboard input
+    InputMonitoring,
+    /// Apps allowed to get the location
+    LocationServices,
+    /// Apps allowed to use the microphone
+    Microphone,
+    /// Apps allowed to read the photo library
+    Photos,
+    /// Apps allowed to record the screen
+    ScreenRecording,
+}
+
+impl PrivacyPane {
+    fn anchor(self) -> &'static str {
+        match self {
+            PrivacyPane::Accessibility => "Privacy_Accessibility",
+            PrivacyPane::Automation => "Privacy_Automation",
+            PrivacyPane::Calendars => "Privacy_Calendars",
+            PrivacyPane::Camera => "Privacy_Camera",
+            PrivacyPane::Contacts => "Privacy_Contacts",
+            PrivacyPane::FilesAndFolders => "Privacy_FilesAndFolders",
+            PrivacyPane::FullDiskAccess => "Privacy_AllFiles",
+            PrivacyPane::InputMonitoring => "Privacy_ListenEvent",
+            PrivacyPane::LocationServices => "Privacy_LocationServices",
+            PrivacyPane::Microphone => "Privacy_Microphone",
+            PrivacyPane::Photos => "Privacy_Photos",
+            PrivacyPane::ScreenRecording => "Privacy_ScreenCapture",
+        }
+    }
+}
+
+/// A System Settings pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SettingsPane {
+    /// Bluetooth devices
+    Bluetooth,
+    /// Displays arrangement and resolution
+    Displays,
+    /// Dock (Desktop & Dock since macOS 13)
+    Dock,
+    /// Keyboard
+    Keyboard,
+    /// Apps opened at login
+    LoginItems,
+    /// Network interfaces
+    Network,
+    /// Notifications
+    Notifications,
+    /// A Privacy & Security category
+    Privacy(PrivacyPane),
+    /// General security settings, where blocked apps can be allowed
+    Security,
+    /// Sharing services
+    Sharing,
+    /// Software Update
+    SoftwareUpdate,
+    /// Sound input and output
+    Sound,
+    /// Users & Groups
+    Users,
+}
+
+const SCHEME: &str = "x-apple.systempreferences:";
+
+impl SettingsPane {
+    /// The URL opening this pane on the given macOS major version
+    pub fn url_for(self, major_version: u32) -> String {
+        if major_version >= 13 {
+            self.settings_url()
+        } else {
+            self.preferences_url()
+        }
+    }
+
+    /// The URL opening this pane on the running macOS
+    pub fn url(self) -> String {
+        self.url_for(
+            macos_version()
+                .map(|version| version.major() as u32)
+                .unwrap_or(10),
+        )
+    }
+
+    /// System Settings, macOS 13 and later
+    fn settings_url(self) -> String {
+        let pane = match self {
+            SettingsPane::Bluetooth => "com.apple.BluetoothSettings",
+            SettingsPane::Displays => "com.apple.Displays-Settings.extension",
+            SettingsPane::Dock => "com.apple.Desktop-Settings.extension",
+            SettingsPane::Keyboard => "com.apple.Keyboard-Settings.extension",
+            SettingsPane::LoginItems => "com.apple.LoginItems-Settings.extension",
+            SettingsPane::Network => "com.apple.Network-Settings.extension",
+            SettingsPane::Notifications => "com.apple.Notifications-Settings.extension",
+            SettingsPane::Privacy(privacy) => {
+                return format!(
+                    "{}com.apple.settings.PrivacySecurity.extension?{}",
+                    SCHEME,
+                    privacy.anchor()
+                )
+            }
+            SettingsPane::Security => "com.apple.settings.PrivacySecurity.extension?Security",
+            SettingsPane::Sharing => "com.apple.Sharing-Settings.extension",
+            SettingsPane::SoftwareUpdate => "com.apple.Software-Update-Settings.extension",
+            SettingsPane::Sound => "com.apple.Sound-Settings.extension",
+            SettingsPane::Users => "com.apple.Users-Groups-Settings.extension",
+        };
+        format!("{}{}", SCHEME, pane)
+    }
+
+    /// System Preferences, macOS 12 and earlier
+    fn preferences_url(self) -> String {
+        let pane = match self {
+            SettingsPane::Bluetooth => "com.apple.preferences.Bluetooth",
+            SettingsPane::Displays => "com.apple.preference.displays",
+            SettingsPane::Dock => "com.apple.preference.dock",
+            SettingsPane::Keyboard => "com.apple.preference.keyboard",
+            SettingsPane::LoginItems => "com.apple.preferences.users",
+            SettingsPane::Network => "com.apple.preference.network",
+            SettingsPane::Notifications => "com.apple.preference.notifications",
+            SettingsPane::Privacy(privacy) => {
+                return format!(
+                    "{}com.apple.preference.security?{}",
+                    SCHEME,
+                    privacy.anchor()
+                )
+            }
+            SettingsPane::Security => "com.apple.preference.security?General",
+            SettingsPane::Sharing => "com.apple.preferences.sharing",
+            SettingsPane::SoftwareUpdate => "com.apple.preferences.softwareupdate",
+            SettingsPane::Sound => "com.apple.preference.sound",
+            SettingsPane::Users => "com.apple.preferences.users",
+        };
+        format!("{}{}", SCHEME, pane)
+    }
+}
+
+/// Open a System Settings pane
+pub fn open_settings_pane(pane: SettingsPane) -> OpenResult<Option<PathBuf>> {
+    open_default(&pane.url())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_pane_url() {
+        assert_eq!(
+            SettingsPane::Privacy(PrivacyPane::ScreenRecording).url_for(12),
+            "x-apple.systempreferences:com.apple.preference.security?Privacy_ScreenCapture"
+        );
+        assert_eq!(
+            SettingsPane::Privacy(PrivacyPane::ScreenRecording).url_for(13),
+            "x-apple.systempreferences:com.apple.settings.PrivacySecurity.extension?Privacy_ScreenCapture"
+        );
+        assert_eq!(
+            SettingsPane::Notifications.url_for(10),
+            "x-apple.systempreferences:com.apple.preference.notifications"
+        );
+        assert_eq!(
+            SettingsPane::Notifications.url_for(14),
+            "x-apple.systempreferences:com.apple.Notifications-Settings.extension"
+        );
+    }
+
+    #[test]
+    fn test_open_settings_pane() {
+        assert!(open_settings_pane(SettingsPane::Network).is_ok());
+    }
+}
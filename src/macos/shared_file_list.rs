@@ -0,0 +1,157 @@
+//! Minimal wrapper of the Launch Services shared file lists.
+
+use crate::macos::{OpenError, OpenResult};
+
+use core_foundation::array::{CFArray, CFArrayRef};
+use core_foundation::base::{CFAllocatorRef, CFType, CFTypeRef, TCFType};
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::error::CFErrorRef;
+use core_foundation::string::CFStringRef;
+use core_foundation::url::{CFURLRef, CFURL};
+use core_foundation_sys::base::{kCFAllocatorDefault, CFRelease, OSStatus};
+use libc::c_void;
+
+use std::path::PathBuf;
+
+type LSSharedFileListRef = CFTypeRef;
+type LSSharedFileListItemRef = CFTypeRef;
+
+const K_LS_SHARED_FILE_LIST_NO_USER_INTERACTION: u32 = 1 << 0;
+const K_LS_SHARED_FILE_LIST_DO_NOT_MOUNT_VOLUMES: u32 = 1 << 1;
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    pub(crate) static kLSSharedFileListSessionLoginItems: CFStringRef;
+    pub(crate) static kLSSharedFileListLoginItemHidden: CFStringRef;
+    static kLSSharedFileListItemLast: LSSharedFileListItemRef;
+
+    fn LSSharedFileListCreate(
+        inAllocator: CFAllocatorRef,
+        inListType: CFStringRef,
+        listOptions: CFTypeRef,
+    ) -> LSSharedFileListRef;
+    fn LSSharedFileListCopySnapshot(
+        inList: LSSharedFileListRef,
+        outSnapshotSeed: *mut u32,
+    ) -> CFArrayRef;
+    fn LSSharedFileListItemCopyResolvedURL(
+        inItem: LSSharedFileListItemRef,
+        inFlags: u32,
+        outError: *mut CFErrorRef,
+    ) -> CFURLRef;
+    fn LSSharedFileListInsertItemURL(
+        inList: LSSharedFileListRef,
+        insertAfterThisItem: LSSharedFileListItemRef,
+        inDisplayName: CFStringRef,
+        inIconRef: *const c_void,
+        inURL: CFURLRef,
+        inPropertiesToSet: CFDictionaryRef,
+        inPropertiesToClear: CFArrayRef,
+    ) -> LSSharedFileListItemRef;
+    fn LSSharedFileListItemRemove(
+        inList: LSSharedFileListRef,
+        inItem: LSSharedFileListItemRef,
+    ) -> OSStatus;
+}
+
+/// An item of a shared file list, with its resolved url
+pub(crate) struct Item {
+    item: CFType,
+    url: Option<CFURL>,
+}
+
+impl Item {
+    pub(crate) fn path(&self) -> Option<PathBuf> {
+        self.url.as_ref().and_then(CFURL::to_path)
+    }
+}
+
+/// A shared file list, released when dropped
+pub(crate) struct SharedFileList(LSSharedFileListRef);
+
+impl Drop for SharedFileList {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.0) };
+    }
+}
+
+fn check(code: OSStatus) -> OpenResult<()> {
+    match code {
+        0 => Ok(()),
+        code => Err(OpenError::Status(code)),
+    }
+}
+
+impl SharedFileList {
+    /// Open the list of the given kind, one of the `kLSSharedFileList*Items` constants
+    pub(crate) fn new(kind: CFStringRef) -> OpenResult<SharedFileList> {
+        let list = unsafe { LSSharedFileListCreate(kCFAllocatorDefault, kind, std::ptr::null()) };
+        if list.is_null() {
+            Err(OpenError::Unsupported("shared file list is not available"))
+        } else {
+            Ok(SharedFileList(list))
+        }
+    }
+
+    /// The items of the list, without prompting the user nor mounting volumes to resolve them
+    pub(crate) fn items(&self) -> Vec<Item> {
+        let mut seed = 0;
+        let snapshot = unsafe { LSSharedFileListCopySnapshot(self.0, &mut seed) };
+        if snapshot.is_null() {
+            return Vec::new();
+        }
+        let snapshot: CFArray = unsafe { CFArray::wrap_under_create_rule(snapshot) };
+
+        snapshot
+            .iter()
+            .map(|item| {
+                let item = unsafe { CFType::wrap_under_get_rule(*item) };
+                let url = unsafe {
+                    LSSharedFileListItemCopyResolvedURL(
+                        item.as_CFTypeRef(),
+                        K_LS_SHARED_FILE_LIST_NO_USER_INTERACTION
+                            | K_LS_SHARED_FILE_LIST_DO_NOT_MOUNT_VOLUMES,
+                        std::ptr::null_mut(),
+                    )
+                };
+                let url = if url.is_null() {
+                    None
+                } else {
+                    Some(unsafe { CFURL::wrap_under_create_rule(url) })
+                };
+                Item { item, url }
+            })
+            .collect()
+    }
+
+    /// Append the url at the end of the list, with the given item properties
+    pub(crate) fn insert<K, V>(
+        &self,
+        url: &CFURL,
+        properties: &CFDictionary<K, V>,
+    ) -> OpenResult<()> {
+        let item = unsafe {
+            LSSharedFileListInsertItemURL(
+                self.0,
+                kLSSharedFileListItemLast,
+                std::ptr::null(),
+                std::ptr::null(),
+                url.as_concrete_TypeRef(),
+                properties.as_concrete_TypeRef(),
+                std::ptr::null(),
+            )
+        };
+        if item.is_null() {
+            Err(OpenError::Unsupported(
+                "the item can't be added to the list",
+            ))
+        } else {
+            unsafe { CFRelease(item) };
+            Ok(())
+        }
+    }
+
+    pub(crate) fn remove(&self, item: &Item) -> OpenResult<()> {
+        check(unsafe { LSSharedFileListItemRemove(self.0, item.item.as_CFTypeRef()) })
+    }
+}
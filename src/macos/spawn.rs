@@ -0,0 +1,223 @@
+//! Direct launches of the bundle executable, used when Launch Services can't honour the
+//! requested options.
+
+use crate::macos::bundle::executable_path;
+use crate::macos::launch::CHECK_IN_TIMEOUT;
+use crate::macos::{Backend, LaunchedApp, OpenError, OpenOptions, OpenResult, Redirect};
+
+use core_foundation::array::CFArray;
+use core_foundation::url::CFURL;
+use libc::{
+    c_char, c_int, fcntl, pid_t, pipe, posix_spawn, posix_spawn_file_actions_adddup2,
+    posix_spawn_file_actions_addopen, posix_spawn_file_actions_destroy,
+    posix_spawn_file_actions_init, posix_spawn_file_actions_t, posix_spawnattr_destroy,
+    posix_spawnattr_init, posix_spawnattr_t, size_t, waitpid, FD_CLOEXEC, F_SETFD, O_CREAT,
+    O_RDONLY, O_TRUNC, O_WRONLY,
+};
+
+use std::ffi::{CString, OsStr};
+use std::fs::File;
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::Path;
+use std::thread;
+
+extern "C" {
+    fn posix_spawnattr_setbinpref_np(
+        attr: *mut posix_spawnattr_t,
+        count: size_t,
+        pref: *mut c_int,
+        ocount: *mut size_t,
+    ) -> c_int;
+}
+
+fn cstring<S: AsRef<OsStr>>(value: S) -> OpenResult<CString> {
+    CString::new(value.as_ref().as_bytes()).map_err(|err| OpenError::Io(err.into()))
+}
+
+fn check(code: c_int) -> OpenResult<()> {
+    if code == 0 {
+        Ok(())
+    } else {
+        Err(OpenError::Io(io::Error::from_raw_os_error(code)))
+    }
+}
+
+/// Both ends of a pipe, closed on exec so only the duplicated end reaches the app
+fn cloexec_pipe() -> OpenResult<(File, File)> {
+    let mut fds = [0 as c_int; 2];
+    unsafe {
+        if pipe(fds.as_mut_ptr()) != 0 {
+            return Err(OpenError::Io(io::Error::last_os_error()));
+        }
+        let (read, write) = (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]));
+        for fd in &fds {
+            if fcntl(*fd, F_SETFD, FD_CLOEXEC) != 0 {
+                return Err(OpenError::Io(io::Error::last_os_error()));
+            }
+        }
+        Ok((read, write))
+    }
+}
+
+/// The redirections of a spawn: the child side is registered in the file actions, the parent
+/// side of the pipes is kept to be handed to the `LaunchedApp`
+struct Stdio {
+    actions: posix_spawn_file_actions_t,
+    paths: Vec<CString>,
+    children: Vec<File>,
+    parents: [Option<File>; 3],
+}
+
+impl Stdio {
+    fn new() -> OpenResult<Stdio> {
+        let mut actions: posix_spawn_file_actions_t = unsafe { std::mem::zeroed() };
+        check(unsafe { posix_spawn_file_actions_init(&mut actions) })?;
+        Ok(Stdio {
+            actions,
+            paths: Vec::new(),
+            children: Vec::new(),
+            parents: [None, None, None],
+        })
+    }
+
+    fn redirect(&mut self, fd: c_int, redirect: Option<&Redirect>) -> OpenResult<()> {
+        let read = fd == 0;
+        let path = match redirect {
+            None | Some(Redirect::Inherit) => return Ok(()),
+            Some(Redirect::Null) => Path::new("/dev/null"),
+            Some(Redirect::File(path)) => path.as_path(),
+            Some(Redirect::Piped) => {
+                let (reader, writer) = cloexec_pipe()?;
+                let (child, parent) = if read {
+                    (reader, writer)
+                } else {
+                    (writer, reader)
+                };
+                check(unsafe {
+                    posix_spawn_file_actions_adddup2(&mut self.actions, child.as_raw_fd(), fd)
+                })?;
+                self.children.push(child);
+                self.parents[fd as usize] = Some(parent);
+                return Ok(());
+            }
+        };
+
+        let flags = if read {
+            O_RDONLY
+        } else {
+            O_WRONLY | O_CREAT | O_TRUNC
+        };
+        let path = cstring(path)?;
+        check(unsafe {
+            posix_spawn_file_actions_addopen(&mut self.actions, fd, path.as_ptr(), flags, 0o644)
+        })?;
+        self.paths.push(path);
+        Ok(())
+    }
+}
+
+impl Drop for Stdio {
+    fn drop(&mut self) {
+        unsafe { posix_spawn_file_actions_destroy(&mut self.actions) };
+    }
+}
+
+/// Must be called with an initialized `attr`
+unsafe fn spawn_executable(
+    attr: &mut posix_spawnattr_t,
+    stdio: &Stdio,
+    executable: &CString,
+    argv: &[CString],
+    envp: &[CString],
+    options: &OpenOptions,
+) -> OpenResult<pid_t> {
+    if let Some(arch) = options.arch {
+        let mut pref = arch.cpu_type();
+        let mut ocount: size_t = 0;
+        check(posix_spawnattr_setbinpref_np(attr, 1, &mut pref, &mut ocount))?;
+    }
+
+    let mut argv = argv.iter().map(|arg| arg.as_ptr()).collect::<Vec<_>>();
+    argv.push(std::ptr::null());
+    let mut envp = envp.iter().map(|var| var.as_ptr()).collect::<Vec<_>>();
+    envp.push(std::ptr::null());
+
+    let mut pid: pid_t = 0;
+    check(posix_spawn(
+        &mut pid,
+        executable.as_ptr(),
+        &stdio.actions,
+        attr,
+        argv.as_ptr() as *const *mut c_char,
+        envp.as_ptr() as *const *mut c_char,
+    ))?;
+    Ok(pid)
+}
+
+/// Start the executable of the app with the arguments, environment, architecture and
+/// redirections of the options, then hand the urls to the new instance through Launch Services
+pub(crate) fn spawn(
+    app: &Path,
+    urls: Option<CFArray<CFURL>>,
+    options: &OpenOptions,
+) -> OpenResult<LaunchedApp> {
+    let executable = executable_path(app).ok_or(OpenError::InvalidApp)?;
+    let executable = cstring(&executable)?;
+
+    let mut argv = vec![executable.clone()];
+    for arg in options.arguments() {
+        argv.push(cstring(arg)?);
+    }
+
+    let mut envp = Vec::new();
+    for (key, value) in std::env::vars_os() {
+        if options.env.iter().all(|(k, _)| OsStr::new(k) != key) {
+            let mut var = key;
+            var.push("=");
+            var.push(value);
+            envp.push(cstring(var)?);
+        }
+    }
+    for (key, value) in &options.env {
+        envp.push(cstring(format!("{}={}", key, value))?);
+    }
+
+    let mut stdio = Stdio::new()?;
+    stdio.redirect(0, options.stdin.as_ref())?;
+    stdio.redirect(1, options.stdout.as_ref())?;
+    stdio.redirect(2, options.stderr.as_ref())?;
+
+    let pid = unsafe {
+        let mut attr: posix_spawnattr_t = std::mem::zeroed();
+        check(posix_spawnattr_init(&mut attr))?;
+        let res = spawn_executable(&mut attr, &stdio, &executable, &argv, &envp, options);
+        posix_spawnattr_destroy(&mut attr);
+        res?
+    };
+
+    // the app owns its side of the pipes now
+    stdio.children.clear();
+    let [stdin, stdout, stderr] = std::mem::replace(&mut stdio.parents, [None, None, None]);
+
+    // reap the child so it doesn't linger as a zombie once it exits
+    thread::spawn(move || {
+        let mut status: c_int = 0;
+        unsafe { waitpid(pid, &mut status, 0) };
+    });
+
+    let launched = LaunchedApp::new(Some(app.to_path_buf()), Some(pid))
+        .with_stdio(stdin, stdout, stderr)
+        .with_backend(Backend::Spawn);
+
+    if let Some(urls) = urls {
+        let running = launched
+            .wait_running(CHECK_IN_TIMEOUT)
+            .ok_or_else(|| OpenError::NotRunning(app.display().to_string()))?;
+        let app = CFURL::from_path(app, true).ok_or(OpenError::InvalidApp)?;
+        options.deliver(app, &running, urls)?;
+    }
+
+    Ok(launched)
+}
@@ -0,0 +1,324 @@
+//! Spotlight queries returning any metadata attribute of the results, not just their paths.
+//! They run on the `file-metadata` query, only reading attributes by name is bound here.
+
+use crate::macos::{invalid_items, MultiOpenable, OpenError, OpenResult, Openable};
+
+use core_foundation::array::CFArray;
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::boolean::CFBoolean;
+use core_foundation::date::CFDate;
+use core_foundation::number::CFNumber;
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation::url::CFURL;
+use file_metadata::mditem::{attributes, MDItem};
+use file_metadata::mdquery::{MDQuery, MDQueryOptionFlags};
+use libc::c_void;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Seconds between the Unix epoch and the Core Foundation one (2001-01-01)
+const CF_EPOCH_OFFSET: f64 = 978_307_200.0;
+
+type MDItemRef = *const c_void;
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn MDItemCopyAttribute(item: MDItemRef, name: CFStringRef) -> CFTypeRef;
+}
+
+/// The name of a Spotlight metadata attribute (e.g. `kMDItemDisplayName`), the common ones are
+/// associated constants
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Attribute(Cow<'static, str>);
+
+impl Attribute {
+    /// The path of the item
+    pub const PATH: Attribute = Attribute(Cow::Borrowed("kMDItemPath"));
+    /// The localized name shown by the Finder
+    pub const DISPLAY_NAME: Attribute = Attribute(Cow::Borrowed("kMDItemDisplayName"));
+    /// The uniform type identifier of the item
+    pub const CONTENT_TYPE: Attribute = Attribute(Cow::Borrowed("kMDItemContentType"));
+    /// The bundle identifier of an app
+    pub const BUNDLE_ID: Attribute = Attribute(Cow::Borrowed("kMDItemCFBundleIdentifier"));
+    /// The version of an app
+    pub const VERSION: Attribute = Attribute(Cow::Borrowed("kMDItemVersion"));
+    /// When the item was last opened
+    pub const LAST_USED_DATE: Attribute = Attribute(Cow::Borrowed("kMDItemLastUsedDate"));
+    /// The size of the file in bytes
+    pub const FS_SIZE: Attribute = Attribute(Cow::Borrowed("kMDItemFSSize"));
+    /// The Finder tags of the item
+    pub const USER_TAGS: Attribute = Attribute(Cow::Borrowed("kMDItemUserTags"));
+
+    /// An attribute by name, for the ones without a constant
+    pub fn new<S: Into<String>>(name: S) -> Attribute {
+        Attribute(Cow::Owned(name.into()))
+    }
+
+    /// The name of the attribute
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The value of a metadata attribute
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MDValue {
+    /// A string, e.g. a name or a path
+    String(String),
+    /// A number, integers included
+    Number(f64),
+    /// A flag
+    Bool(bool),
+    /// A point in time
+    Date(SystemTime),
+    /// A list of values, e.g. the tags or the authors
+    Array(Vec<MDValue>),
+}
+
+impl MDValue {
+    /// The string, if the value is one
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MDValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Convert a Core Foundation value, `None` for the types without a variant
+    fn from_cf(value: &CFType) -> Option<MDValue> {
+        if let Some(string) = value.downcast::<CFString>() {
+            Some(MDValue::String(string.to_string()))
+        } else if let Some(boolean) = value.downcast::<CFBoolean>() {
+            Some(MDValue::Bool(boolean.into()))
+        } else if let Some(number) = value.downcast::<CFNumber>() {
+            number
+                .to_i64()
+                .map(|number| number as f64)
+                .or_else(|| number.to_f64())
+                .map(MDValue::Number)
+        } else if let Some(date) = value.downcast::<CFDate>() {
+            Some(MDValue::Date(system_time(date.abs_time())))
+        } else {
+            value
+                .downcast::<CFArray>()
+                .map(|array| MDValue::from_array(&array))
+        }
+    }
+
+    /// Convert the items of an array, skipping the ones without a variant
+    fn from_array(array: &CFArray) -> MDValue {
+        MDValue::Array(
+            array
+                .iter()
+                .filter_map(|item| {
+                    MDValue::from_cf(&unsafe { CFType::wrap_under_get_rule(*item as CFTypeRef) })
+                })
+                .collect(),
+        )
+    }
+}
+
+/// A result of `spotlight_query`: the path of the item, read whatever the attributes asked, and the
+/// attributes. It's openable by its path, so results can be opened as they are.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueryResult {
+    path: Option<PathBuf>,
+    attributes: HashMap<Attribute, MDValue>,
+}
+
+impl QueryResult {
+    pub(crate) fn new(path: Option<PathBuf>, attributes: HashMap<Attribute, MDValue>) -> Self {
+        QueryResult { path, attributes }
+    }
+
+    /// The path of the item, `None` if Spotlight doesn't know it (e.g. a removed file)
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The value of an attribute, `None` if it wasn't asked or the item doesn't have it
+    pub fn get(&self, attribute: &Attribute) -> Option<&MDValue> {
+        self.attributes.get(attribute)
+    }
+
+    /// The attributes the item has among the asked ones
+    pub fn attributes(&self) -> &HashMap<Attribute, MDValue> {
+        &self.attributes
+    }
+
+    /// Take the attributes
+    pub fn into_attributes(self) -> HashMap<Attribute, MDValue> {
+        self.attributes
+    }
+}
+
+impl Openable for QueryResult {
+    fn into_openable(&self) -> Option<CFURL> {
+        Openable::into_openable(self.path()?)
+    }
+}
+
+impl MultiOpenable for [QueryResult] {
+    fn into_openable(&self) -> Option<CFArray<CFURL>> {
+        let urls = self
+            .iter()
+            .map(Openable::into_openable)
+            .collect::<Option<Vec<_>>>()?;
+        Some(CFArray::<CFURL>::from_CFTypes(&urls))
+    }
+
+    fn invalid_items(&self) -> Vec<(usize, String)> {
+        invalid_items(self.iter())
+    }
+}
+
+impl MultiOpenable for Vec<QueryResult> {
+    fn into_openable(&self) -> Option<CFArray<CFURL>> {
+        MultiOpenable::into_openable(&self[..])
+    }
+
+    fn invalid_items(&self) -> Vec<(usize, String)> {
+        invalid_items(self.iter())
+    }
+}
+
+impl MultiOpenable for QueryResult {
+    fn into_openable(&self) -> Option<CFArray<CFURL>> {
+        let url = Openable::into_openable(self)?;
+        Some(CFArray::<CFURL>::from_CFTypes(&[url]))
+    }
+
+    fn invalid_items(&self) -> Vec<(usize, String)> {
+        invalid_items(std::iter::once(self))
+    }
+}
+
+/// The time from seconds since the Core Foundation epoch
+fn system_time(abs_time: f64) -> SystemTime {
+    let unix = abs_time + CF_EPOCH_OFFSET;
+    if unix >= 0.0 {
+        UNIX_EPOCH + Duration::from_secs_f64(unix)
+    } else {
+        UNIX_EPOCH - Duration::from_secs_f64(-unix)
+    }
+}
+
+/// Run the Spotlight query (e.g. `kMDItemContentTypeTree == "public.image"`) synchronously
+/// and read the given attributes of every result in the same pass. Attributes a result
+/// doesn't have are missing from it. The path of every result is read anyway, so the results
+/// can be opened directly.
+///
+/// ```no_run
+/// use macos_open::{spotlight_query, Attribute};
+///
+/// let apps = spotlight_query(
+///     "kMDItemContentTypeTree == \"com.apple.application\"c",
+///     &[Attribute::DISPLAY_NAME, Attribute::VERSION, Attribute::LAST_USED_DATE],
+/// )
+/// .unwrap();
+/// for app in &apps {
+///     println!("{:?}", app.get(&Attribute::DISPLAY_NAME));
+/// }
+///
+/// let pdfs = spotlight_query(
+///     "kMDItemContentType == \"com.adobe.pdf\" && kMDItemFSName == \"*invoice*\"c",
+///     &[],
+/// )
+/// .unwrap();
+/// macos_open::OpenOptions::new().open(&pdfs).unwrap();
+/// ```
+pub fn spotlight_query(
+    query_string: &str,
+    attributes: &[Attribute],
+) -> OpenResult<Vec<QueryResult>> {
+    traced!("spotlight", { query = query_string }, {
+        let names: Vec<CFString> = attributes
+            .iter()
+            .map(|attribute| CFString::new(attribute.name()))
+            .collect();
+
+        Ok(run_query(query_string)?
+            .iter()
+            .map(|item| {
+                let path = item
+                    .get(attributes::Path)
+                    .map(|path| PathBuf::from(path.to_string()));
+                let mut values = HashMap::with_capacity(attributes.len());
+                for (attribute, name) in attributes.iter().zip(&names) {
+                    if let Some(value) = copy_attribute(&item, name) {
+                        values.insert(attribute.clone(), value);
+                    }
+                }
+                QueryResult::new(path, values)
+            })
+            .collect())
+    })
+}
+
+/// Run the Spotlight query synchronously, the results are read from the stopped query
+pub(crate) fn run_query(query_string: &str) -> OpenResult<MDQuery> {
+    let query =
+        MDQuery::new(CFString::new(query_string), None, None).ok_or(OpenError::QueryFailed)?;
+    if !query.execute(MDQueryOptionFlags::SYNC | MDQueryOptionFlags::ALLOW_FS_TRANSLATION) {
+        return Err(OpenError::QueryFailed);
+    }
+    query.stop();
+    Ok(query)
+}
+
+/// Read an attribute of the item by name, `None` if it doesn't have it or it has no variant
+fn copy_attribute(item: &MDItem, name: &CFString) -> Option<MDValue> {
+    let value = unsafe {
+        MDItemCopyAttribute(
+            item.as_concrete_TypeRef() as MDItemRef,
+            name.as_concrete_TypeRef(),
+        )
+    };
+    if value.is_null() {
+        return None;
+    }
+    MDValue::from_cf(&unsafe { CFType::wrap_under_create_rule(value) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_attributes() {
+        let results = spotlight_query(
+            "kMDItemCFBundleIdentifier == \"com.apple.finder\"",
+            &[Attribute::new("kMDItemCFBundleIdentifier")],
+        )
+        .unwrap();
+        assert!(results.iter().any(|result| {
+            result.get(&Attribute::BUNDLE_ID).and_then(MDValue::as_str) == Some("com.apple.finder")
+                && result.path().is_some()
+                && result.get(&Attribute::PATH).is_none()
+        }));
+    }
+
+    #[test]
+    fn test_query_result_openable() {
+        assert!(Openable::into_openable(&QueryResult::default()).is_none());
+        let result = QueryResult::new(Some(PathBuf::from("/etc/hosts")), HashMap::new());
+        let results = vec![result, QueryResult::default()];
+        assert!(MultiOpenable::into_openable(&results[..1]).is_some());
+        assert_eq!(MultiOpenable::invalid_items(&results).len(), 1);
+    }
+
+    #[test]
+    fn test_system_time() {
+        assert_eq!(system_time(-CF_EPOCH_OFFSET), UNIX_EPOCH);
+        assert_eq!(
+            system_time(0.0),
+            UNIX_EPOCH + Duration::from_secs(978_307_200)
+        );
+    }
+}
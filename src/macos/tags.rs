@@ -0,0 +1,147 @@
+//! Finder tags, read and written through the url resource properties and queried with
+//! Spotlight.
+
+use crate::macos::{spotlight_paths, NameMatch, OpenError, OpenResult};
+
+use core_foundation::array::CFArray;
+use core_foundation::base::{CFType, CFTypeRef, TCFType};
+use core_foundation::error::{CFError, CFErrorRef};
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation::url::{CFURLRef, CFURL};
+use core_foundation_sys::base::Boolean;
+
+use std::path::{Path, PathBuf};
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    static kCFURLTagNamesKey: CFStringRef;
+
+    fn CFURLCopyResourcePropertyForKey(
+        url: CFURLRef,
+        key: CFStringRef,
+        propertyValueTypeRefPtr: *mut CFTypeRef,
+        error: *mut CFErrorRef,
+    ) -> Boolean;
+    fn CFURLSetResourcePropertyForKey(
+        url: CFURLRef,
+        key: CFStringRef,
+        propertyValue: CFTypeRef,
+        error: *mut CFErrorRef,
+    ) -> Boolean;
+}
+
+fn tags_error(error: CFErrorRef) -> OpenError {
+    if error.is_null() {
+        return OpenError::Tags {
+            code: 0,
+            message: "unknown error".to_string(),
+        };
+    }
+    let error = unsafe { CFError::wrap_under_create_rule(error) };
+    OpenError::Tags {
+        code: error.code() as i64,
+        message: error.description().to_string(),
+    }
+}
+
+fn file_url(path: &Path) -> OpenResult<CFURL> {
+    CFURL::from_path(path, path.is_dir()).ok_or(OpenError::InvalidUrl)
+}
+
+/// Get the Finder tags of the file at the given path
+pub fn tags_for_path(path: &Path) -> OpenResult<Vec<String>> {
+    let url = file_url(path)?;
+    let mut value: CFTypeRef = std::ptr::null();
+    let mut error: CFErrorRef = std::ptr::null_mut();
+    let ok = unsafe {
+        CFURLCopyResourcePropertyForKey(
+            url.as_concrete_TypeRef(),
+            kCFURLTagNamesKey,
+            &mut value,
+            &mut error,
+        )
+    };
+    if ok == 0 {
+        return Err(tags_error(error));
+    }
+    // files without tags have no value at all
+    if value.is_null() {
+        return Ok(Vec::new());
+    }
+
+    let value = unsafe { CFType::wrap_under_create_rule(value) };
+    Ok(value
+        .downcast::<CFArray>()
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|tag| {
+                    unsafe { CFType::wrap_under_get_rule(*tag) }.downcast::<CFString>()
+                })
+                .map(|tag| tag.to_string())
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Replace the Finder tags of the file at the given path, an empty list removes them all
+pub fn set_tags<S: AsRef<str>>(path: &Path, tags: &[S]) -> OpenResult<()> {
+    let url = file_url(path)?;
+    let tags = tags
+        .iter()
+        .map(|tag| CFString::new(tag.as_ref()))
+        .collect::<Vec<_>>();
+    let tags = CFArray::<CFString>::from_CFTypes(&tags);
+
+    let mut error: CFErrorRef = std::ptr::null_mut();
+    let ok = unsafe {
+        CFURLSetResourcePropertyForKey(
+            url.as_concrete_TypeRef(),
+            kCFURLTagNamesKey,
+            tags.as_CFTypeRef(),
+            &mut error,
+        )
+    };
+    if ok == 0 {
+        Err(tags_error(error))
+    } else {
+        Ok(())
+    }
+}
+
+/// Find the files having the given Finder tag, optionally only the ones inside the given
+/// directory. The match is case insensitive, like in Finder.
+pub fn files_with_tag(tag: &str, scope: Option<&Path>) -> OpenResult<Vec<PathBuf>> {
+    let mut query_string = String::from("kMDItemUserTags == \"");
+    query_string.push_str(&NameMatch::Exact.pattern(tag));
+    query_string.push_str("\"c");
+
+    let paths = spotlight_paths(&query_string)?;
+    Ok(match scope {
+        Some(scope) => paths
+            .into_iter()
+            .filter(|path| path.starts_with(scope))
+            .collect(),
+        None => paths,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags() {
+        let dir = std::env::temp_dir().join("macos-open-test-tags");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("tagged.txt");
+        std::fs::write(&file, b"tagged").unwrap();
+
+        assert_eq!(tags_for_path(&file).unwrap(), Vec::<String>::new());
+        set_tags(&file, &["Urgent", "Work"]).unwrap();
+        assert_eq!(tags_for_path(&file).unwrap(), vec!["Urgent", "Work"]);
+        set_tags::<&str>(&file, &[]).unwrap();
+        assert_eq!(tags_for_path(&file).unwrap(), Vec::<String>::new());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
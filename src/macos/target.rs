@@ -0,0 +1,265 @@
+//! Explicit url and path inputs, for callers which don't want the input kind to be guessed.
+
+use crate::macos::{_url, invalid_items, url, MultiOpenable, OpenError, OpenResult, Openable};
+
+use core_foundation::array::CFArray;
+use core_foundation::url::CFURL;
+use url::Url;
+
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// An input to open, telling how it must be interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpenTarget<'a> {
+    /// A url, handed to Core Foundation as it is without any normalization
+    Url(&'a str),
+    /// A file system path
+    Path(&'a Path),
+    /// Guess between a url and a path, like the `Openable` implementation of strings
+    Auto(&'a str),
+}
+
+impl<'a> OpenTarget<'a> {
+    /// A path given by its raw bytes, for file names which aren't valid UTF-8
+    pub fn from_raw_path_bytes(bytes: &'a [u8]) -> OpenTarget<'a> {
+        OpenTarget::Path(Path::new(OsStr::from_bytes(bytes)))
+    }
+
+    /// Transform the input in a CFURL. In strict mode nothing is guessed: urls must have a
+    /// scheme, paths must be absolute and `Auto` inputs which are both a valid url and an
+    /// existing path fail with `OpenError::Ambiguous`.
+    pub fn to_url(&self, strict: bool) -> OpenResult<CFURL> {
+        match *self {
+            OpenTarget::Url(value) => {
+                if strict && Url::parse(value).is_err() {
+                    return Err(OpenError::InvalidUrl);
+                }
+                _url(value).ok_or(OpenError::InvalidUrl)
+            }
+            OpenTarget::Path(path) => {
+                if strict && path.is_relative() {
+                    return Err(OpenError::InvalidUrl);
+                }
+                Openable::into_openable(path).ok_or(OpenError::InvalidUrl)
+            }
+            OpenTarget::Auto(value) if strict => {
+                let is_url = Url::parse(value).is_ok();
+                let is_path = Path::new(value).exists();
+                match (is_url, is_path) {
+                    (true, true) => Err(OpenError::Ambiguous(value.to_string())),
+                    (true, false) => OpenTarget::Url(value).to_url(true),
+                    (false, true) => OpenTarget::Path(Path::new(value)).to_url(false),
+                    (false, false) => Err(OpenError::InvalidUrl),
+                }
+            }
+            OpenTarget::Auto(value) => url(value).ok_or(OpenError::InvalidUrl),
+        }
+    }
+}
+
+/// An owned `OpenTarget`, what an `Opener` resolver maps its input to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResolvedTarget {
+    /// A url, handed to Core Foundation as it is
+    Url(String),
+    /// A file system path
+    Path(PathBuf),
+    /// Guess between a url and a path, like the `Openable` implementation of strings
+    Auto(String),
+}
+
+impl ResolvedTarget {
+    /// Borrow the target as an input to open
+    pub fn as_target(&self) -> OpenTarget<'_> {
+        match self {
+            ResolvedTarget::Url(url) => OpenTarget::Url(url),
+            ResolvedTarget::Path(path) => OpenTarget::Path(path),
+            ResolvedTarget::Auto(value) => OpenTarget::Auto(value),
+        }
+    }
+}
+
+/// How paths are transformed in file urls, in the same fashion of `OpenOptions`. The defaults
+/// are the ones of the `Openable` implementation of paths.
+///
+/// ```no_run
+/// use macos_open::{open, OpenableOptions};
+/// use std::path::Path;
+///
+/// let url = OpenableOptions::new()
+///     .is_dir(true)
+///     .canonicalize_relative(false)
+///     .to_url(Path::new("build/output"))
+///     .unwrap();
+/// open(&url).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OpenableOptions {
+    is_dir: Option<bool>,
+    resolve_symlinks: bool,
+    canonicalize_relative: bool,
+}
+
+impl Default for OpenableOptions {
+    fn default() -> OpenableOptions {
+        OpenableOptions::new()
+    }
+}
+
+impl OpenableOptions {
+    /// Create the default options: directories are detected by a trailing slash or by
+    /// looking at the file system, symlinks are kept and relative paths are canonicalized
+    pub fn new() -> OpenableOptions {
+        OpenableOptions {
+            is_dir: None,
+            resolve_symlinks: false,
+            canonicalize_relative: true,
+        }
+    }
+
+    /// Tell if the path is a directory instead of looking at the file system, e.g. for paths
+    /// which don't exist yet
+    pub fn is_dir(&mut self, is_dir: bool) -> &mut OpenableOptions {
+        self.is_dir = Some(is_dir);
+        self
+    }
+
+    /// Resolve the symlinks of absolute paths too, the path must exist
+    pub fn resolve_symlinks(&mut self, resolve: bool) -> &mut OpenableOptions {
+        self.resolve_symlinks = resolve;
+        self
+    }
+
+    /// Canonicalize relative paths, which must exist, or just join them to the current
+    /// directory when false
+    pub fn canonicalize_relative(&mut self, canonicalize: bool) -> &mut OpenableOptions {
+        self.canonicalize_relative = canonicalize;
+        self
+    }
+
+    /// Transform the path in a file url with these options
+    pub fn to_url(&self, path: &Path) -> Option<CFURL> {
+        let is_dir = self
+            .is_dir
+            .unwrap_or_else(|| path.as_os_str().as_bytes().ends_with(b"/") || path.is_dir());
+        let path = if self.resolve_symlinks || (path.is_relative() && self.canonicalize_relative) {
+            path.canonicalize().ok()?
+        } else if path.is_relative() {
+            std::env::current_dir().ok()?.join(path)
+        } else {
+            PathBuf::from(path)
+        };
+        CFURL::from_path(&path, is_dir)
+    }
+}
+
+impl<'a> Openable for OpenTarget<'a> {
+    fn into_openable(&self) -> Option<CFURL> {
+        self.to_url(false).ok()
+    }
+}
+
+impl Openable for CFURL {
+    fn into_openable(&self) -> Option<CFURL> {
+        Some(self.clone())
+    }
+}
+
+impl<'a> MultiOpenable for [OpenTarget<'a>] {
+    fn into_openable(&self) -> Option<CFArray<CFURL>> {
+        let urls = self
+            .iter()
+            .map(Openable::into_openable)
+            .collect::<Option<Vec<_>>>()?;
+        Some(CFArray::<CFURL>::from_CFTypes(&urls))
+    }
+
+    fn invalid_items(&self) -> Vec<(usize, String)> {
+        invalid_items(self.iter())
+    }
+}
+
+impl<'a> MultiOpenable for Vec<OpenTarget<'a>> {
+    fn into_openable(&self) -> Option<CFArray<CFURL>> {
+        MultiOpenable::into_openable(&self[..])
+    }
+
+    fn invalid_items(&self) -> Vec<(usize, String)> {
+        invalid_items(self.iter())
+    }
+}
+
+impl<'a> MultiOpenable for OpenTarget<'a> {
+    fn into_openable(&self) -> Option<CFArray<CFURL>> {
+        let url = Openable::into_openable(self)?;
+        Some(CFArray::<CFURL>::from_CFTypes(&[url]))
+    }
+
+    fn invalid_items(&self) -> Vec<(usize, String)> {
+        invalid_items(std::iter::once(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_target_strict() {
+        let url = OpenTarget::Url("myscheme://Host/a%2Fb/../c")
+            .to_url(false)
+            .unwrap();
+        assert_eq!(url.get_string().to_string(), "myscheme://Host/a%2Fb/../c");
+
+        match OpenTarget::Path(Path::new("relative/path")).to_url(true) {
+            Err(OpenError::InvalidUrl) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        match OpenTarget::Auto("surely/not/existing").to_url(true) {
+            Err(OpenError::InvalidUrl) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(OpenTarget::Auto("/tmp").to_url(true).is_ok());
+    }
+
+    #[test]
+    fn test_openable_options() {
+        let url = OpenableOptions::new()
+            .to_url(Path::new("/surely/not/existing/"))
+            .unwrap();
+        assert_eq!(url.get_string().to_string(), "file:///surely/not/existing/");
+
+        assert_eq!(
+            OpenableOptions::new().to_url(Path::new("surely/not/existing")),
+            None
+        );
+        let url = OpenableOptions::new()
+            .canonicalize_relative(false)
+            .is_dir(true)
+            .to_url(Path::new("surely/not/existing"))
+            .unwrap();
+        assert!(url
+            .get_string()
+            .to_string()
+            .ends_with("/surely/not/existing/"));
+    }
+
+    #[test]
+    fn test_open_target_encoding() {
+        let url = OpenTarget::Auto("/surely/not existing/caffè.txt")
+            .to_url(false)
+            .unwrap();
+        assert_eq!(
+            url.get_string().to_string(),
+            "file:///surely/not%20existing/caff%C3%A8.txt"
+        );
+
+        let url = OpenTarget::from_raw_path_bytes(b"/tmp/invalid-\xff")
+            .to_url(true)
+            .unwrap();
+        assert_eq!(url.get_string().to_string(), "file:///tmp/invalid-%FF");
+    }
+}
@@ -0,0 +1,95 @@
+//! Open a terminal window in a given directory.
+
+use crate::macos::{OpenError, OpenResult, Opener};
+
+use std::path::{Path, PathBuf};
+
+/// Terminal emulators which can be opened in a directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TerminalApp {
+    /// Terminal.app
+    Terminal,
+    /// iTerm2
+    ITerm2,
+    /// Alacritty
+    Alacritty,
+    /// kitty
+    Kitty,
+    /// WezTerm
+    WezTerm,
+}
+
+impl TerminalApp {
+    /// The bundle identifier of the app
+    pub fn bundle_id(self) -> &'static str {
+        match self {
+            TerminalApp::Terminal => "com.apple.Terminal",
+            TerminalApp::ITerm2 => "com.googlecode.iterm2",
+            TerminalApp::Alacritty => "org.alacritty",
+            TerminalApp::Kitty => "net.kovidgoyal.kitty",
+            TerminalApp::WezTerm => "com.github.wez.wezterm",
+        }
+    }
+
+    /// The arguments starting a new instance in the directory, `None` if the app opens a
+    /// window in a directory handed to it like a document
+    fn arguments(self, dir: &Path) -> Option<Vec<String>> {
+        let dir = dir.to_string_lossy().into_owned();
+        match self {
+            TerminalApp::Terminal | TerminalApp::ITerm2 => None,
+            TerminalApp::Alacritty => Some(vec!["--working-directory".to_string(), dir]),
+            TerminalApp::Kitty => Some(vec!["--directory".to_string(), dir]),
+            TerminalApp::WezTerm => Some(vec!["start".to_string(), "--cwd".to_string(), dir]),
+        }
+    }
+}
+
+/// Open a new window of the terminal with the shell in the given directory. Terminal.app and
+/// iTerm2 receive the directory like `open -a`, the others are started with the option setting
+/// their working directory.
+pub fn open_terminal_at(dir: &Path, app: TerminalApp) -> OpenResult<Option<PathBuf>> {
+    let dir = dir.canonicalize().map_err(|_| OpenError::InvalidUrl)?;
+    if !dir.is_dir() {
+        return Err(OpenError::InvalidUrl);
+    }
+    let path = Opener::global()
+        .app_for_bundle_id(app.bundle_id())
+        .ok_or_else(|| OpenError::AppNotFound(app.bundle_id().to_string()))?;
+
+    let mut options = Opener::global().options().clone();
+    options.app(&path);
+    match app.arguments(&dir) {
+        Some(args) => {
+            // the app doesn't reuse its running instance for a new directory
+            Ok(options.new_instance(true).args(args).launch()?.into_path())
+        }
+        None => Ok(options.open(&dir)?.into_path()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terminal_arguments() {
+        let dir = Path::new("/tmp/my dir");
+        assert_eq!(TerminalApp::Terminal.arguments(dir), None);
+        assert_eq!(
+            TerminalApp::Alacritty.arguments(dir),
+            Some(vec![
+                "--working-directory".to_string(),
+                "/tmp/my dir".to_string()
+            ])
+        );
+        assert_eq!(
+            TerminalApp::WezTerm.arguments(dir),
+            Some(vec![
+                "start".to_string(),
+                "--cwd".to_string(),
+                "/tmp/my dir".to_string()
+            ])
+        );
+    }
+}
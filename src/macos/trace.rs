@@ -0,0 +1,44 @@
+//! Optional instrumentation through `tracing`. Without the `tracing` feature `traced!` expands
+//! to the bare operation.
+
+#[cfg(feature = "tracing")]
+use core_foundation::array::CFArray;
+#[cfg(feature = "tracing")]
+use core_foundation::url::CFURL;
+
+/// Run the operation inside a debug span with the given fields, then emit an event with its
+/// result (an OSStatus is reported as `OpenError::Status`) and how long it took
+#[cfg(feature = "tracing")]
+macro_rules! traced {
+    ($name:expr, { $($field:tt)* }, $body:expr) => {{
+        let span = tracing::debug_span!($name, $($field)*);
+        let _entered = span.enter();
+        let start = std::time::Instant::now();
+        let res = $crate::macos::trace::run(|| $body);
+        tracing::debug!(result = ?res, elapsed = ?start.elapsed(), "{} done", $name);
+        res
+    }};
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! traced {
+    ($name:expr, { $($field:tt)* }, $body:expr) => {
+        $crate::macos::trace::run(|| $body)
+    };
+}
+
+/// Run the operation in a closure, so `?` and `return` leave the operation only
+pub(crate) fn run<T, F: FnOnce() -> T>(operation: F) -> T {
+    operation()
+}
+
+/// The urls as strings, for span fields
+#[cfg(feature = "tracing")]
+pub(crate) fn urls(urls: Option<&CFArray<CFURL>>) -> Vec<String> {
+    urls.map(|urls| {
+        urls.iter()
+            .map(|url| url.get_string().to_string())
+            .collect()
+    })
+    .unwrap_or_default()
+}
@@ -0,0 +1,83 @@
+//! Move files to the Trash.
+
+use crate::macos::{remap_multiopenable, MultiOpenable, OpenError, OpenResult};
+
+use core_foundation_sys::base::OSStatus;
+
+use std::ffi::{CStr, CString, OsStr};
+use std::os::raw::c_char;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+const K_FS_FILE_OPERATION_DEFAULT_OPTIONS: u32 = 0;
+
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    fn FSPathMoveObjectToTrashSync(
+        sourcePath: *const c_char,
+        targetPath: *mut *mut c_char,
+        options: u32,
+    ) -> OSStatus;
+}
+
+/// Move the file to the Trash, `None` if the system moved it without telling where
+fn trash_path(path: &Path) -> OpenResult<Option<PathBuf>> {
+    let source = CString::new(path.as_os_str().as_bytes()).map_err(|_| OpenError::InvalidUrl)?;
+    let mut target: *mut c_char = std::ptr::null_mut();
+
+    let code = unsafe {
+        FSPathMoveObjectToTrashSync(
+            source.as_ptr(),
+            &mut target,
+            K_FS_FILE_OPERATION_DEFAULT_OPTIONS,
+        )
+    };
+
+    if code != 0 {
+        return Err(OpenError::Status(code));
+    }
+
+    if target.is_null() {
+        return Ok(None);
+    }
+    let res = PathBuf::from(OsStr::from_bytes(
+        unsafe { CStr::from_ptr(target) }.to_bytes(),
+    ));
+    unsafe { libc::free(target as *mut libc::c_void) };
+    Ok(Some(res))
+}
+
+/// Move the given files to the Trash, returning their new locations in the same order, `None`
+/// for the files the system didn't tell where it moved. Every path is checked before trashing
+/// anything, but if the move fails midway the previous files are already in the Trash.
+pub fn trash<T: MultiOpenable + ?Sized>(paths: &T) -> OpenResult<Vec<Option<PathBuf>>> {
+    let urls = remap_multiopenable(Some(paths))?.ok_or(OpenError::InvalidUrls)?;
+    let paths = urls
+        .iter()
+        .map(|url| url.to_path())
+        .collect::<Option<Vec<_>>>()
+        .ok_or(OpenError::InvalidUrls)?;
+
+    paths.iter().map(|path| trash_path(path)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::File;
+
+    #[test]
+    fn test_trash() {
+        let path =
+            std::env::temp_dir().join(format!("macos-open-trash-{}.txt", std::process::id()));
+        File::create(&path).unwrap();
+
+        let trashed = trash(&path).unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert!(!path.exists());
+        let trashed = trashed[0].as_ref().unwrap();
+        assert!(trashed.exists());
+        std::fs::remove_file(trashed).unwrap();
+    }
+}
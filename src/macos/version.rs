@@ -0,0 +1,137 @@
+//! App and system versions, to refuse copies older than a given release.
+
+use crate::macos::bundle::short_version;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Split a version string in its numeric components, non numeric suffixes are ignored
+fn version_components(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| c == '.' || c == '-' || c == ' ')
+        .map(|part| {
+            part.chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+        })
+        .take_while(|part| !part.is_empty())
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+/// A dotted version like the CFBundleShortVersionString of apps (e.g. `13.1`). Non numeric
+/// suffixes are ignored and trailing zeros aren't significant, so `2` equals `2.0.0`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Version {
+    components: Vec<u64>,
+}
+
+impl Version {
+    /// Parse a version, `None` if it doesn't start with a number
+    pub fn parse(version: &str) -> Option<Version> {
+        let mut components = version_components(version.trim());
+        if components.is_empty() {
+            return None;
+        }
+        while components.len() > 1 && components.last() == Some(&0) {
+            components.pop();
+        }
+        Some(Version { components })
+    }
+
+    /// The numeric components, without the trailing zeros
+    pub fn components(&self) -> &[u64] {
+        &self.components
+    }
+
+    /// The first component, e.g. `13` for macOS 13.1
+    pub fn major(&self) -> u64 {
+        self.components[0]
+    }
+
+    /// The version of the app bundle at the given path
+    pub fn of_app(app: &Path) -> Option<Version> {
+        Version::parse(&short_version(app)?)
+    }
+}
+
+impl FromStr for Version {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Version, ()> {
+        Version::parse(value).ok_or(())
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, component) in self.components.iter().enumerate() {
+            if i != 0 {
+                f.write_str(".")?;
+            }
+            write!(f, "{}", component)?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering {
+        let len = self.components.len().max(other.components.len());
+        (0..len)
+            .map(|i| {
+                let a = self.components.get(i).unwrap_or(&0);
+                let b = other.components.get(i).unwrap_or(&0);
+                a.cmp(b)
+            })
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// If the app is at least the given version, apps without a readable version never are
+pub(crate) fn is_at_least(app: &Path, min_version: Option<&Version>) -> bool {
+    match min_version {
+        Some(min_version) => {
+            matches!(Version::of_app(app), Some(ref version) if version >= min_version)
+        }
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version() {
+        let version = Version::parse("13.1.0 (15608)").unwrap();
+        assert_eq!(version.components(), &[13, 1]);
+        assert_eq!(version.to_string(), "13.1");
+        assert_eq!(Version::parse("2"), Version::parse("2.0.0"));
+        assert!(Version::parse("1.10").unwrap() > Version::parse("1.9.9").unwrap());
+        assert!(Version::parse("0.9").unwrap() < "1".parse().unwrap());
+        assert_eq!(Version::parse("beta"), None);
+        assert_eq!(Version::parse("0").unwrap().components(), &[0]);
+        assert!(Version::parse("77.0.3865.90").unwrap() < Version::parse("78.0").unwrap());
+        assert_eq!(Version::parse("13.0 (15608.1.19)"), Version::parse("13"));
+        assert_eq!(Version::parse("10.15.7").unwrap().major(), 10);
+    }
+
+    #[test]
+    fn test_is_at_least() {
+        let safari = Path::new("/Applications/Safari.app");
+        assert!(is_at_least(safari, None));
+        assert!(is_at_least(safari, Version::parse("1").as_ref()));
+        assert!(!is_at_least(safari, Version::parse("999").as_ref()));
+    }
+}
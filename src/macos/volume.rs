@@ -0,0 +1,191 @@
+//! Mounted volumes and their ejection through DiskArbitration, to clean up after opening disk
+//! images.
+
+use crate::macos::ns::{array_iter, to_path, AutoreleasePool, Id};
+use crate::macos::{OpenError, OpenResult};
+
+use core_foundation::base::{CFAllocatorRef, CFTypeRef, TCFType};
+use core_foundation::url::{CFURLRef, CFURL};
+use core_foundation_sys::base::{kCFAllocatorDefault, CFRelease};
+use libc::{c_char, c_void};
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::time::Duration;
+
+type DASessionRef = CFTypeRef;
+type DADiskRef = CFTypeRef;
+type DADissenterRef = CFTypeRef;
+type DAReturn = i32;
+type DADiskCallback = extern "C" fn(DADiskRef, DADissenterRef, *mut c_void);
+
+const K_DA_DISK_UNMOUNT_OPTION_WHOLE: u32 = 0x0000_0001;
+const K_DA_DISK_EJECT_OPTION_DEFAULT: u32 = 0x0000_0000;
+/// kDAReturnBusy, an app holds files open on the volume
+const K_DA_RETURN_BUSY: DAReturn = 0xF8DA_0002_u32 as DAReturn;
+const NS_VOLUME_ENUMERATION_SKIP_HIDDEN_VOLUMES: usize = 1 << 1;
+
+/// How long to wait for the volume to be unmounted or ejected, apps may hold it busy
+const EJECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[link(name = "DiskArbitration", kind = "framework")]
+extern "C" {
+    fn DASessionCreate(allocator: CFAllocatorRef) -> DASessionRef;
+    fn DASessionSetDispatchQueue(session: DASessionRef, queue: *mut c_void);
+    fn DADiskCreateFromVolumePath(
+        allocator: CFAllocatorRef,
+        session: DASessionRef,
+        path: CFURLRef,
+    ) -> DADiskRef;
+    fn DADiskCopyWholeDisk(disk: DADiskRef) -> DADiskRef;
+    fn DADiskUnmount(disk: DADiskRef, options: u32, callback: DADiskCallback, context: *mut c_void);
+    fn DADiskEject(disk: DADiskRef, options: u32, callback: DADiskCallback, context: *mut c_void);
+    fn DADissenterGetStatus(dissenter: DADissenterRef) -> DAReturn;
+}
+
+extern "C" {
+    fn dispatch_queue_create(label: *const c_char, attr: *const c_void) -> *mut c_void;
+    fn dispatch_release(object: *mut c_void);
+}
+
+/// A retained DiskArbitration object, released when dropped
+struct Owned(CFTypeRef);
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { CFRelease(self.0) };
+        }
+    }
+}
+
+/// Report the outcome of an unmount or eject to the waiting thread
+extern "C" fn done(_disk: DADiskRef, dissenter: DADissenterRef, context: *mut c_void) {
+    let status = if dissenter.is_null() {
+        0
+    } else {
+        unsafe { DADissenterGetStatus(dissenter) }
+    };
+    let tx = unsafe { &*(context as *const SyncSender<DAReturn>) };
+    let _ = tx.send(status);
+}
+
+/// Run an asynchronous DiskArbitration request on the volume and wait for its callback
+fn wait_for<F: FnOnce(*mut c_void)>(volume_path: &Path, request: F) -> OpenResult<()> {
+    let (tx, rx) = sync_channel::<DAReturn>(1);
+    let tx = Box::into_raw(Box::new(tx));
+    request(tx as *mut c_void);
+    match rx.recv_timeout(EJECT_TIMEOUT) {
+        Ok(status) => {
+            drop(unsafe { Box::from_raw(tx) });
+            match status {
+                0 => Ok(()),
+                K_DA_RETURN_BUSY => Err(OpenError::Busy(volume_path.display().to_string())),
+                status => Err(OpenError::Status(status)),
+            }
+        }
+        // the callback may still run later, the sender is leaked on purpose
+        Err(_) => Err(OpenError::TimedOut),
+    }
+}
+
+/// Get the paths of the mounted volumes shown in Finder
+pub fn mounted_volumes() -> Vec<PathBuf> {
+    let _pool = AutoreleasePool::new();
+    let manager: Id = unsafe { msg_send![class!(NSFileManager), defaultManager] };
+    let volumes: Id = unsafe {
+        msg_send![
+            manager,
+            mountedVolumeURLsIncludingResourceValuesForKeys: std::ptr::null_mut::<c_void>()
+            options: NS_VOLUME_ENUMERATION_SKIP_HIDDEN_VOLUMES
+        ]
+    };
+    array_iter(volumes).filter_map(to_path).collect()
+}
+
+/// Unmount the volume mounted at the given path and eject its disk (e.g. detach the disk
+/// image), like the eject button in Finder. Fails with `OpenError::Busy` when an app holds
+/// the volume open and `OpenError::TimedOut` when DiskArbitration doesn't answer in 30s.
+pub fn eject(volume_path: &Path) -> OpenResult<()> {
+    let url = CFURL::from_path(volume_path, true).ok_or(OpenError::InvalidUrl)?;
+
+    let session = Owned(unsafe { DASessionCreate(kCFAllocatorDefault) });
+    if session.0.is_null() {
+        return Err(OpenError::Unsupported("DiskArbitration is not available"));
+    }
+    let queue = unsafe { dispatch_queue_create(std::ptr::null(), std::ptr::null()) };
+    unsafe { DASessionSetDispatchQueue(session.0, queue) };
+
+    let res = (|| {
+        let disk = Owned(unsafe {
+            DADiskCreateFromVolumePath(kCFAllocatorDefault, session.0, url.as_concrete_TypeRef())
+        });
+        if disk.0.is_null() {
+            return Err(OpenError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                volume_path.display().to_string(),
+            )));
+        }
+
+        wait_for(volume_path, |context| unsafe {
+            DADiskUnmount(disk.0, K_DA_DISK_UNMOUNT_OPTION_WHOLE, done, context)
+        })?;
+
+        // network volumes have no disk to eject
+        let whole = Owned(unsafe { DADiskCopyWholeDisk(disk.0) });
+        if whole.0.is_null() {
+            return Ok(());
+        }
+        wait_for(volume_path, |context| unsafe {
+            DADiskEject(whole.0, K_DA_DISK_EJECT_OPTION_DEFAULT, done, context)
+        })
+    })();
+
+    unsafe {
+        DASessionSetDispatchQueue(session.0, std::ptr::null_mut());
+        dispatch_release(queue);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mounted_volumes() {
+        assert!(mounted_volumes().contains(&PathBuf::from("/")));
+    }
+
+    #[test]
+    fn test_eject() {
+        use std::process::Command;
+
+        let dir = std::env::temp_dir().join(format!("macos-open-eject-{}", std::process::id()));
+        let image = dir.join("test.dmg");
+        let mount_point = dir.join("mnt");
+        std::fs::create_dir_all(&mount_point).unwrap();
+        let created = Command::new("hdiutil")
+            .args(["create", "-quiet", "-size", "1m"])
+            .args(["-fs", "HFS+", "-volname", "EjectTest"])
+            .arg(&image)
+            .status()
+            .unwrap();
+        assert!(created.success());
+        let attached = Command::new("hdiutil")
+            .args(["attach", "-quiet", "-mountpoint"])
+            .arg(&mount_point)
+            .arg(&image)
+            .status()
+            .unwrap();
+        assert!(attached.success());
+        assert!(mounted_volumes().contains(&mount_point));
+
+        let res = eject(&mount_point);
+        let still_mounted = mounted_volumes().contains(&mount_point);
+        std::fs::remove_dir_all(&dir).unwrap();
+        res.unwrap();
+        assert!(!still_mounted);
+    }
+}
@@ -0,0 +1,83 @@
+//! Background threads polling the system for changes, shared by the watchers.
+
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// A thread polling at a fixed interval until it's stopped or dropped
+pub(crate) struct Poller {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Poller {
+    /// Call `start` on a new thread, then the poll it returns at every interval. The state to
+    /// compare with is built by `start`, so it doesn't need to be `Send`.
+    pub(crate) fn spawn<S, P>(interval: Duration, start: S) -> Poller
+    where
+        S: FnOnce() -> P + Send + 'static,
+        P: FnMut(),
+    {
+        let (stop, stopped) = channel::<()>();
+        let thread = thread::spawn(move || {
+            let mut poll = start();
+            // stopping drops the sender, waking the thread right away
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                poll();
+            }
+        });
+
+        Poller {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// Stop polling and wait for the thread to finish
+    pub(crate) fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    #[test]
+    fn test_poller() {
+        let polls = Arc::new(AtomicUsize::new(0));
+        let counter = polls.clone();
+        let poller = Poller::spawn(Duration::from_millis(10), move || {
+            move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        thread::sleep(Duration::from_millis(100));
+        assert!(polls.load(Ordering::SeqCst) > 0);
+
+        let start = Instant::now();
+        drop(Poller::spawn(Duration::from_secs(60), || || ()));
+        poller.stop();
+        let stopped = polls.load(Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(polls.load(Ordering::SeqCst), stopped);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
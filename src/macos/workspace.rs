@@ -0,0 +1,153 @@
+//! NSWorkspace backend, based on `NSWorkspaceOpenConfiguration` (macOS 10.15 and later).
+
+use crate::macos::ns::{shared_workspace, to_path, to_string, AutoreleasePool, Id};
+use crate::macos::{Backend, LaunchedApp, OpenError, OpenOptions, OpenResult};
+
+use block::ConcreteBlock;
+use core_foundation::array::CFArray;
+use core_foundation::base::TCFType;
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::string::CFString;
+use core_foundation::url::CFURL;
+use launch_services::LSLaunchFlags;
+use libc::pid_t;
+use objc::runtime::{BOOL, NO, YES};
+
+use std::sync::mpsc::channel;
+
+/// Check if the running system has `NSWorkspaceOpenConfiguration`
+pub(crate) fn available() -> bool {
+    let _pool = AutoreleasePool::new();
+    let responds: BOOL = unsafe {
+        msg_send![
+            shared_workspace(),
+            respondsToSelector: sel!(openURLs:withApplicationAtURL:configuration:completionHandler:)
+        ]
+    };
+    responds != NO
+}
+
+fn to_bool(value: bool) -> BOOL {
+    if value {
+        YES
+    } else {
+        NO
+    }
+}
+
+/// Must be called inside an autorelease pool
+unsafe fn configuration(options: &OpenOptions, flags: LSLaunchFlags) -> Id {
+    let config: Id = msg_send![class!(NSWorkspaceOpenConfiguration), configuration];
+
+    let _: () =
+        msg_send![config, setActivates: to_bool(!flags.contains(LSLaunchFlags::DONT_SWITCH))];
+    let _: () = msg_send![config, setHides: to_bool(flags.contains(LSLaunchFlags::AND_HIDE))];
+    let _: () =
+        msg_send![config, setHidesOthers: to_bool(flags.contains(LSLaunchFlags::AND_HIDE_OTHERS))];
+    let recents = to_bool(!flags.contains(LSLaunchFlags::DONT_ADD_TO_RECENTS));
+    let _: () = msg_send![config, setAddsToRecentItems: recents];
+    let prompts = to_bool(flags.contains(LSLaunchFlags::AND_DISPLAY_ERRORS));
+    let _: () = msg_send![config, setPromptsUserIfNeeded: prompts];
+    let new_instance = to_bool(flags.contains(LSLaunchFlags::NEW_INSTANCE));
+    let _: () = msg_send![config, setCreatesNewApplicationInstance: new_instance];
+
+    if let Some(arch) = options.arch {
+        let _: () = msg_send![config, setArchitecture: arch.cpu_type()];
+    }
+
+    let args = options.arguments();
+    if !args.is_empty() {
+        let args = args
+            .iter()
+            .map(|arg| CFString::new(arg))
+            .collect::<Vec<_>>();
+        let args = CFArray::<CFString>::from_CFTypes(&args[..]);
+        let _: () = msg_send![config, setArguments: args.as_concrete_TypeRef() as Id];
+    }
+
+    if !options.env.is_empty() {
+        let env = options
+            .env
+            .iter()
+            .map(|(key, value)| (CFString::new(key), CFString::new(value)))
+            .collect::<Vec<_>>();
+        let env = CFDictionary::from_CFType_pairs(&env[..]);
+        let _: () = msg_send![config, setEnvironment: env.as_concrete_TypeRef() as Id];
+    }
+
+    config
+}
+
+/// Open the urls (or launch the app when there are no urls) and wait for the system to report
+/// the outcome. Without an app exactly one url must be given.
+pub(crate) fn open(
+    options: &OpenOptions,
+    app: Option<CFURL>,
+    urls: Option<CFArray<CFURL>>,
+) -> OpenResult<LaunchedApp> {
+    let flags = options.launch_flags()?;
+    let _pool = AutoreleasePool::new();
+    let (tx, rx) = channel::<OpenResult<LaunchedApp>>();
+
+    let handler = ConcreteBlock::new(move |running: Id, error: Id| {
+        let res = if error.is_null() {
+            let (path, pid) = unsafe {
+                let bundle_url: Id = msg_send![running, bundleURL];
+                let pid: pid_t = msg_send![running, processIdentifier];
+                (to_path(bundle_url), pid)
+            };
+            Ok(LaunchedApp::new(path, Some(pid)).with_backend(Backend::Workspace))
+        } else {
+            let (code, message) = unsafe {
+                let code: isize = msg_send![error, code];
+                let message: Id = msg_send![error, localizedDescription];
+                (code as i64, to_string(message).unwrap_or_default())
+            };
+            Err(OpenError::Workspace { code, message })
+        };
+        let _ = tx.send(res);
+    })
+    .copy();
+
+    unsafe {
+        let workspace = shared_workspace();
+        let config = configuration(options, flags);
+
+        match (app, urls) {
+            (Some(app), Some(urls)) => {
+                let _: () = msg_send![
+                    workspace,
+                    openURLs: urls.as_concrete_TypeRef() as Id
+                    withApplicationAtURL: app.as_concrete_TypeRef() as Id
+                    configuration: config
+                    completionHandler: &*handler
+                ];
+            }
+            (Some(app), None) => {
+                let _: () = msg_send![
+                    workspace,
+                    openApplicationAtURL: app.as_concrete_TypeRef() as Id
+                    configuration: config
+                    completionHandler: &*handler
+                ];
+            }
+            (None, Some(urls)) if urls.len() == 1 => {
+                let url = urls.get(0).ok_or(OpenError::InvalidUrls)?;
+                let _: () = msg_send![
+                    workspace,
+                    openURL: url.as_concrete_TypeRef() as Id
+                    configuration: config
+                    completionHandler: &*handler
+                ];
+            }
+            _ => return Err(OpenError::InvalidUrls),
+        }
+    }
+
+    rx.recv().unwrap_or_else(|_| {
+        Err(OpenError::Workspace {
+            code: 0,
+            message: "NSWorkspace dropped the completion handler".to_string(),
+        })
+    })
+}
@@ -0,0 +1,380 @@
+//! x-callback-url round trips: build the request, open it and wait for the target app to call
+//! back through a scheme handled by the caller.
+
+use crate::macos::mailto::encode;
+use crate::macos::{open_default, OpenError, OpenResult, OpenTarget};
+
+use libc::{c_int, c_void};
+use url::Url;
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+const X_CALLBACK_HOST: &str = "x-callback-url";
+
+#[link(name = "Security", kind = "framework")]
+extern "C" {
+    fn SecRandomCopyBytes(rnd: *const c_void, count: usize, bytes: *mut u8) -> c_int;
+}
+
+/// An unguessable token identifying a request in its callbacks, so other apps and web pages
+/// can't resolve it
+fn token() -> OpenResult<String> {
+    let mut bytes = [0u8; 16];
+    let status = unsafe { SecRandomCopyBytes(std::ptr::null(), bytes.len(), bytes.as_mut_ptr()) };
+    if status != 0 {
+        return Err(OpenError::Status(status));
+    }
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// A value percent encoded but for the unreserved characters, as query values require
+struct Encoded<'a>(&'a str);
+
+impl fmt::Display for Encoded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        encode(self.0, b"", f)
+    }
+}
+
+/// An x-callback-url request, e.g. `things://x-callback-url/add?title=...`
+///
+/// ```no_run
+/// use macos_open::{XCallback, XCallbackRouter};
+///
+/// // the app registers `myapp` and passes the urls it receives to `router.handle`
+/// let router = XCallbackRouter::new("myapp");
+/// let response = router
+///     .open(XCallback::new("things", "add").param("title", "Buy milk"))
+///     .unwrap()
+///     .wait(None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XCallback {
+    scheme: String,
+    action: String,
+    params: Vec<(String, String)>,
+    source: Option<String>,
+}
+
+impl XCallback {
+    /// Create a request of the action of the app handling the scheme
+    pub fn new<S: Into<String>, A: Into<String>>(scheme: S, action: A) -> XCallback {
+        XCallback {
+            scheme: scheme.into(),
+            action: action.into(),
+            params: Vec::new(),
+            source: None,
+        }
+    }
+
+    /// Add a parameter of the action
+    pub fn param<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> &mut XCallback {
+        self.params.push((key.into(), value.into()));
+        self
+    }
+
+    /// Set the name of the calling app (`x-source`), some apps show it to the user
+    pub fn source<S: Into<String>>(&mut self, source: S) -> &mut XCallback {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// The url of the request without callbacks
+    pub fn url(&self) -> String {
+        self.url_with(&[])
+    }
+
+    fn url_with(&self, callbacks: &[(&str, String)]) -> String {
+        let mut url = format!(
+            "{}://{}/{}",
+            self.scheme,
+            X_CALLBACK_HOST,
+            Encoded(&self.action)
+        );
+        let source = self
+            .source
+            .iter()
+            .map(|source| ("x-source", source.as_str()));
+        let params = self
+            .params
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .chain(source)
+            .chain(callbacks.iter().map(|(key, value)| (*key, value.as_str())));
+        for (index, (key, value)) in params.enumerate() {
+            let separator = if index == 0 { '?' } else { '&' };
+            write!(url, "{}{}={}", separator, Encoded(key), Encoded(value)).unwrap();
+        }
+        url
+    }
+}
+
+/// How the target app called back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XCallbackResponse {
+    /// The action succeeded (`x-success`), with the parameters returned by the app
+    Success(Vec<(String, String)>),
+    /// The action failed (`x-error`)
+    Error {
+        /// The `errorCode` parameter
+        code: Option<String>,
+        /// The `errorMessage` parameter
+        message: Option<String>,
+        /// All the parameters returned by the app
+        params: Vec<(String, String)>,
+    },
+    /// The user cancelled the action (`x-cancel`)
+    Cancel,
+}
+
+impl XCallbackResponse {
+    fn parse(kind: &str, params: Vec<(String, String)>) -> Option<XCallbackResponse> {
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.clone())
+        };
+        match kind {
+            "success" => Some(XCallbackResponse::Success(params)),
+            "error" => Some(XCallbackResponse::Error {
+                code: param("errorCode"),
+                message: param("errorMessage"),
+                params,
+            }),
+            "cancel" => Some(XCallbackResponse::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// The state of a request waiting for its callback
+#[derive(Debug, Default)]
+struct Pending {
+    response: Option<XCallbackResponse>,
+    waker: Option<Waker>,
+}
+
+type Shared = Arc<(Mutex<Pending>, Condvar)>;
+type PendingMap = Arc<Mutex<HashMap<String, Shared>>>;
+
+/// Open x-callback-url requests and route the callbacks back to them. The router doesn't
+/// receive urls by itself: the app must handle `callback_scheme` and pass the urls it receives
+/// to `handle`. Every request gets a random token in its callback urls, the callbacks without
+/// the token of a pending request are ignored.
+#[derive(Debug)]
+pub struct XCallbackRouter {
+    scheme: String,
+    pending: PendingMap,
+}
+
+impl XCallbackRouter {
+    /// Create a router whose callbacks use the given scheme, case insensitive
+    pub fn new<S: Into<String>>(callback_scheme: S) -> XCallbackRouter {
+        XCallbackRouter {
+            scheme: callback_scheme.into().to_ascii_lowercase(),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn callback(&self, token: &str, kind: &str) -> String {
+        format!("{}://{}/{}/{}", self.scheme, X_CALLBACK_HOST, token, kind)
+    }
+
+    /// The url of the request, with the callbacks of this router
+    fn request_url(&self, request: &XCallback, token: &str) -> String {
+        request.url_with(&[
+            ("x-success", self.callback(token, "success")),
+            ("x-error", self.callback(token, "error")),
+            ("x-cancel", self.callback(token, "cancel")),
+        ])
+    }
+
+    /// Open the request with its default handler, returning the future of its callback
+    pub fn open(&self, request: &XCallback) -> OpenResult<XCallbackFuture> {
+        let token = token()?;
+        let url = OpenTarget::Url(&self.request_url(request, &token)).to_url(false)?;
+
+        let shared: Shared = Arc::new((Mutex::new(Pending::default()), Condvar::new()));
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(token.clone(), shared.clone());
+        let future = XCallbackFuture {
+            token,
+            shared,
+            pending: self.pending.clone(),
+        };
+
+        open_default(&url)?;
+        Ok(future)
+    }
+
+    /// Resolve the request the callback url belongs to. Returns false if the url isn't a
+    /// callback of this router, or its request was already resolved or dropped.
+    pub fn handle(&self, url: &str) -> bool {
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(_) => return false,
+        };
+        if !url.scheme().eq_ignore_ascii_case(&self.scheme)
+            || url.host_str() != Some(X_CALLBACK_HOST)
+        {
+            return false;
+        }
+        let mut segments = match url.path_segments() {
+            Some(segments) => segments,
+            None => return false,
+        };
+        let token = match segments.next() {
+            Some(token) => token.to_string(),
+            None => return false,
+        };
+        let params = url.query_pairs().into_owned().collect();
+        let response = match segments
+            .next()
+            .and_then(|kind| XCallbackResponse::parse(kind, params))
+        {
+            Some(response) => response,
+            None => return false,
+        };
+
+        let shared = match self.pending.lock().unwrap().remove(&token) {
+            Some(shared) => shared,
+            None => return false,
+        };
+        let (ref state, ref condvar) = *shared;
+        let mut state = state.lock().unwrap();
+        state.response = Some(response);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        condvar.notify_all();
+        true
+    }
+}
+
+/// The callback of an x-callback-url request, resolved by `XCallbackRouter::handle`
+#[derive(Debug)]
+pub struct XCallbackFuture {
+    token: String,
+    shared: Shared,
+    pending: PendingMap,
+}
+
+impl XCallbackFuture {
+    /// Block until the target app calls back, at most for the given time
+    pub fn wait(&self, timeout: Option<Duration>) -> Option<XCallbackResponse> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let (ref state, ref condvar) = *self.shared;
+        let mut state = state.lock().unwrap();
+        loop {
+            if let Some(ref response) = state.response {
+                return Some(response.clone());
+            }
+            state = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return None;
+                    }
+                    condvar.wait_timeout(state, deadline - now).unwrap().0
+                }
+                None => condvar.wait(state).unwrap(),
+            };
+        }
+    }
+}
+
+impl Future for XCallbackFuture {
+    type Output = XCallbackResponse;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<XCallbackResponse> {
+        let mut state = self.shared.0.lock().unwrap();
+        match state.response {
+            Some(ref response) => Poll::Ready(response.clone()),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for XCallbackFuture {
+    fn drop(&mut self) {
+        // callbacks of dropped requests are ignored
+        self.pending.lock().unwrap().remove(&self.token);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xcallback_url() {
+        let mut request = XCallback::new("things", "add");
+        request.param("title", "Buy milk & eggs").source("My App");
+        assert_eq!(
+            request.url(),
+            "things://x-callback-url/add?title=Buy%20milk%20%26%20eggs&x-source=My%20App"
+        );
+
+        let router = XCallbackRouter::new("myapp");
+        assert_eq!(
+            router.request_url(&XCallback::new("bear", "search"), "f00d"),
+            "bear://x-callback-url/search\
+             ?x-success=myapp%3A%2F%2Fx-callback-url%2Ff00d%2Fsuccess\
+             &x-error=myapp%3A%2F%2Fx-callback-url%2Ff00d%2Ferror\
+             &x-cancel=myapp%3A%2F%2Fx-callback-url%2Ff00d%2Fcancel"
+        );
+
+        let (first, second) = (token().unwrap(), token().unwrap());
+        assert_eq!(first.len(), 32);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_xcallback_router_handle() {
+        let router = XCallbackRouter::new("MyApp");
+        let token = "0123456789abcdef0123456789abcdef";
+        let shared: Shared = Arc::new((Mutex::new(Pending::default()), Condvar::new()));
+        router
+            .pending
+            .lock()
+            .unwrap()
+            .insert(token.to_string(), shared.clone());
+        let future = XCallbackFuture {
+            token: token.to_string(),
+            shared,
+            pending: router.pending.clone(),
+        };
+
+        assert!(!router.handle(&format!("other://x-callback-url/{}/success", token)));
+        // a forged callback can't guess the token
+        assert!(!router.handle("myapp://x-callback-url/0/success"));
+        assert!(router.handle(&format!(
+            "myapp://x-callback-url/{}/error?errorCode=2&errorMessage=Not%20found",
+            token
+        )));
+        assert!(!router.handle(&format!("myapp://x-callback-url/{}/success", token)));
+        assert_eq!(
+            future.wait(Some(Duration::from_secs(0))),
+            Some(XCallbackResponse::Error {
+                code: Some("2".to_string()),
+                message: Some("Not found".to_string()),
+                params: vec![
+                    ("errorCode".to_string(), "2".to_string()),
+                    ("errorMessage".to_string(), "Not found".to_string()),
+                ],
+            })
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! The policy of the global opener applies to every entry point. It has its own test binary
+//! because the global opener can only be set before anything uses it.
+#![cfg(target_os = "macos")]
+
+use macos_open::{
+    focus_mode_open, open_and_wait, open_in_default_browser, OpenError, OpenOptions, OpenPolicy,
+    Opener,
+};
+
+#[test]
+fn test_global_policy() {
+    let mut policy = OpenPolicy::new();
+    policy.deny("x-macos-open-denied");
+    Opener::set_global(Opener::with_policy(OpenOptions::new(), None, policy)).unwrap();
+
+    let url = "x-macos-open-denied:test";
+    let results = vec![
+        open_and_wait(url),
+        focus_mode_open(url).map(|_| ()),
+        open_in_default_browser(url).map(|_| ()),
+    ];
+    for result in results {
+        match result {
+            Err(OpenError::Denied(denied)) => assert_eq!(denied, url),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}